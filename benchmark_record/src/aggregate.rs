@@ -0,0 +1,118 @@
+use crate::OutputValue;
+use crate::stats::Summary;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Combine the outputs recorded on each PE (indexed by PE id) into one entry per output key.
+///
+/// Every entry holds a `per_pe` array (with `null` for PEs that did not record the key).
+/// If all recorded values are numeric, the entry also holds the min/max/mean/stddev over the PEs
+/// and an `imbalance` factor (max / mean).
+pub fn summarize_pe_outputs(
+    per_pe: &[HashMap<String, OutputValue>],
+) -> BTreeMap<String, OutputValue> {
+    let keys = per_pe
+        .iter()
+        .flat_map(|outputs| outputs.keys().cloned())
+        .collect::<BTreeSet<String>>();
+
+    let mut summary = BTreeMap::new();
+    for key in keys {
+        let values = per_pe
+            .iter()
+            .map(|outputs| outputs.get(&key).cloned().unwrap_or(OutputValue::Null))
+            .collect::<Vec<OutputValue>>();
+        let present = values
+            .iter()
+            .filter(|v| **v != OutputValue::Null)
+            .collect::<Vec<_>>();
+        let samples = present
+            .iter()
+            .filter_map(|v| v.as_f64())
+            .collect::<Vec<f64>>();
+
+        let mut entry = match Summary::from_samples(&samples) {
+            Some(stats) if samples.len() == present.len() => {
+                let mut entry = match stats.to_output() {
+                    OutputValue::Object(fields) => fields,
+                    _ => BTreeMap::new(),
+                };
+                entry.insert("imbalance".to_string(), stats.imbalance().into());
+                entry
+            }
+            _ => BTreeMap::new(),
+        };
+        entry.insert("per_pe".to_string(), OutputValue::Array(values));
+        summary.insert(key, OutputValue::Object(entry));
+    }
+    summary
+}
+
+#[cfg(feature = "lamellar")]
+mod collective {
+    use crate::{BenchmarkInformation, OutputValue};
+    use lamellar::active_messaging::prelude::*;
+    use lamellar::darc::prelude::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[lamellar::AmData]
+    struct GatherOutputAm {
+        collected: Darc<Mutex<Vec<Option<String>>>>,
+        pe: usize,
+        output: String,
+    }
+
+    #[lamellar::am]
+    impl LamellarAM for GatherOutputAm {
+        async fn exec() {
+            self.collected.lock().unwrap()[self.pe] = Some(self.output.clone());
+        }
+    }
+
+    impl BenchmarkInformation {
+        /// Collective operation: must be called on every PE.
+        ///
+        /// Gathers each PE's outputs onto PE 0 and returns (on PE 0 only) a copy of this record
+        /// with a `pe_summary` section holding the per-PE values and their min/max/mean/stddev.
+        /// All other PEs receive `None`.
+        pub fn aggregate(&self, world: &LamellarWorld) -> Option<BenchmarkInformation> {
+            let my_pe = world.my_pe();
+            let collected = Darc::new(world, Mutex::new(vec![None; world.num_pes()]))
+                .block()
+                .expect("unable to create darc"); // created with the world team so should be valid on all pes
+
+            let output = json::stringify(self.output_json());
+            world.block_on(world.exec_am_pe(
+                0,
+                GatherOutputAm {
+                    collected: collected.clone(),
+                    pe: my_pe,
+                    output,
+                },
+            ));
+            world.barrier(); // at this point PE 0 holds the outputs of every PE
+
+            if my_pe != 0 {
+                return None;
+            }
+            let per_pe = collected
+                .lock()
+                .unwrap()
+                .iter()
+                .map(
+                    |output| match output.as_ref().and_then(|o| json::parse(o).ok()) {
+                        Some(obj) => obj
+                            .entries()
+                            .map(|(k, v)| (k.to_string(), OutputValue::from_json(v)))
+                            .collect::<HashMap<_, _>>(),
+                        None => HashMap::new(),
+                    },
+                )
+                .collect::<Vec<_>>();
+
+            let mut record = self.clone();
+            record.set_pe_summary(&per_pe);
+            Some(record)
+        }
+    }
+}
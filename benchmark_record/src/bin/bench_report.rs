@@ -0,0 +1,220 @@
+//! Summarize benchmark result files as comparison tables.
+//!
+//! usage: bench_report [--format markdown|csv] [--group-by <fields>] [--metric <key>]...
+//!                     [--only <benchmark>]... <result file or directory>...
+//!
+//! Directories are searched recursively for `*_result.jsonl` files. Records are grouped by
+//! `--group-by`, a comma separated list of `name`, `params`, `num_pes`, `git` and `lamellar`
+//! (all of them by default), and every group shows the mean and stddev of the MUPS, MB_per_sec and
+//! global time outputs. `--metric` replaces those columns with the given output keys.
+//!
+//! usage: bench_report compare [--threshold <fraction>] [--alpha <p>] [--metric <key>]...
+//!                     [--metric-higher <key>]... [--only <benchmark>]...
+//!                     [--baseline-git <hash prefix>] [--candidate-git <hash prefix>]
+//!                     --baseline <path>... --candidate <path>...
+//!
+//! Matches the configurations (benchmark, parameters, num_pes) present in both sets and compares
+//! the global time (using every repeated trial), MUPS and MB_per_sec, or the given metrics
+//! (`--metric` for lower is better, `--metric-higher` for higher is better). A metric whose median
+//! got worse by more than `--threshold` (default 0.05) with a Mann-Whitney U p-value below
+//! `--alpha` (default 0.05) is a regression, and the exit code is 1 if there is any.
+//! The git filters select records by commit, e.g. when both sets live in the same directory.
+
+use benchmark_record::compare::{self, CompareOptions, ComparedMetric, Direction};
+use benchmark_record::report::{GroupField, Metric, Report};
+use benchmark_record::results::{Record, read_path};
+use std::path::PathBuf;
+use std::process::exit;
+
+enum Format {
+    Markdown,
+    Csv,
+}
+
+struct Args {
+    format: Format,
+    fields: Vec<GroupField>,
+    metrics: Vec<Metric>,
+    only: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+struct CompareArgs {
+    options: CompareOptions,
+    metrics: Vec<ComparedMetric>,
+    only: Vec<String>,
+    baseline: Vec<PathBuf>,
+    candidate: Vec<PathBuf>,
+    baseline_git: Option<String>,
+    candidate_git: Option<String>,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: bench_report [--format markdown|csv] [--group-by name,params,num_pes,git,lamellar] [--metric <key>]... [--only <benchmark>]... <result file or directory>..."
+    );
+    eprintln!(
+        "       bench_report compare [--threshold <fraction>] [--alpha <p>] [--metric <key>]... [--metric-higher <key>]... [--only <benchmark>]... [--baseline-git <hash>] [--candidate-git <hash>] --baseline <path>... --candidate <path>..."
+    );
+    exit(2);
+}
+
+fn parse_args(mut iter: impl Iterator<Item = String>) -> Args {
+    let mut args = Args {
+        format: Format::Markdown,
+        fields: GroupField::ALL.to_vec(),
+        metrics: Vec::new(),
+        only: Vec::new(),
+        paths: Vec::new(),
+    };
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--format" => {
+                args.format = match value().as_str() {
+                    "markdown" | "md" => Format::Markdown,
+                    "csv" => Format::Csv,
+                    _ => usage(),
+                }
+            }
+            "--csv" => args.format = Format::Csv,
+            "--group-by" => {
+                args.fields = value()
+                    .split(',')
+                    .filter(|f| !f.is_empty())
+                    .map(|f| {
+                        GroupField::parse(f).unwrap_or_else(|| {
+                            eprintln!("unknown group field {f}");
+                            usage()
+                        })
+                    })
+                    .collect()
+            }
+            "--metric" => {
+                let key = value();
+                args.metrics.push(Metric::new(&key, &[&key]));
+            }
+            "--only" => args.only.push(value()),
+            "--help" | "-h" => usage(),
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option {arg}");
+                usage();
+            }
+            _ => args.paths.push(PathBuf::from(arg)),
+        }
+    }
+    if args.paths.is_empty() {
+        usage();
+    }
+    if args.metrics.is_empty() {
+        args.metrics = Metric::defaults();
+    }
+    args
+}
+
+fn parse_compare_args(mut iter: impl Iterator<Item = String>) -> CompareArgs {
+    let mut args = CompareArgs {
+        options: CompareOptions::default(),
+        metrics: Vec::new(),
+        only: Vec::new(),
+        baseline: Vec::new(),
+        candidate: Vec::new(),
+        baseline_git: None,
+        candidate_git: None,
+    };
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().unwrap_or_else(|| usage());
+        let mut number = || value().parse::<f64>().unwrap_or_else(|_| usage());
+        match arg.as_str() {
+            "--threshold" => args.options.threshold = number(),
+            "--alpha" => args.options.alpha = number(),
+            "--metric" | "--metric-higher" => {
+                let key = value();
+                let direction = match arg.as_str() {
+                    "--metric" => Direction::LowerIsBetter,
+                    _ => Direction::HigherIsBetter,
+                };
+                args.metrics
+                    .push(ComparedMetric::new(Metric::new(&key, &[&key]), direction));
+            }
+            "--only" => args.only.push(value()),
+            "--baseline" => args.baseline.push(PathBuf::from(value())),
+            "--candidate" => args.candidate.push(PathBuf::from(value())),
+            "--baseline-git" => args.baseline_git = Some(value()),
+            "--candidate-git" => args.candidate_git = Some(value()),
+            "--help" | "-h" => usage(),
+            _ => {
+                eprintln!("unknown argument {arg}");
+                usage();
+            }
+        }
+    }
+    if args.baseline.is_empty() || args.candidate.is_empty() {
+        usage();
+    }
+    if args.metrics.is_empty() {
+        args.metrics = ComparedMetric::defaults();
+    }
+    args
+}
+
+/// Read every record below the given paths, keeping the selected benchmarks and commit.
+fn load(paths: &[PathBuf], only: &[String], git: Option<&str>) -> Vec<Record> {
+    let mut records = Vec::new();
+    for path in paths {
+        match read_path(path) {
+            Ok(found) => records.extend(found),
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1);
+            }
+        }
+    }
+    records.retain(|r| {
+        (only.is_empty() || only.contains(&r.name))
+            && git.is_none_or(|git| r.git_hash.as_deref().unwrap_or("").starts_with(git))
+    });
+    if records.is_empty() {
+        eprintln!("no benchmark records found in {paths:?}");
+        exit(1);
+    }
+    records
+}
+
+fn run_compare(args: CompareArgs) {
+    let baseline = load(&args.baseline, &args.only, args.baseline_git.as_deref());
+    let candidate = load(&args.candidate, &args.only, args.candidate_git.as_deref());
+    let comparisons = compare::compare(&baseline, &candidate, &args.metrics, &args.options);
+    if comparisons.is_empty() {
+        eprintln!("no configuration is present in both the baseline and the candidate");
+        exit(1);
+    }
+    print!("{}", compare::to_markdown(&comparisons));
+
+    let regressions = comparisons.iter().filter(|c| c.regression).count();
+    if regressions > 0 {
+        eprintln!(
+            "{regressions} regression(s) above {:.1}% (alpha {})",
+            args.options.threshold * 100.0,
+            args.options.alpha
+        );
+        exit(1);
+    }
+}
+
+fn main() {
+    let mut iter = std::env::args().skip(1).peekable();
+    if iter.peek().map(String::as_str) == Some("compare") {
+        iter.next();
+        run_compare(parse_compare_args(iter));
+        return;
+    }
+    let args = parse_args(iter);
+    let records = load(&args.paths, &args.only, None);
+
+    let report = Report::new(&records, &args.fields, &args.metrics);
+    match args.format {
+        Format::Markdown => print!("{}", report.to_markdown()),
+        Format::Csv => print!("{}", report.to_csv()),
+    }
+}
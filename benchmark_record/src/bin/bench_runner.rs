@@ -0,0 +1,202 @@
+//! Run the benchmark sweeps described by `benchmark_config.json` files.
+//!
+//! usage: bench_runner [run] [--dry-run] [--only <benchmark>]... <benchmark_config.json>...
+//!        bench_runner sbatch [--out-dir <dir>] [--partition <p>] [--account <a>] [--time <t>]
+//!                            [--only <benchmark>]... <benchmark_config.json>...
+//!
+//! `run` (the default) runs every benchmark × lamellar version × slurm setting × parameter set ×
+//! repetition on the local machine (see `Job::local_commands`), from the config's `benchmark_dir`
+//! so the results land in its `benchmarking/` directory. The binaries must already be built with
+//! `cargo build --release`. `--dry-run` prints the plan and the commands without running them.
+//!
+//! `sbatch` writes one sbatch script per slurm setting instead (see `SweepConfig::sbatch_scripts`),
+//! by default into `<benchmark_dir>/sbatch/`, and prints the `sbatch` commands to submit them.
+
+use benchmark_record::sweep::{Job, SbatchOptions, SweepConfig};
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+
+#[derive(PartialEq)]
+enum Mode {
+    Run,
+    Sbatch,
+}
+
+struct Args {
+    mode: Mode,
+    dry_run: bool,
+    only: Vec<String>,
+    out_dir: Option<PathBuf>,
+    sbatch: SbatchOptions,
+    configs: Vec<PathBuf>,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: bench_runner [run] [--dry-run] [--only <benchmark>]... <benchmark_config.json>..."
+    );
+    eprintln!(
+        "       bench_runner sbatch [--out-dir <dir>] [--partition <p>] [--account <a>] [--time <t>] [--only <benchmark>]... <benchmark_config.json>..."
+    );
+    exit(2);
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        mode: Mode::Run,
+        dry_run: false,
+        only: Vec::new(),
+        out_dir: None,
+        sbatch: SbatchOptions::default(),
+        configs: Vec::new(),
+    };
+    let mut iter = std::env::args().skip(1).peekable();
+    match iter.peek().map(String::as_str) {
+        Some("run") => {
+            iter.next();
+        }
+        Some("sbatch") => {
+            iter.next();
+            args.mode = Mode::Sbatch;
+        }
+        _ => {}
+    }
+    let sbatch = args.mode == Mode::Sbatch;
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--dry-run" | "-n" if !sbatch => args.dry_run = true,
+            "--only" => args.only.push(value()),
+            "--out-dir" if sbatch => args.out_dir = Some(PathBuf::from(value())),
+            "--partition" if sbatch => args.sbatch.partition = Some(value()),
+            "--account" if sbatch => args.sbatch.account = Some(value()),
+            "--time" if sbatch => args.sbatch.time = Some(value()),
+            "--help" | "-h" => usage(),
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option {arg}");
+                usage();
+            }
+            _ => args.configs.push(PathBuf::from(arg)),
+        }
+    }
+    if args.configs.is_empty() {
+        usage();
+    }
+    args
+}
+
+/// Run every process of a job concurrently and wait for all of them.
+fn run_job(config: &SweepConfig, job: &Job, job_id: usize) -> Result<(), String> {
+    let binary = config.binary_path(&job.benchmark);
+    if !binary.exists() {
+        return Err(format!(
+            "{} not found, build it with `cargo build --release` in {}",
+            binary.display(),
+            config.benchmark_dir.display()
+        ));
+    }
+    let children = job
+        .local_commands(&binary, &config.benchmark_dir, job_id)
+        .iter()
+        .map(|command| {
+            command
+                .spawn()
+                .map_err(|e| format!("unable to start {}: {e}", binary.display()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut failures = Vec::new();
+    for (pe, child) in children.into_iter().enumerate() {
+        match child.and_then(|mut child| child.wait().map_err(|e| e.to_string())) {
+            Ok(status) if status.success() => {}
+            Ok(status) => failures.push(format!("PE {pe} exited with {status}")),
+            Err(e) => failures.push(format!("PE {pe}: {e}")),
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join(", "))
+    }
+}
+
+fn selected_jobs(args: &Args, config: &SweepConfig) -> Vec<Job> {
+    config
+        .plan()
+        .into_iter()
+        .filter(|job| args.only.is_empty() || args.only.contains(&job.benchmark))
+        .collect()
+}
+
+fn write_sbatch_scripts(args: &Args, configs: &[SweepConfig]) {
+    for config in configs {
+        let out_dir = args
+            .out_dir
+            .clone()
+            .unwrap_or_else(|| config.benchmark_dir.join("sbatch"));
+        if let Err(e) = fs::create_dir_all(&out_dir) {
+            eprintln!("unable to create {}: {e}", out_dir.display());
+            exit(1);
+        }
+        let jobs = selected_jobs(args, config);
+        for script in config.sbatch_scripts(&jobs, &args.sbatch) {
+            let path = out_dir.join(&script.file_name);
+            if let Err(e) = fs::write(&path, &script.contents) {
+                eprintln!("unable to write {}: {e}", path.display());
+                exit(1);
+            }
+            println!("sbatch {}", path.display());
+        }
+    }
+}
+
+fn main() {
+    let args = parse_args();
+
+    let mut configs = Vec::new();
+    for path in &args.configs {
+        match SweepConfig::load(path) {
+            Ok(config) => configs.push(config),
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1);
+            }
+        }
+    }
+
+    if args.mode == Mode::Sbatch {
+        write_sbatch_scripts(&args, &configs);
+        return;
+    }
+
+    let mut job_id = 0;
+    let mut failed = 0;
+    for config in &configs {
+        let jobs = selected_jobs(&args, config);
+        println!(
+            "{}: {} runs in {}",
+            config.group_name,
+            jobs.len(),
+            config.benchmark_dir.display()
+        );
+        for job in &jobs {
+            job_id += 1;
+            println!("[{job_id}] {job}");
+            if args.dry_run {
+                let binary = config.binary_path(&job.benchmark);
+                for command in job.local_commands(&binary, &config.benchmark_dir, job_id) {
+                    println!("    {command}");
+                }
+            } else if let Err(e) = run_job(config, job, job_id) {
+                eprintln!("[{job_id}] failed: {e}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{failed} of {job_id} runs failed");
+        exit(1);
+    }
+}
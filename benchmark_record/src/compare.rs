@@ -0,0 +1,203 @@
+use crate::OutputValue;
+use crate::report::{GroupField, Metric};
+use crate::results::Record;
+use crate::stats::{Summary, mann_whitney_u};
+use std::collections::BTreeMap;
+
+/// Records are matched between the baseline and the candidate on these fields.
+pub const MATCH_FIELDS: [GroupField; 3] =
+    [GroupField::Name, GroupField::Parameters, GroupField::NumPes];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    LowerIsBetter,
+    HigherIsBetter,
+}
+
+/// A metric to compare, and which way is an improvement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComparedMetric {
+    pub metric: Metric,
+    pub direction: Direction,
+    /// Use the per-trial times recorded by `Trials::record` when a record has them,
+    /// instead of the single value of the metric.
+    pub use_trials: bool,
+}
+
+impl ComparedMetric {
+    pub fn new(metric: Metric, direction: Direction) -> ComparedMetric {
+        ComparedMetric {
+            metric,
+            direction,
+            use_trials: false,
+        }
+    }
+
+    /// Global time (from the repeated trials where available), MUPS and MB_per_sec.
+    pub fn defaults() -> Vec<ComparedMetric> {
+        let mut defaults = Metric::defaults().into_iter();
+        let mups = defaults.next().expect("default metrics");
+        let mb_per_sec = defaults.next().expect("default metrics");
+        let time = defaults.next().expect("default metrics");
+        vec![
+            ComparedMetric {
+                use_trials: true,
+                ..ComparedMetric::new(time, Direction::LowerIsBetter)
+            },
+            ComparedMetric::new(mups, Direction::HigherIsBetter),
+            ComparedMetric::new(mb_per_sec, Direction::HigherIsBetter),
+        ]
+    }
+
+    /// The samples a record contributes to this metric.
+    pub fn samples(&self, record: &Record) -> Vec<f64> {
+        if self.use_trials
+            && let Some(OutputValue::Object(trials)) = record.output("trials")
+            && let Some(OutputValue::Array(times)) = trials.get("times (secs)")
+        {
+            return times.iter().filter_map(OutputValue::as_f64).collect();
+        }
+        self.metric.value(record).into_iter().collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompareOptions {
+    /// Relative change of the median (in the "worse" direction) above which a metric may be flagged.
+    pub threshold: f64,
+    /// Significance level of the Mann-Whitney U test.
+    pub alpha: f64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            threshold: 0.05,
+            alpha: 0.05,
+        }
+    }
+}
+
+/// The comparison of one metric for one configuration present in both sets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// Values of `MATCH_FIELDS`.
+    pub key: Vec<String>,
+    pub metric: String,
+    pub baseline: Summary,
+    pub candidate: Summary,
+    /// Relative change of the median, positive when the candidate is worse.
+    pub change: f64,
+    /// Mann-Whitney U p-value, `None` when either side has a single sample.
+    pub p_value: Option<f64>,
+    /// `change` is above the threshold and, when a p-value is available, significant.
+    pub regression: bool,
+}
+
+/// Match the configurations present in both sets and compare every metric they have.
+pub fn compare(
+    baseline: &[Record],
+    candidate: &[Record],
+    metrics: &[ComparedMetric],
+    options: &CompareOptions,
+) -> Vec<Comparison> {
+    let baseline = group(baseline);
+    let candidate = group(candidate);
+
+    let mut comparisons = Vec::new();
+    for (key, base_records) in &baseline {
+        let Some(cand_records) = candidate.get(key) else {
+            continue;
+        };
+        for metric in metrics {
+            let samples = |records: &[&Record]| {
+                records
+                    .iter()
+                    .flat_map(|r| metric.samples(r))
+                    .collect::<Vec<_>>()
+            };
+            let (base_samples, cand_samples) = (samples(base_records), samples(cand_records));
+            let (Some(base), Some(cand)) = (
+                Summary::from_samples(&base_samples),
+                Summary::from_samples(&cand_samples),
+            ) else {
+                continue;
+            };
+
+            let change = if base.median == 0.0 {
+                0.0
+            } else {
+                match metric.direction {
+                    Direction::LowerIsBetter => (cand.median - base.median) / base.median,
+                    Direction::HigherIsBetter => (base.median - cand.median) / base.median,
+                }
+            };
+            let p_value = if base.count > 1 && cand.count > 1 {
+                mann_whitney_u(&base_samples, &cand_samples)
+            } else {
+                None
+            };
+            let regression =
+                change > options.threshold && p_value.is_none_or(|p| p < options.alpha);
+            comparisons.push(Comparison {
+                key: key.clone(),
+                metric: metric.metric.label.clone(),
+                baseline: base,
+                candidate: cand,
+                change,
+                p_value,
+                regression,
+            });
+        }
+    }
+    comparisons
+}
+
+/// Markdown table of the comparisons, regressions are marked in the last column.
+pub fn to_markdown(comparisons: &[Comparison]) -> String {
+    let mut header = MATCH_FIELDS
+        .iter()
+        .map(|f| f.label().to_string())
+        .collect::<Vec<_>>();
+    header.extend(
+        [
+            "metric",
+            "baseline median",
+            "candidate median",
+            "change (+ is worse)",
+            "p-value",
+            "",
+        ]
+        .map(str::to_string),
+    );
+    let mut lines = vec![
+        format!("| {} |", header.join(" | ")),
+        format!("|{}", "---|".repeat(header.len())),
+    ];
+    for c in comparisons {
+        let mut cells = c.key.clone();
+        cells.push(c.metric.clone());
+        cells.push(format!("{:.4} (n={})", c.baseline.median, c.baseline.count));
+        cells.push(format!(
+            "{:.4} (n={})",
+            c.candidate.median, c.candidate.count
+        ));
+        cells.push(format!("{:+.1}%", c.change * 100.0));
+        cells.push(match c.p_value {
+            Some(p) => format!("{p:.4}"),
+            None => "-".to_string(),
+        });
+        cells.push(if c.regression { "REGRESSION" } else { "" }.to_string());
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn group(records: &[Record]) -> BTreeMap<Vec<String>, Vec<&Record>> {
+    let mut groups: BTreeMap<Vec<String>, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        let key = MATCH_FIELDS.iter().map(|f| f.value(record)).collect();
+        groups.entry(key).or_default().push(record);
+    }
+    groups
+}
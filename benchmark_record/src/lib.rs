@@ -1,19 +1,37 @@
 use json::JsonValue;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::ffi::OsStr;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::PathBuf;
 
+pub mod aggregate;
+pub mod compare;
+pub mod report;
+pub mod results;
+pub mod stats;
+pub mod sweep;
+pub mod trial;
+pub mod value;
+pub use stats::Summary;
+pub use trial::{Runner, Trials};
+pub use value::OutputValue;
+
+/// Version of the record layout produced by `as_json`/`write`.
+/// Records written before the version field existed should be treated as version 1.
+pub const SCHEMA_VERSION: u32 = 2;
+
 const CHECK_PACKAGES: [&str; 4] = ["lamellar", "rofi", "rofisys", "lamellar-impl"];
 
+#[derive(Clone)]
 pub struct BenchmarkInformation {
     pub benchark_name: String,
     executable: PathBuf,
     parameters: Vec<String>,
     run_date: String,
-    output: HashMap<String, String>,
+    output: HashMap<String, OutputValue>,
+    pe_summary: BTreeMap<String, OutputValue>,
     build_type: String,
     package_info: HashMap<String, String>,
     git: HashMap<String, String>,
@@ -24,6 +42,12 @@ pub struct BenchmarkInformation {
     rust_compiler: String,
 }
 
+impl Default for BenchmarkInformation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BenchmarkInformation {
     /// Create a new BenchmarkInformation instance with default benchmark name.
     /// This is the suggested way to construct a benchmark information record.
@@ -41,6 +65,7 @@ impl BenchmarkInformation {
             parameters: env::args().skip(1).collect(),
             run_date: BenchmarkInformation::get_run_date(),
             output: HashMap::new(),
+            pe_summary: BTreeMap::new(),
             build_type: BenchmarkInformation::get_build_type(),
             package_info: BenchmarkInformation::get_package_info(),
             git: BenchmarkInformation::get_git_info(),
@@ -53,19 +78,61 @@ impl BenchmarkInformation {
     }
 
     /// Add a key/value pair to the output section of the benchmark information.
-    pub fn with_output(&mut self, key: &str, value: String) {
-        self.output.insert(key.to_string(), value);
+    /// The value keeps its type (integer, float, bool, string, duration, array or object)
+    /// and is written with the matching JSON type.
+    pub fn with_output(&mut self, key: &str, value: impl Into<OutputValue>) {
+        self.output.insert(key.to_string(), value.into());
+    }
+
+    /// Get a previously recorded output value.
+    pub fn output(&self, key: &str) -> Option<&OutputValue> {
+        self.output.get(key)
+    }
+
+    /// Replace the cross-PE summary section with one computed from the given per-PE outputs
+    /// (indexed by PE id). See `aggregate::summarize_pe_outputs` for the layout.
+    pub fn set_pe_summary(&mut self, per_pe: &[HashMap<String, OutputValue>]) {
+        self.pe_summary = aggregate::summarize_pe_outputs(per_pe);
+    }
+
+    /// Get the summary of a given output key across PEs, if one has been computed.
+    pub fn pe_summary(&self, key: &str) -> Option<&OutputValue> {
+        self.pe_summary.get(key)
+    }
+
+    /// Sum of the numeric per-PE values of an output key, if a cross-PE summary has been computed
+    /// (PEs that did not record the key are skipped).
+    pub fn pe_total(&self, key: &str) -> Option<f64> {
+        match self.pe_summary.get(key) {
+            Some(OutputValue::Object(entry)) => match entry.get("per_pe") {
+                Some(OutputValue::Array(per_pe)) => {
+                    Some(per_pe.iter().filter_map(OutputValue::as_f64).sum())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn output_json(&self) -> JsonValue {
+        let mut output = JsonValue::new_object();
+        for (key, value) in &self.output {
+            output[key.as_str()] = value.to_json();
+        }
+        output
     }
 
     /// Convert the captured information into a JsonValue object.
     /// This is not intended as a stable API, but may be useful for some cases...use with caution.
     pub fn as_json(&self) -> JsonValue {
-        json::object! {
+        let output = self.output_json();
+        let mut json_obj = json::object! {
+            "schema_version" => SCHEMA_VERSION,
             "benchark name" => self.benchark_name.clone(),
             "executable" => self.executable.to_string_lossy().to_string(),
             "parameters" => self.parameters.clone(),
             "run_date" => self.run_date.clone(),
-            "output" => self.output.clone(),
+            "output" => output,
             "build type" => self.build_type.clone(),
             "dependencies" => self.package_info.clone(),
             "git" => self.git.clone(),
@@ -74,7 +141,11 @@ impl BenchmarkInformation {
             "slurm_params" => self.slurm_params.clone(),
             "rust_edition" => self.rust_edition.clone(),
             "rust_compiler" => self.rust_compiler.clone(),
+        };
+        if !self.pe_summary.is_empty() {
+            json_obj["pe_summary"] = OutputValue::Object(self.pe_summary.clone()).to_json();
         }
+        json_obj
     }
 
     /// Display the captured information in JSON format to stdout.
@@ -317,9 +388,12 @@ pub fn default_benchmark_name() -> String {
         .to_string()
 }
 
-/// Generate a default output file name based on the benchmark name and current timestamp
+/// Generate a default output file name based on the benchmark name.
+/// The `BENCHMARK_OUTPUT_DIR` environment variable, if set, replaces `root`
+/// (the generated sbatch scripts use it to give every job its own directory).
 pub fn default_output_path(root: &str) -> PathBuf {
     let stem = default_benchmark_name();
+    let root = env::var("BENCHMARK_OUTPUT_DIR").unwrap_or_else(|_| root.to_string());
     PathBuf::from(format!("{root}/{stem}_result.jsonl"))
 }
 
@@ -332,9 +406,373 @@ mod tests {
         let mut benchmark_info = BenchmarkInformation::new();
         assert_eq!(benchmark_info.output.len(), 0);
 
-        benchmark_info.with_output("test_key", "test_value".into());
+        benchmark_info.with_output("test_key", "test_value");
         assert_eq!(benchmark_info.output.len(), 1);
-        assert_eq!(benchmark_info.output["test_key"], "test_value");
+        assert_eq!(
+            benchmark_info.output["test_key"],
+            OutputValue::from("test_value")
+        );
+    }
+
+    #[test]
+    fn test_typed_output() {
+        let mut benchmark_info = BenchmarkInformation::with_name("typed");
+        benchmark_info.with_output("count", 42usize);
+        benchmark_info.with_output("rate", 1.5f64);
+        benchmark_info.with_output("verified", true);
+        benchmark_info.with_output("elapsed", std::time::Duration::from_millis(250));
+        benchmark_info.with_output("per_pe", vec![1u64, 2, 3]);
+        benchmark_info.with_output("missing", None::<usize>);
+        let mut nested = std::collections::BTreeMap::new();
+        nested.insert("inner".to_string(), OutputValue::from("x"));
+        benchmark_info.with_output("nested", nested);
+
+        let json_obj = benchmark_info.as_json();
+        assert_eq!(json_obj["schema_version"], SCHEMA_VERSION);
+        let output = &json_obj["output"];
+        assert_eq!(output["count"].as_u64(), Some(42));
+        assert_eq!(output["rate"].as_f64(), Some(1.5));
+        assert_eq!(output["verified"].as_bool(), Some(true));
+        assert_eq!(output["elapsed"].as_f64(), Some(0.25));
+        assert!(output["per_pe"].is_array());
+        assert_eq!(output["per_pe"][2].as_u64(), Some(3));
+        assert!(output["missing"].is_null());
+        assert_eq!(output["nested"]["inner"].as_str(), Some("x"));
+
+        let round_trip =
+            OutputValue::from_json(&json::parse(&json::stringify(output.clone())).unwrap());
+        match round_trip {
+            OutputValue::Object(fields) => {
+                assert_eq!(fields["count"], OutputValue::UInt(42));
+                assert_eq!(fields["rate"], OutputValue::Float(1.5));
+                assert_eq!(fields["verified"], OutputValue::Bool(true));
+            }
+            other => panic!("expected an object, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_pe_summary() {
+        let mut per_pe = vec![HashMap::new(), HashMap::new(), HashMap::new()];
+        for (pe, outputs) in per_pe.iter_mut().enumerate() {
+            outputs.insert(
+                "local_mups".to_string(),
+                OutputValue::from(2.0 * (pe + 1) as f64),
+            );
+            outputs.insert("array_type".to_string(), OutputValue::from("atomic"));
+        }
+        per_pe[0].insert("issue_time".to_string(), OutputValue::from(1.0));
+
+        let mut benchmark_info = BenchmarkInformation::with_name("summary");
+        benchmark_info.set_pe_summary(&per_pe);
+        let json_obj = benchmark_info.as_json();
+        let mups = &json_obj["pe_summary"]["local_mups"];
+        assert_eq!(mups["min"].as_f64(), Some(2.0));
+        assert_eq!(mups["max"].as_f64(), Some(6.0));
+        assert_eq!(mups["mean"].as_f64(), Some(4.0));
+        assert_eq!(mups["stddev"].as_f64(), Some(2.0));
+        assert_eq!(mups["imbalance"].as_f64(), Some(1.5));
+        assert_eq!(mups["per_pe"].len(), 3);
+
+        let array_type = &json_obj["pe_summary"]["array_type"];
+        assert!(array_type["mean"].is_null());
+        assert_eq!(array_type["per_pe"][1].as_str(), Some("atomic"));
+
+        assert_eq!(benchmark_info.pe_total("local_mups"), Some(12.0));
+        assert_eq!(benchmark_info.pe_total("issue_time"), Some(1.0));
+        assert_eq!(benchmark_info.pe_total("missing"), None);
+
+        let issue_time = &json_obj["pe_summary"]["issue_time"];
+        assert_eq!(issue_time["count"].as_u64(), Some(1));
+        assert!(issue_time["per_pe"][2].is_null());
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::from_samples(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        let (low, high) = summary.ci95();
+        assert!(low < summary.mean && summary.mean < high);
+        assert!(Summary::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_runner() {
+        let mut resets = 0;
+        let mut calls = 0;
+        let trials = Runner::new(2, 3).run(
+            || resets += 1,
+            || {
+                calls += 1;
+                calls
+            },
+        );
+        assert_eq!(resets, 5);
+        assert_eq!(trials.times.len(), 3);
+        assert_eq!(trials.outputs, vec![3, 4, 5]);
+        let (median_time, median_output) = trials.median_trial();
+        assert!(trials.times.contains(&median_time));
+        assert!(trials.outputs.contains(median_output));
+
+        let mut benchmark_info = BenchmarkInformation::with_name("trials");
+        trials.record(&mut benchmark_info);
+        let json_obj = benchmark_info.as_json();
+        let recorded = &json_obj["output"]["trials"];
+        assert_eq!(recorded["warmup"].as_u64(), Some(2));
+        assert_eq!(recorded["count"].as_u64(), Some(3));
+        assert_eq!(recorded["times (secs)"].len(), 3);
+        assert_eq!(recorded["ci95"].len(), 2);
+    }
+
+    #[test]
+    fn test_sweep_plan() {
+        let config = json::parse(
+            r#"{
+                "group_name": "randperm",
+                "benchmarks": ["randperm"],
+                "benchmark_dir": ".",
+                "n": 3,
+                "benchmark_parameters": ["1000 10", "10000 20"],
+                "lamellar_versions": ["0.7.1"],
+                "slurm_configurations": [
+                    {"nodes": [1, 2, 4], "ntasks-per-node": [1], "cpus-per-task": [64]},
+                    {"nodes": [1], "ntasks-per-node": [2], "cpus-per-task": [32]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let config = sweep::SweepConfig::from_json(&config).unwrap();
+        let plan = config.plan();
+        assert_eq!(plan.len(), (3 + 1) * 2 * 3);
+        assert_eq!(plan[0].parameters, vec!["1000", "10"]);
+        assert_eq!(plan[0].lamellar_version.as_deref(), Some("0.7.1"));
+        assert_eq!(plan.iter().filter(|job| job.num_pes() == 4).count(), 6);
+        assert_eq!(plan.iter().filter(|job| job.repetition == 2).count(), 8);
+
+        let binary = config.binary_path("randperm");
+        assert!(binary.ends_with("target/release/randperm"));
+        let single = plan[0].local_commands(&binary, &config.benchmark_dir, 1);
+        assert_eq!(single.len(), 1);
+        assert!(
+            single[0]
+                .env
+                .contains(&("LAMELLAR_THREADS".to_string(), "64".to_string()))
+        );
+
+        let shmem = plan
+            .last()
+            .unwrap()
+            .local_commands(&binary, &config.benchmark_dir, 7);
+        assert_eq!(shmem.len(), 2);
+        assert!(
+            shmem[1]
+                .env
+                .contains(&("LAMELLAR_BACKEND".to_string(), "shmem".to_string()))
+        );
+        assert!(
+            shmem[1]
+                .env
+                .contains(&("LAMELLAR_PE_ID".to_string(), "1".to_string()))
+        );
+        assert!(shmem[1].to_string().ends_with("randperm 10000 20)"));
+
+        let empty = json::parse(r#"{"benchmarks": ["histo_darc"], "benchmark_parameters": []}"#);
+        let plan = sweep::SweepConfig::from_json(&empty.unwrap())
+            .unwrap()
+            .plan();
+        assert_eq!(plan.len(), 1);
+        assert!(plan[0].parameters.is_empty());
+        assert!(sweep::SweepConfig::from_json(&json::parse(r#"{"n": 2}"#).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_sbatch_scripts() {
+        let config = json::parse(
+            r#"{
+                "group_name": "triangle_counts",
+                "benchmarks": ["triangle_count", "triangle_count_buffered"],
+                "benchmark_dir": "/bench/triangle_count",
+                "n": 3,
+                "lamellar_versions": ["0.7.1"],
+                "benchmark_parameters": ["input_graphs/graph500-scale18-ef16_adj.tsv"],
+                "slurm_configurations": [
+                    {"nodes": [1, 2], "ntasks-per-node": [4], "cpus-per-task": [16]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let config = sweep::SweepConfig::from_json(&config).unwrap();
+        let options = sweep::SbatchOptions {
+            partition: Some("short".to_string()),
+            ..Default::default()
+        };
+        let scripts = config.sbatch_scripts(&config.plan(), &options);
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(
+            scripts[1].file_name,
+            "triangle_counts_lamellar0.7.1_N2_T4_C16.sbatch"
+        );
+
+        let script = &scripts[1].contents;
+        assert!(script.starts_with("#!/bin/bash\n"));
+        for line in [
+            "#SBATCH --job-name=triangle_counts_lamellar0.7.1_N2_T4_C16",
+            "#SBATCH --nodes=2",
+            "#SBATCH --ntasks-per-node=4",
+            "#SBATCH --cpus-per-task=16",
+            "#SBATCH --chdir=/bench/triangle_count",
+            "#SBATCH --partition=short",
+            "export LAMELLAR_THREADS=16",
+            "export BENCHMARK_OUTPUT_DIR=\"benchmarking/${SLURM_JOB_NAME}_${SLURM_JOB_ID}\"",
+            "for rep in $(seq 1 3); do",
+            "    srun --nodes=2 --ntasks-per-node=4 --cpus-per-task=16 ./target/release/triangle_count_buffered input_graphs/graph500-scale18-ef16_adj.tsv",
+        ] {
+            assert!(
+                script.lines().any(|l| l == line),
+                "missing `{line}` in\n{script}"
+            );
+        }
+        assert!(!script.contains("--account"));
+        assert_eq!(script.matches("srun ").count(), 2);
+    }
+
+    #[test]
+    fn test_read_results() {
+        let dir = env::temp_dir().join(format!("benchmark_record_read_{}", std::process::id()));
+        let nested = dir.join("job_1");
+        fs::create_dir_all(&nested).unwrap();
+
+        let mut record = BenchmarkInformation::with_name("histo_safe_am");
+        record.with_output("MUPS", 10.0);
+        record.with_output("num_pes", 2usize);
+        record.write(&dir.join("histo_safe_am_result.jsonl"));
+        record.with_output("MUPS", 14.0);
+        record.write(&nested.join("histo_safe_am_result.jsonl"));
+        // a version 1 record, every output is a string and there is no schema_version
+        fs::write(
+            nested.join("histo_buffered_safe_am_result.jsonl"),
+            "{\"benchark name\":\"histo_buffered_safe_am\",\"parameters\":[\"1000\"],\
+             \"output\":{\"MUPS\":\"30.5\",\"array_type\":\"safe\"},\
+             \"dependencies\":{\"lamellar\":\"0.7.1/registry+https://github.com/rust-lang/crates.io-index\"},\
+             \"slurm_params\":{\"SLURM_NTASKS\":\"4\"}}\n\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a result file").unwrap();
+
+        let records = results::read_path(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(records.len(), 3);
+        let v1 = records
+            .iter()
+            .find(|r| r.name == "histo_buffered_safe_am")
+            .unwrap();
+        assert_eq!(v1.schema_version, 1);
+        assert_eq!(v1.output("MUPS"), Some(&OutputValue::Float(30.5)));
+        assert_eq!(v1.output("array_type"), Some(&OutputValue::from("safe")));
+        assert_eq!(v1.lamellar_version.as_deref(), Some("0.7.1"));
+        assert_eq!(v1.num_pes(), Some(4));
+        assert!(records.iter().all(|r| r.source.is_some()));
+
+        let metrics = report::Metric::defaults();
+        let report = report::Report::new(
+            &records,
+            &[report::GroupField::Name, report::GroupField::NumPes],
+            &metrics,
+        );
+        assert_eq!(report.rows.len(), 2);
+        let safe = &report.rows[1];
+        assert_eq!(safe.key, vec!["histo_safe_am", "2"]);
+        assert_eq!(safe.runs, 2);
+        assert_eq!(safe.metrics[0].as_ref().unwrap().mean, 12.0);
+        assert!(safe.metrics[1].is_none());
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("| benchmark | num_pes | runs | MUPS | MB_per_sec |"));
+        assert!(markdown.contains("| histo_safe_am | 2 | 2 | 12.0000 ± 2.8284 | - |"));
+        let csv = report.to_csv();
+        assert!(csv.contains("\nhisto_buffered_safe_am,4,1,30.5,0,1,,,0,"));
+    }
+
+    #[test]
+    fn test_mann_whitney() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let b = [11.0, 12.0, 13.0, 14.0, 15.0, 16.0, 17.0, 18.0];
+        let p = stats::mann_whitney_u(&a, &b).unwrap();
+        assert!(p < 0.01, "p = {p}");
+        assert!((stats::mann_whitney_u(&a, &a).unwrap() - 1.0).abs() < 1e-9);
+        let mixed = [1.5, 2.5, 3.5, 4.5, 5.5, 6.5, 7.5, 8.5];
+        assert!(stats::mann_whitney_u(&a, &mixed).unwrap() > 0.5);
+        assert!(stats::mann_whitney_u(&a, &[]).is_none());
+    }
+
+    #[test]
+    fn test_chi_square() {
+        // 95th percentiles of the chi-square distribution
+        assert!((stats::chi_square_sf(11.070, 5) - 0.05).abs() < 2e-3);
+        assert!((stats::chi_square_sf(66.339, 49) - 0.05).abs() < 1e-3);
+        assert!(stats::chi_square_sf(49.0, 49) > 0.4);
+        assert_eq!(stats::chi_square_sf(0.0, 3), 1.0);
+    }
+
+    #[test]
+    fn test_compare() {
+        let record = |name: &str, times: &[f64], mups: f64| {
+            let mut info = BenchmarkInformation::with_name(name);
+            let mut trials = BTreeMap::new();
+            trials.insert(
+                "times (secs)".to_string(),
+                OutputValue::from(times.to_vec()),
+            );
+            info.with_output("trials", trials);
+            info.with_output("MUPS", mups);
+            info.with_output("num_pes", 2usize);
+            results::Record::from_json(&info.as_json()).unwrap()
+        };
+        let baseline = vec![
+            record("histo_safe_am", &[1.0, 1.01, 0.99, 1.02], 100.0),
+            record("histo_safe_am", &[1.0, 0.98, 1.01, 1.0], 101.0),
+            record("histo_darc", &[2.0, 2.01, 1.99], 50.0),
+            record("randperm", &[3.0], 1.0),
+        ];
+        let candidate = vec![
+            record("histo_safe_am", &[1.0, 1.01, 0.99, 1.0], 100.5),
+            record("histo_safe_am", &[0.99, 1.0, 1.02, 1.01], 99.5),
+            record("histo_darc", &[2.5, 2.51, 2.49], 40.0),
+            record("histo_unsafe_am", &[1.0], 1.0),
+        ];
+        let comparisons = compare::compare(
+            &baseline,
+            &candidate,
+            &compare::ComparedMetric::defaults(),
+            &compare::CompareOptions::default(),
+        );
+        // histo_darc and histo_safe_am are in both sets, each with a time and a MUPS comparison
+        assert_eq!(comparisons.len(), 4);
+        let find = |name: &str, metric: &str| {
+            comparisons
+                .iter()
+                .find(|c| c.key[0] == name && c.metric == metric)
+                .unwrap()
+        };
+        let darc_time = find("histo_darc", "global_time (secs)");
+        assert_eq!(darc_time.baseline.count, 3);
+        assert!((darc_time.change - 0.25).abs() < 1e-9);
+        // 3 samples against 3 cannot be significant, so only the change is reported
+        assert!(!darc_time.regression);
+        let darc_mups = find("histo_darc", "MUPS");
+        assert!((darc_mups.change - 0.2).abs() < 1e-9);
+        assert!(darc_mups.p_value.is_none());
+        assert!(darc_mups.regression);
+
+        let safe_time = find("histo_safe_am", "global_time (secs)");
+        assert_eq!(safe_time.candidate.count, 8);
+        assert!(!safe_time.regression);
+        assert!(!find("histo_safe_am", "MUPS").regression);
+        assert!(compare::to_markdown(&comparisons).contains("| REGRESSION |"));
     }
 
     #[test]
@@ -356,7 +794,7 @@ mod tests {
         let output_path_str = output_path.to_string_lossy().to_string();
         println!("Default output file name: {output_path_str}");
 
-        assert!(output_path_str.ends_with(".json"));
+        assert!(output_path_str.ends_with(".jsonl"));
         assert!(output_path_str.contains(default_benchmark_name().as_str()));
     }
 
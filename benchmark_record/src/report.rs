@@ -0,0 +1,237 @@
+use crate::results::Record;
+use crate::stats::Summary;
+use std::collections::BTreeMap;
+
+/// A record property that results can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupField {
+    /// Benchmark name, with the `run_mode` output appended when there is one.
+    Name,
+    Parameters,
+    NumPes,
+    GitHash,
+    LamellarVersion,
+}
+
+impl GroupField {
+    pub const ALL: [GroupField; 5] = [
+        GroupField::Name,
+        GroupField::Parameters,
+        GroupField::NumPes,
+        GroupField::GitHash,
+        GroupField::LamellarVersion,
+    ];
+
+    /// Parse the command line name of a field (`name`, `params`, `num_pes`, `git`, `lamellar`).
+    pub fn parse(name: &str) -> Option<GroupField> {
+        match name {
+            "name" | "benchmark" => Some(GroupField::Name),
+            "params" | "parameters" => Some(GroupField::Parameters),
+            "num_pes" | "pes" => Some(GroupField::NumPes),
+            "git" | "git_hash" | "commit" => Some(GroupField::GitHash),
+            "lamellar" | "lamellar_version" => Some(GroupField::LamellarVersion),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupField::Name => "benchmark",
+            GroupField::Parameters => "parameters",
+            GroupField::NumPes => "num_pes",
+            GroupField::GitHash => "git",
+            GroupField::LamellarVersion => "lamellar",
+        }
+    }
+
+    /// Value of this field for a record ("-" when unknown).
+    pub fn value(&self, record: &Record) -> String {
+        let unknown = || "-".to_string();
+        match self {
+            GroupField::Name => match record.output("run_mode").and_then(|m| m.as_str()) {
+                Some(mode) => format!("{} ({mode})", record.name),
+                None => record.name.clone(),
+            },
+            GroupField::Parameters => record.parameters.join(" "),
+            GroupField::NumPes => record
+                .num_pes()
+                .map(|n| n.to_string())
+                .unwrap_or_else(unknown),
+            GroupField::GitHash => record
+                .git_hash
+                .as_ref()
+                .map(|hash| hash.chars().take(10).collect())
+                .unwrap_or_else(unknown),
+            GroupField::LamellarVersion => record.lamellar_version.clone().unwrap_or_else(unknown),
+        }
+    }
+}
+
+/// A column of a report: the mean of the first output key present in each record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metric {
+    pub label: String,
+    /// Output keys holding this metric, in order of preference.
+    pub keys: Vec<String>,
+}
+
+impl Metric {
+    pub fn new(label: &str, keys: &[&str]) -> Metric {
+        Metric {
+            label: label.to_string(),
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+
+    pub fn value(&self, record: &Record) -> Option<f64> {
+        let keys = self.keys.iter().map(String::as_str).collect::<Vec<_>>();
+        record.metric(&keys)
+    }
+
+    /// The metrics shown by default, covering the names used across the benchmarks.
+    pub fn defaults() -> Vec<Metric> {
+        vec![
+            Metric::new("MUPS", &["MUPS", "global_mups"]),
+            Metric::new("MB_per_sec", &["MB_per_sec", "MB_per_s"]),
+            Metric::new(
+                "global_time (secs)",
+                &[
+                    "global_run_time (secs)",
+                    "global_time (secs)",
+                    "global_time_secs",
+                    "global_execution_time (secs)",
+                    "global_execution_time_secs",
+                    "permute_time_secs",
+                ],
+            ),
+        ]
+    }
+}
+
+/// One group of records sharing the same values for the grouping fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub key: Vec<String>,
+    pub runs: usize,
+    /// One entry per metric, `None` if no record of the group has it.
+    pub metrics: Vec<Option<Summary>>,
+}
+
+/// Records grouped by a set of fields, with summary statistics of a set of metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub fields: Vec<GroupField>,
+    pub metrics: Vec<Metric>,
+    /// Sorted by key.
+    pub rows: Vec<Row>,
+}
+
+impl Report {
+    pub fn new(records: &[Record], fields: &[GroupField], metrics: &[Metric]) -> Report {
+        let mut groups: BTreeMap<Vec<String>, Vec<&Record>> = BTreeMap::new();
+        for record in records {
+            let key = fields.iter().map(|f| f.value(record)).collect();
+            groups.entry(key).or_default().push(record);
+        }
+        let rows = groups
+            .into_iter()
+            .map(|(key, group)| Row {
+                key,
+                runs: group.len(),
+                metrics: metrics
+                    .iter()
+                    .map(|metric| {
+                        let samples = group
+                            .iter()
+                            .filter_map(|r| metric.value(r))
+                            .collect::<Vec<_>>();
+                        Summary::from_samples(&samples)
+                    })
+                    .collect(),
+            })
+            .collect();
+        Report {
+            fields: fields.to_vec(),
+            metrics: metrics.to_vec(),
+            rows,
+        }
+    }
+
+    /// Markdown table with one row per group, metrics are shown as `mean ± stddev`.
+    pub fn to_markdown(&self) -> String {
+        let mut header = self
+            .fields
+            .iter()
+            .map(|f| f.label().to_string())
+            .collect::<Vec<_>>();
+        header.push("runs".to_string());
+        header.extend(self.metrics.iter().map(|m| m.label.clone()));
+
+        let mut lines = vec![
+            format!("| {} |", header.join(" | ")),
+            format!("|{}", "---|".repeat(header.len())),
+        ];
+        for row in &self.rows {
+            let mut cells = row
+                .key
+                .iter()
+                .map(|k| k.replace('|', "\\|"))
+                .collect::<Vec<_>>();
+            cells.push(row.runs.to_string());
+            cells.extend(row.metrics.iter().map(|summary| match summary {
+                Some(s) if s.count > 1 => format!("{:.4} ± {:.4}", s.mean, s.stddev),
+                Some(s) => format!("{:.4}", s.mean),
+                None => "-".to_string(),
+            }));
+            lines.push(format!("| {} |", cells.join(" | ")));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// CSV table with one row per group and `mean`, `stddev` and `n` columns per metric.
+    pub fn to_csv(&self) -> String {
+        let mut header = self
+            .fields
+            .iter()
+            .map(|f| f.label().to_string())
+            .collect::<Vec<_>>();
+        header.push("runs".to_string());
+        for metric in &self.metrics {
+            for stat in ["mean", "stddev", "n"] {
+                header.push(format!("{} {stat}", metric.label));
+            }
+        }
+
+        let mut lines = vec![csv_line(&header)];
+        for row in &self.rows {
+            let mut cells = row.key.clone();
+            cells.push(row.runs.to_string());
+            for summary in &row.metrics {
+                match summary {
+                    Some(s) => {
+                        cells.push(s.mean.to_string());
+                        cells.push(s.stddev.to_string());
+                        cells.push(s.count.to_string());
+                    }
+                    None => cells.extend(["".to_string(), "".to_string(), "0".to_string()]),
+                }
+            }
+            lines.push(csv_line(&cells));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+fn csv_line(cells: &[String]) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,168 @@
+use crate::OutputValue;
+use json::JsonValue;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A benchmark record read back from a `*_result.jsonl` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// 1 for records written before the `schema_version` field existed.
+    pub schema_version: u32,
+    pub name: String,
+    pub executable: String,
+    pub parameters: Vec<String>,
+    pub run_date: String,
+    pub output: BTreeMap<String, OutputValue>,
+    pub pe_summary: BTreeMap<String, OutputValue>,
+    /// `git.commit_hash` of the benchmark checkout, if it was captured.
+    pub git_hash: Option<String>,
+    /// Version of the `lamellar` dependency from the Cargo.lock, if it was captured.
+    pub lamellar_version: Option<String>,
+    pub slurm_params: BTreeMap<String, String>,
+    pub environment: BTreeMap<String, String>,
+    /// File the record was read from, if any.
+    pub source: Option<PathBuf>,
+}
+
+impl Record {
+    /// Parse one line of a result file.
+    /// Version 1 records store every output as a string, numeric strings are converted back to numbers.
+    pub fn from_json(value: &JsonValue) -> Result<Record, String> {
+        if !value.is_object() {
+            return Err("record is not a JSON object".to_string());
+        }
+        let name = value["benchark name"]
+            .as_str()
+            .ok_or_else(|| "record has no benchmark name".to_string())?
+            .to_string();
+        let schema_version = value["schema_version"].as_u32().unwrap_or(1);
+
+        let mut output = object_values(&value["output"]);
+        if schema_version < 2 {
+            for value in output.values_mut() {
+                if let OutputValue::Str(s) = value
+                    && let Ok(parsed) = json::parse(s)
+                    && parsed.is_number()
+                {
+                    *value = OutputValue::from_json(&parsed);
+                }
+            }
+        }
+
+        Ok(Record {
+            schema_version,
+            name,
+            executable: value["executable"].as_str().unwrap_or_default().to_string(),
+            parameters: value["parameters"]
+                .members()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect(),
+            run_date: value["run_date"].as_str().unwrap_or_default().to_string(),
+            output,
+            pe_summary: object_values(&value["pe_summary"]),
+            git_hash: value["git"]["commit_hash"].as_str().map(str::to_string),
+            // stored as "<version>/<source>"
+            lamellar_version: value["dependencies"]["lamellar"]
+                .as_str()
+                .and_then(|dep| dep.split('/').next())
+                .filter(|version| !version.is_empty())
+                .map(str::to_string),
+            slurm_params: object_strings(&value["slurm_params"]),
+            environment: object_strings(&value["environment"]),
+            source: None,
+        })
+    }
+
+    /// Get an output value.
+    pub fn output(&self, key: &str) -> Option<&OutputValue> {
+        self.output.get(key)
+    }
+
+    /// Numeric value of the first of the given output keys that is present.
+    /// Different benchmarks use different names for the same metric, so callers pass all aliases.
+    pub fn metric(&self, keys: &[&str]) -> Option<f64> {
+        keys.iter()
+            .find_map(|key| self.output.get(*key).and_then(OutputValue::as_f64))
+    }
+
+    /// Number of PEs the benchmark ran on: the `num_pes` output if recorded, otherwise the
+    /// number of PEs in the cross-PE summary, otherwise the slurm/lamellar environment.
+    pub fn num_pes(&self) -> Option<usize> {
+        if let Some(num_pes) = self.metric(&["num_pes"]) {
+            return Some(num_pes as usize);
+        }
+        if let Some(OutputValue::Object(entry)) = self.pe_summary.values().next()
+            && let Some(OutputValue::Array(per_pe)) = entry.get("per_pe")
+        {
+            return Some(per_pe.len());
+        }
+        ["SLURM_NTASKS", "SLURM_NPROCS"]
+            .iter()
+            .find_map(|key| self.slurm_params.get(*key))
+            .or_else(|| self.environment.get("LAMELLAR_NUM_PES"))
+            .and_then(|n| n.parse().ok())
+    }
+}
+
+/// Read every record of a JSON lines result file. Blank lines are skipped.
+pub fn read_file(path: &Path) -> Result<Vec<Record>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("unable to read {}: {e}", path.display()))?;
+    let mut records = Vec::new();
+    for (line_num, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record = json::parse(line)
+            .map_err(|e| e.to_string())
+            .and_then(|value| Record::from_json(&value))
+            .map_err(|e| format!("{}:{}: {e}", path.display(), line_num + 1))?;
+        record.source = Some(path.to_path_buf());
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Read a result file, or every `*_result.jsonl` file below a directory (recursively).
+pub fn read_path(path: &Path) -> Result<Vec<Record>, String> {
+    if !path.is_dir() {
+        return read_file(path);
+    }
+    let mut records = Vec::new();
+    for file in find_result_files(path)? {
+        records.extend(read_file(&file)?);
+    }
+    Ok(records)
+}
+
+/// All `*_result.jsonl` files below a directory, sorted by path.
+pub fn find_result_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("unable to read {}: {e}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(find_result_files(&path)?);
+        } else if path.to_string_lossy().ends_with("_result.jsonl") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn object_values(value: &JsonValue) -> BTreeMap<String, OutputValue> {
+    value
+        .entries()
+        .map(|(k, v)| (k.to_string(), OutputValue::from_json(v)))
+        .collect()
+}
+
+fn object_strings(value: &JsonValue) -> BTreeMap<String, String> {
+    value
+        .entries()
+        .filter_map(|(k, v)| v.as_str().map(|v| (k.to_string(), v.to_string())))
+        .collect()
+}
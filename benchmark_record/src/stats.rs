@@ -0,0 +1,170 @@
+use crate::OutputValue;
+use std::collections::BTreeMap;
+
+/// Summary statistics over a set of samples (e.g. one value per PE).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample standard deviation (0 when there is a single sample).
+    pub stddev: f64,
+}
+
+impl Summary {
+    /// Compute the summary of the given samples, returns `None` if there are no samples.
+    pub fn from_samples(samples: &[f64]) -> Option<Summary> {
+        if samples.is_empty() {
+            return None;
+        }
+        let count = samples.len();
+        let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = samples.iter().sum::<f64>() / count as f64;
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = if count.is_multiple_of(2) {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        } else {
+            sorted[count / 2]
+        };
+        let stddev = if count > 1 {
+            let var = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+            var.sqrt()
+        } else {
+            0.0
+        };
+        Some(Summary {
+            count,
+            min,
+            max,
+            mean,
+            median,
+            stddev,
+        })
+    }
+
+    /// Two-sided 95% confidence interval of the mean, using the Student t distribution.
+    pub fn ci95(&self) -> (f64, f64) {
+        if self.count < 2 {
+            return (self.mean, self.mean);
+        }
+        let half_width = t_critical_95(self.count - 1) * self.stddev / (self.count as f64).sqrt();
+        (self.mean - half_width, self.mean + half_width)
+    }
+
+    /// Ratio of the maximum to the mean, a common measure of load imbalance (1.0 is perfectly balanced).
+    pub fn imbalance(&self) -> f64 {
+        if self.mean == 0.0 {
+            1.0
+        } else {
+            self.max / self.mean
+        }
+    }
+
+    pub fn to_output(&self) -> OutputValue {
+        let mut fields = BTreeMap::new();
+        fields.insert("count".to_string(), OutputValue::from(self.count));
+        fields.insert("min".to_string(), OutputValue::from(self.min));
+        fields.insert("max".to_string(), OutputValue::from(self.max));
+        fields.insert("mean".to_string(), OutputValue::from(self.mean));
+        fields.insert("median".to_string(), OutputValue::from(self.median));
+        fields.insert("stddev".to_string(), OutputValue::from(self.stddev));
+        OutputValue::Object(fields)
+    }
+}
+
+/// Two-sided p-value of the Mann-Whitney U test that samples `a` and `b` come from the same
+/// distribution, using the normal approximation with tie and continuity corrections.
+/// Returns `None` if either sample is empty.
+/// With very few samples (e.g. 3 against 3) the test cannot reach p < 0.05, pool several runs.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let n = n1 + n2;
+
+    let mut combined = a
+        .iter()
+        .map(|v| (*v, true))
+        .chain(b.iter().map(|v| (*v, false)))
+        .collect::<Vec<_>>();
+    combined.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // average ranks over ties
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < combined.len() {
+        let mut j = i;
+        while j + 1 < combined.len() && combined[j + 1].0 == combined[i].0 {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        let ties = (j - i + 1) as f64;
+        tie_term += ties.powi(3) - ties;
+        rank_sum_a += rank * combined[i..=j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return Some(1.0);
+    }
+    let z = ((u - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+    Some((2.0 * normal_sf(z)).min(1.0))
+}
+
+/// Survival function (1 - cdf) of the chi-square distribution with `df` degrees of freedom,
+/// i.e. the p-value of a chi-square statistic, using the Wilson-Hilferty approximation
+/// (accurate to about 1e-3 for a few degrees of freedom and better as `df` grows).
+pub fn chi_square_sf(x: f64, df: usize) -> f64 {
+    if df == 0 || x <= 0.0 {
+        return 1.0;
+    }
+    let k = df as f64;
+    let z = ((x / k).cbrt() - (1.0 - 2.0 / (9.0 * k))) / (2.0 / (9.0 * k)).sqrt();
+    normal_sf(z)
+}
+
+/// Survival function (1 - cdf) of the standard normal distribution.
+fn normal_sf(z: f64) -> f64 {
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function (Numerical Recipes `erfcc`, fractional error below 1.2e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 { result } else { 2.0 - result }
+}
+
+/// Two-sided 95% critical value of the Student t distribution for the given degrees of freedom.
+fn t_critical_95(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match df {
+        0 => f64::INFINITY,
+        1..=30 => TABLE[df - 1],
+        31..=60 => 2.000,
+        61..=120 => 1.980,
+        _ => 1.960,
+    }
+}
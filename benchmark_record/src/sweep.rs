@@ -0,0 +1,438 @@
+use json::JsonValue;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+/// One entry of the `slurm_configurations` list of a benchmark config.
+/// Every field is a list of values, a sweep runs their cartesian product.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlurmConfiguration {
+    pub nodes: Vec<usize>,
+    pub ntasks_per_node: Vec<usize>,
+    pub cpus_per_task: Vec<usize>,
+}
+
+/// The contents of a `benchmark_config.json` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepConfig {
+    pub group_name: String,
+    pub benchmarks: Vec<String>,
+    /// Directory of the cargo package holding the benchmarks, relative to the config file.
+    pub benchmark_dir: PathBuf,
+    /// Number of repetitions of every configuration.
+    pub n: usize,
+    /// Each entry is one set of whitespace separated command line arguments.
+    pub benchmark_parameters: Vec<String>,
+    pub lamellar_versions: Vec<String>,
+    pub slurm_configurations: Vec<SlurmConfiguration>,
+}
+
+/// A single run of a benchmark binary, as expanded from a `SweepConfig`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub group_name: String,
+    pub benchmark: String,
+    pub parameters: Vec<String>,
+    pub nodes: usize,
+    pub ntasks_per_node: usize,
+    pub cpus_per_task: usize,
+    pub lamellar_version: Option<String>,
+    /// Zero based repetition index, in `0..n`.
+    pub repetition: usize,
+}
+
+/// A fully resolved process to start: program, arguments, extra environment variables and
+/// working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub working_dir: PathBuf,
+}
+
+impl SweepConfig {
+    /// Read and parse a benchmark config file.
+    /// A relative `benchmark_dir` is resolved against the directory holding the file.
+    pub fn load(path: &Path) -> Result<SweepConfig, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {e}", path.display()))?;
+        let parsed = json::parse(&contents)
+            .map_err(|e| format!("unable to parse {}: {e}", path.display()))?;
+        let mut config = SweepConfig::from_json(&parsed)
+            .map_err(|e| format!("invalid config {}: {e}", path.display()))?;
+        if config.benchmark_dir.is_relative() {
+            let parent = path.parent().unwrap_or(Path::new("."));
+            config.benchmark_dir = parent.join(&config.benchmark_dir);
+        }
+        // the benchmarks run from inside benchmark_dir, so the binary paths must not be relative
+        if let Ok(dir) = fs::canonicalize(&config.benchmark_dir) {
+            config.benchmark_dir = dir;
+        }
+        Ok(config)
+    }
+
+    /// Build a config from its JSON representation.
+    /// `n` defaults to 1 and the parameter/version lists default to empty.
+    pub fn from_json(value: &JsonValue) -> Result<SweepConfig, String> {
+        let benchmarks = string_list(value, "benchmarks")?;
+        if benchmarks.is_empty() {
+            return Err("`benchmarks` must list at least one benchmark".to_string());
+        }
+        let n = match &value["n"] {
+            JsonValue::Null => 1,
+            n => n
+                .as_usize()
+                .ok_or_else(|| "`n` must be a non-negative integer".to_string())?,
+        };
+        let slurm_configurations = match &value["slurm_configurations"] {
+            JsonValue::Null => vec![SlurmConfiguration {
+                nodes: vec![1],
+                ntasks_per_node: vec![1],
+                cpus_per_task: vec![1],
+            }],
+            JsonValue::Array(entries) => entries
+                .iter()
+                .map(|entry| {
+                    Ok(SlurmConfiguration {
+                        nodes: usize_list(entry, "nodes")?,
+                        ntasks_per_node: usize_list(entry, "ntasks-per-node")?,
+                        cpus_per_task: usize_list(entry, "cpus-per-task")?,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+            _ => return Err("`slurm_configurations` must be an array".to_string()),
+        };
+        Ok(SweepConfig {
+            group_name: value["group_name"].as_str().unwrap_or_default().to_string(),
+            benchmarks,
+            benchmark_dir: PathBuf::from(value["benchmark_dir"].as_str().unwrap_or(".")),
+            n,
+            benchmark_parameters: string_list(value, "benchmark_parameters")?,
+            lamellar_versions: string_list(value, "lamellar_versions")?,
+            slurm_configurations,
+        })
+    }
+
+    /// Expand the cartesian product of benchmarks × lamellar versions × slurm settings
+    /// (nodes × tasks per node × cpus per task) × parameters × repetitions.
+    /// An empty parameter list runs every benchmark once without arguments.
+    pub fn plan(&self) -> Vec<Job> {
+        let parameters = if self.benchmark_parameters.is_empty() {
+            vec![Vec::new()]
+        } else {
+            self.benchmark_parameters
+                .iter()
+                .map(|p| p.split_whitespace().map(str::to_string).collect())
+                .collect()
+        };
+        let versions = if self.lamellar_versions.is_empty() {
+            vec![None]
+        } else {
+            self.lamellar_versions.iter().cloned().map(Some).collect()
+        };
+
+        let mut jobs = Vec::new();
+        for benchmark in &self.benchmarks {
+            for version in &versions {
+                for slurm in &self.slurm_configurations {
+                    for &nodes in &slurm.nodes {
+                        for &ntasks_per_node in &slurm.ntasks_per_node {
+                            for &cpus_per_task in &slurm.cpus_per_task {
+                                for params in &parameters {
+                                    for repetition in 0..self.n {
+                                        jobs.push(Job {
+                                            group_name: self.group_name.clone(),
+                                            benchmark: benchmark.clone(),
+                                            parameters: params.clone(),
+                                            nodes,
+                                            ntasks_per_node,
+                                            cpus_per_task,
+                                            lamellar_version: version.clone(),
+                                            repetition,
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        jobs
+    }
+
+    /// Location of a benchmark executable built with `cargo build --release`.
+    pub fn binary_path(&self, benchmark: &str) -> PathBuf {
+        self.benchmark_dir
+            .join("target")
+            .join("release")
+            .join(benchmark)
+    }
+}
+
+impl Job {
+    pub fn num_pes(&self) -> usize {
+        self.nodes * self.ntasks_per_node
+    }
+
+    /// Commands to run this job on the local machine, started from `working_dir`.
+    ///
+    /// A single PE job is run directly with the local lamellae. Larger jobs start one process
+    /// per PE using the shmem lamellae (the same settings as Lamellar's `lamellar_run.sh`):
+    /// `LAMELLAR_BACKEND=shmem`, `LAMELLAR_NUM_PES`, `LAMELLAR_PE_ID` and `LAMELLAR_JOB_ID`.
+    /// In both cases `LAMELLAR_THREADS` is set from the cpus per task.
+    pub fn local_commands(
+        &self,
+        binary: &Path,
+        working_dir: &Path,
+        job_id: usize,
+    ) -> Vec<LaunchCommand> {
+        let num_pes = self.num_pes();
+        let threads = (
+            "LAMELLAR_THREADS".to_string(),
+            self.cpus_per_task.to_string(),
+        );
+        let command = |env: Vec<(String, String)>| LaunchCommand {
+            program: binary.to_path_buf(),
+            args: self.parameters.clone(),
+            env,
+            working_dir: working_dir.to_path_buf(),
+        };
+        if num_pes <= 1 {
+            return vec![command(vec![
+                ("LAMELLAR_BACKEND".to_string(), "local".to_string()),
+                threads,
+            ])];
+        }
+        (0..num_pes)
+            .map(|pe| {
+                command(vec![
+                    ("LAMELLAR_BACKEND".to_string(), "shmem".to_string()),
+                    ("LAMELLAR_NUM_PES".to_string(), num_pes.to_string()),
+                    ("LAMELLAR_PE_ID".to_string(), pe.to_string()),
+                    ("LAMELLAR_JOB_ID".to_string(), job_id.to_string()),
+                    threads.clone(),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// Optional `#SBATCH` settings shared by every generated script.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SbatchOptions {
+    pub partition: Option<String>,
+    pub account: Option<String>,
+    /// Wall clock limit in any format accepted by `sbatch --time`.
+    pub time: Option<String>,
+}
+
+/// A generated sbatch script, `file_name` is relative to the output directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbatchScript {
+    pub file_name: String,
+    pub contents: String,
+}
+
+impl SweepConfig {
+    /// Generate one sbatch script per lamellar version × nodes × tasks per node × cpus per task.
+    ///
+    /// Each script runs every benchmark and parameter set `n` times with `srun`, from inside
+    /// `benchmark_dir`. It sets `LAMELLAR_THREADS` to the cpus per task and points
+    /// `BENCHMARK_OUTPUT_DIR` at `benchmarking/<job name>_<job id>` so every job keeps its results apart.
+    pub fn sbatch_scripts(&self, jobs: &[Job], options: &SbatchOptions) -> Vec<SbatchScript> {
+        let mut scripts: Vec<(String, Vec<&Job>)> = Vec::new();
+        for job in jobs {
+            let name = job.slurm_job_name();
+            match scripts.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, group)) => group.push(job),
+                None => scripts.push((name, vec![job])),
+            }
+        }
+        scripts
+            .into_iter()
+            .map(|(name, group)| SbatchScript {
+                file_name: format!("{name}.sbatch"),
+                contents: self.sbatch_contents(&name, &group, options),
+            })
+            .collect()
+    }
+
+    fn sbatch_contents(&self, name: &str, jobs: &[&Job], options: &SbatchOptions) -> String {
+        let first = jobs[0];
+        let mut lines = vec![
+            "#!/bin/bash".to_string(),
+            format!("#SBATCH --job-name={name}"),
+            format!("#SBATCH --nodes={}", first.nodes),
+            format!("#SBATCH --ntasks-per-node={}", first.ntasks_per_node),
+            format!("#SBATCH --cpus-per-task={}", first.cpus_per_task),
+            format!("#SBATCH --chdir={}", self.benchmark_dir.display()),
+            "#SBATCH --output=slurm-%x-%j.out".to_string(),
+        ];
+        if let Some(partition) = &options.partition {
+            lines.push(format!("#SBATCH --partition={partition}"));
+        }
+        if let Some(account) = &options.account {
+            lines.push(format!("#SBATCH --account={account}"));
+        }
+        if let Some(time) = &options.time {
+            lines.push(format!("#SBATCH --time={time}"));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "# generated by bench_runner from the '{}' benchmark config",
+            self.group_name
+        ));
+        if let Some(version) = &first.lamellar_version {
+            lines.push(format!(
+                "# binaries are expected to be built against lamellar {version}"
+            ));
+        }
+        lines.push(format!("export LAMELLAR_THREADS={}", first.cpus_per_task));
+        lines.push(
+            "export BENCHMARK_OUTPUT_DIR=\"benchmarking/${SLURM_JOB_NAME}_${SLURM_JOB_ID}\""
+                .to_string(),
+        );
+        lines.push("mkdir -p \"$BENCHMARK_OUTPUT_DIR\"".to_string());
+
+        // every (benchmark, parameters) pair is run once per repetition
+        let mut runs: Vec<(&Job, usize)> = Vec::new();
+        for job in jobs {
+            match runs
+                .iter_mut()
+                .find(|(j, _)| j.benchmark == job.benchmark && j.parameters == job.parameters)
+            {
+                Some((_, count)) => *count += 1,
+                None => runs.push((job, 1)),
+            }
+        }
+        for (job, count) in runs {
+            lines.push(String::new());
+            lines.push(format!("for rep in $(seq 1 {count}); do"));
+            lines.push(format!("    {}", job.srun_command()));
+            lines.push("done".to_string());
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+impl Job {
+    /// Name of the slurm job running this configuration, e.g. `histo_lamellar0.7.1_N2_T1_C64`.
+    pub fn slurm_job_name(&self) -> String {
+        let version = match &self.lamellar_version {
+            Some(version) => format!("_lamellar{version}"),
+            None => String::new(),
+        };
+        format!(
+            "{}{version}_N{}_T{}_C{}",
+            self.group_name, self.nodes, self.ntasks_per_node, self.cpus_per_task
+        )
+    }
+
+    /// The `srun` line running this job from inside its benchmark directory.
+    pub fn srun_command(&self) -> String {
+        let mut command = format!(
+            "srun --nodes={} --ntasks-per-node={} --cpus-per-task={} ./target/release/{}",
+            self.nodes, self.ntasks_per_node, self.cpus_per_task, self.benchmark
+        );
+        for arg in &self.parameters {
+            command.push(' ');
+            command.push_str(&shell_quote(arg));
+        }
+        command
+    }
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] nodes={} ntasks-per-node={} cpus-per-task={} lamellar={} rep={}",
+            self.benchmark,
+            self.parameters.join(" "),
+            self.nodes,
+            self.ntasks_per_node,
+            self.cpus_per_task,
+            self.lamellar_version.as_deref().unwrap_or("<any>"),
+            self.repetition,
+        )
+    }
+}
+
+impl LaunchCommand {
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .current_dir(&self.working_dir);
+        command
+    }
+
+    pub fn spawn(&self) -> std::io::Result<Child> {
+        self.to_command().spawn()
+    }
+}
+
+impl fmt::Display for LaunchCommand {
+    /// Shell-like rendering, used by the dry-run mode.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(cd {} && ", self.working_dir.display())?;
+        for (key, value) in &self.env {
+            write!(f, "{key}={value} ")?;
+        }
+        write!(f, "{}", self.program.display())?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+fn string_list(value: &JsonValue, key: &str) -> Result<Vec<String>, String> {
+    match &value[key] {
+        JsonValue::Null => Ok(Vec::new()),
+        JsonValue::Array(entries) => entries
+            .iter()
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("`{key}` must only contain strings"))
+            })
+            .collect(),
+        _ => Err(format!("`{key}` must be an array of strings")),
+    }
+}
+
+fn usize_list(value: &JsonValue, key: &str) -> Result<Vec<usize>, String> {
+    match &value[key] {
+        JsonValue::Null => Ok(vec![1]),
+        JsonValue::Array(entries) => entries
+            .iter()
+            .map(|v| {
+                v.as_usize()
+                    .ok_or_else(|| format!("`{key}` must only contain non-negative integers"))
+            })
+            .collect(),
+        v => v
+            .as_usize()
+            .map(|v| vec![v])
+            .ok_or_else(|| format!("`{key}` must be an integer or an array of integers")),
+    }
+}
+
+/// Quote an argument for a POSIX shell, leaving simple words untouched.
+fn shell_quote(arg: &str) -> String {
+    let simple = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if simple {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
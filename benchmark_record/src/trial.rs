@@ -0,0 +1,110 @@
+use crate::stats::Summary;
+use crate::{BenchmarkInformation, OutputValue};
+use std::collections::BTreeMap;
+use std::env;
+use std::time::Instant;
+
+const DEFAULT_WARMUP: usize = 1;
+const DEFAULT_TRIALS: usize = 3;
+
+/// Runs a timed region a number of times: first `warmup` untimed iterations,
+/// then `trials` measured iterations.
+///
+/// Every iteration first calls a `reset` closure (not timed) to put the benchmark state back
+/// to its initial values, followed by the `trial` closure which is timed.
+/// For distributed benchmarks the reset closure should end with a barrier, and the trial
+/// closure should end with one too, so the measured time covers all PEs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Runner {
+    pub warmup: usize,
+    pub trials: usize,
+}
+
+/// The measured iterations of a `Runner`, along with whatever each trial returned.
+#[derive(Debug, Clone)]
+pub struct Trials<R> {
+    pub warmup: usize,
+    /// Wall clock time in seconds of each measured trial.
+    pub times: Vec<f64>,
+    pub outputs: Vec<R>,
+}
+
+impl Runner {
+    pub fn new(warmup: usize, trials: usize) -> Self {
+        Runner {
+            warmup,
+            trials: std::cmp::max(trials, 1),
+        }
+    }
+
+    /// Read the number of warmup and measured iterations from the BENCHMARK_WARMUP and
+    /// BENCHMARK_TRIALS environment variables, falling back to 1 warmup and 3 trials.
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(default)
+        };
+        Runner::new(
+            read("BENCHMARK_WARMUP", DEFAULT_WARMUP),
+            read("BENCHMARK_TRIALS", DEFAULT_TRIALS),
+        )
+    }
+
+    /// Run the warmup and measured iterations, returning the timings of the measured ones.
+    pub fn run<R>(&self, mut reset: impl FnMut(), mut trial: impl FnMut() -> R) -> Trials<R> {
+        for _ in 0..self.warmup {
+            reset();
+            trial();
+        }
+        let mut times = Vec::with_capacity(self.trials);
+        let mut outputs = Vec::with_capacity(self.trials);
+        for _ in 0..self.trials {
+            reset();
+            let now = Instant::now();
+            outputs.push(trial());
+            times.push(now.elapsed().as_secs_f64());
+        }
+        Trials {
+            warmup: self.warmup,
+            times,
+            outputs,
+        }
+    }
+}
+
+impl<R> Trials<R> {
+    /// Statistics over the measured trial times.
+    pub fn summary(&self) -> Summary {
+        Summary::from_samples(&self.times).expect("a runner always measures at least one trial")
+    }
+
+    /// Index of the trial with the median time (the lower of the two middle trials for an even count).
+    pub fn median_index(&self) -> usize {
+        let mut order = (0..self.times.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| self.times[*a].total_cmp(&self.times[*b]));
+        order[(order.len() - 1) / 2]
+    }
+
+    /// The time and returned value of the trial with the median time.
+    pub fn median_trial(&self) -> (f64, &R) {
+        let index = self.median_index();
+        (self.times[index], &self.outputs[index])
+    }
+
+    /// Add a `trials` entry to the record with the per-trial times and their
+    /// min/max/mean/median/stddev and 95% confidence interval.
+    pub fn record(&self, record: &mut BenchmarkInformation) {
+        let summary = self.summary();
+        let mut fields = match summary.to_output() {
+            OutputValue::Object(fields) => fields,
+            _ => BTreeMap::new(),
+        };
+        let (low, high) = summary.ci95();
+        fields.insert("ci95".to_string(), vec![low, high].into());
+        fields.insert("warmup".to_string(), self.warmup.into());
+        fields.insert("times (secs)".to_string(), self.times.clone().into());
+        record.with_output("trials", OutputValue::Object(fields));
+    }
+}
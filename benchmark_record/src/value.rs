@@ -0,0 +1,177 @@
+use json::JsonValue;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A typed value stored in the output section of a benchmark record.
+/// Each variant is serialized with the matching native JSON type, so readers
+/// of the result files do not need to guess how to parse a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(String),
+    /// Serialized as a floating point number of seconds.
+    Duration(Duration),
+    Array(Vec<OutputValue>),
+    Object(BTreeMap<String, OutputValue>),
+}
+
+impl OutputValue {
+    /// Convert this value into the equivalent JsonValue.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            OutputValue::Null => JsonValue::Null,
+            OutputValue::Bool(b) => (*b).into(),
+            OutputValue::Int(i) => (*i).into(),
+            OutputValue::UInt(u) => (*u).into(),
+            OutputValue::Float(f) => {
+                if f.is_finite() {
+                    (*f).into()
+                } else {
+                    JsonValue::Null
+                }
+            }
+            OutputValue::Str(s) => s.clone().into(),
+            OutputValue::Duration(d) => d.as_secs_f64().into(),
+            OutputValue::Array(values) => {
+                JsonValue::Array(values.iter().map(|v| v.to_json()).collect())
+            }
+            OutputValue::Object(fields) => {
+                let mut obj = JsonValue::new_object();
+                for (key, value) in fields {
+                    obj[key.as_str()] = value.to_json();
+                }
+                obj
+            }
+        }
+    }
+
+    /// Convert a JsonValue back into an OutputValue.
+    /// Numbers become `Int`/`UInt` when they have no fractional part, `Float` otherwise.
+    pub fn from_json(value: &JsonValue) -> OutputValue {
+        match value {
+            JsonValue::Null => OutputValue::Null,
+            JsonValue::Boolean(b) => OutputValue::Bool(*b),
+            JsonValue::Number(_) => {
+                if let Some(u) = value.as_u64() {
+                    OutputValue::UInt(u)
+                } else if let Some(i) = value.as_i64() {
+                    OutputValue::Int(i)
+                } else {
+                    OutputValue::Float(value.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            JsonValue::Short(_) | JsonValue::String(_) => {
+                OutputValue::Str(value.as_str().unwrap_or_default().to_string())
+            }
+            JsonValue::Array(values) => {
+                OutputValue::Array(values.iter().map(OutputValue::from_json).collect())
+            }
+            JsonValue::Object(obj) => OutputValue::Object(
+                obj.iter()
+                    .map(|(k, v)| (k.to_string(), OutputValue::from_json(v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Numeric view of the value, if it has one (durations are reported in seconds).
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            OutputValue::Int(i) => Some(*i as f64),
+            OutputValue::UInt(u) => Some(*u as f64),
+            OutputValue::Float(f) => Some(*f),
+            OutputValue::Duration(d) => Some(d.as_secs_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OutputValue::Str(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            OutputValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_int {
+    ($variant:ident, $target:ty, $($t:ty),*) => {
+        $(
+            impl From<$t> for OutputValue {
+                fn from(value: $t) -> Self {
+                    OutputValue::$variant(value as $target)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_int!(Int, i64, i8, i16, i32, i64, isize);
+impl_from_int!(UInt, u64, u8, u16, u32, u64, usize);
+
+impl From<f32> for OutputValue {
+    fn from(value: f32) -> Self {
+        OutputValue::Float(value as f64)
+    }
+}
+
+impl From<f64> for OutputValue {
+    fn from(value: f64) -> Self {
+        OutputValue::Float(value)
+    }
+}
+
+impl From<bool> for OutputValue {
+    fn from(value: bool) -> Self {
+        OutputValue::Bool(value)
+    }
+}
+
+impl From<String> for OutputValue {
+    fn from(value: String) -> Self {
+        OutputValue::Str(value)
+    }
+}
+
+impl From<&str> for OutputValue {
+    fn from(value: &str) -> Self {
+        OutputValue::Str(value.to_string())
+    }
+}
+
+impl From<Duration> for OutputValue {
+    fn from(value: Duration) -> Self {
+        OutputValue::Duration(value)
+    }
+}
+
+impl<T: Into<OutputValue>> From<Option<T>> for OutputValue {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(v) => v.into(),
+            None => OutputValue::Null,
+        }
+    }
+}
+
+impl<T: Into<OutputValue>> From<Vec<T>> for OutputValue {
+    fn from(values: Vec<T>) -> Self {
+        OutputValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<OutputValue>> From<BTreeMap<String, T>> for OutputValue {
+    fn from(fields: BTreeMap<String, T>) -> Self {
+        OutputValue::Object(fields.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
@@ -14,6 +14,7 @@ use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 use benchmark_record;
+use histo::verify::{Verification, VerifyMode};
 
 const COUNTS_LOCAL_LEN: usize = 10000000;
 
@@ -80,6 +81,14 @@ fn histo(
 
 //===== HISTO END ======
 
+// timings of a single trial, relative to the start of the trial
+struct TrialTimes {
+    issue_start_secs: f64,
+    issue_complete_secs: f64,
+    local_run_secs: f64,
+    mb_sent: f64,
+}
+
 fn main() {
 
     let args: Vec<String> = std::env::args().collect();
@@ -107,51 +116,73 @@ fn main() {
         .map(|_| rng.gen_range(0, global_count))
         .collect::<Vec<usize>>();
 
-    //create multiple launch tasks, that iterated through portions of rand_index in parallel
     let num_threads = match std::env::var("LAMELLAR_THREADS") {
         Ok(n) => n.parse::<usize>().unwrap(),
         Err(_) => 1,
     };
     let num_threads = std::cmp::max(num_threads / 2, 1);
-    world.barrier();
-    let now = Instant::now();
-    let launch_tasks = histo(l_num_updates, num_threads, &world, rand_index, &counts);
-
-    result_record.with_output("issue_start_time", now.elapsed().as_secs_f64().to_string());
-
-    world.block_on(async move {
-        for task in launch_tasks {
-            task.await;
-        }
-    });
+    let trials = benchmark_record::Runner::from_env().run(
+        || {
+            //reset the counts between trials
+            for elem in counts.iter() {
+                elem.store(0, Ordering::Relaxed);
+            }
+            world.barrier();
+        },
+        || {
+            let mb_start = world.MB_sent();
+            let now = Instant::now();
+            //create multiple launch tasks, that iterated through portions of rand_index in parallel
+            let launch_tasks = histo(l_num_updates, num_threads, &world, rand_index.clone(), &counts);
+            let issue_start_secs = now.elapsed().as_secs_f64();
+
+            world.block_on(async move {
+                for task in launch_tasks {
+                    task.await;
+                }
+            });
+            let issue_complete_secs = now.elapsed().as_secs_f64();
+            world.wait_all();
+            let local_run_secs = now.elapsed().as_secs_f64();
+            world.barrier();
+            TrialTimes {
+                issue_start_secs,
+                issue_complete_secs,
+                local_run_secs,
+                mb_sent: world.MB_sent() - mb_start,
+            }
+        },
+    );
+    trials.record(&mut result_record);
+    let (global_time, times) = trials.median_trial();
 
-    result_record.with_output("issue_complete_time", now.elapsed().as_secs_f64().to_string());
-    world.wait_all();
-    result_record.with_output("local_run_time", now.elapsed().as_secs_f64().to_string());
-    world.barrier();
-    
+    result_record.with_output("issue_start_time", times.issue_start_secs);
+    result_record.with_output("issue_complete_time", times.issue_complete_secs);
+    result_record.with_output("local_run_time", times.local_run_secs);
 
-    let global_time = now.elapsed().as_secs_f64();
     let total_updates = (l_num_updates * num_pes) as f64;
     let global_mups = (total_updates / 1_000_000.0) / global_time;
-    let mb_sent = world.MB_sent();
+    let mb_sent = times.mb_sent;
     let mb_per_sec = mb_sent / global_time;
 
-    result_record.with_output("global_execution_time (secs)", global_time.to_string());
-    result_record.with_output("MUPS", global_mups.to_string());
-    result_record.with_output("MB_sent", mb_sent.to_string());
-    result_record.with_output("MB_per_sec", mb_per_sec.to_string());
-
-    println!(
-        "pe {:?} sum {:?}",
-        my_pe,
-        counts
-            .iter()
-            .map(|e| e.load(Ordering::Relaxed))
-            .sum::<usize>()
-    );
+    result_record.with_output("global_execution_time (secs)", global_time);
+    result_record.with_output("MUPS", global_mups);
+    result_record.with_output("MB_sent", mb_sent);
+    result_record.with_output("MB_per_sec", mb_per_sec);
+
+    let local_counts = counts
+        .iter()
+        .map(|e| e.load(Ordering::Relaxed))
+        .collect::<Vec<usize>>();
+    let pe_sum = local_counts.iter().sum::<usize>();
+    println!("pe {:?} sum {:?}", my_pe, pe_sum);
+    result_record.with_output("pe_sum", pe_sum);
+
+    // check the table left by the last trial
+    let verification = Verification::run(&world, VerifyMode::from_env(), &local_counts, l_num_updates);
+    verification.record(&mut result_record);
 
-    if my_pe == 0 {
+    if let Some(result_record) = result_record.aggregate(&world) {
         result_record.write(&benchmark_record::default_output_path("benchmarking"));
         println!("Benchmark Results:");
         result_record.display(Some(3));
@@ -5,6 +5,7 @@ use rand::prelude::*;
 use std::sync::Arc;
 use std::time::Instant;
 use benchmark_record;
+use histo::verify::{Verification, VerifyMode};
 
 const COUNTS_LOCAL_LEN: usize = 100_000_000; //this will be 800MBB on each pe
 
@@ -112,90 +113,124 @@ fn main() {
     let rand_index = rand_index.into_read_only().block();
     world.barrier();
 
+    let runner = benchmark_record::Runner::from_env();
+    let verify_mode = VerifyMode::from_env();
+
     let mut result_record = benchmark_record::BenchmarkInformation::new();
-    let res_unsafe = histo(
-        "unsafe",
-        counts.clone().into(),
-        &rand_index,
-        &world,
-        my_pe,
-        num_pes,
-        l_num_updates,
-        1,
-        0.0,
+    let trials = runner.run(
+        || {
+            world.block_on(unsafe { counts.dist_iter_mut().for_each(|x| *x = 0).spawn() });
+            counts.barrier();
+        },
+        || {
+            histo(
+                "unsafe",
+                counts.clone().into(),
+                &rand_index,
+                &world,
+                my_pe,
+                num_pes,
+                l_num_updates,
+                1,
+                world.MB_sent(),
+            )
+        },
     );
-
-    result_record.with_output("run_mode", "unsafe".into());
-    result_record.with_output("updates_total", (l_num_updates * num_pes).to_string());
-    result_record.with_output("updates_per_pe", l_num_updates.to_string());
-    result_record.with_output("table_size_per_pe", COUNTS_LOCAL_LEN.to_string());
-    result_record.with_output("local_run_time (secs)", res_unsafe.local_run_time_secs.to_string());
-    result_record.with_output("local_mups", res_unsafe.mups.to_string());
-    result_record.with_output("global_run_time (secs)", res_unsafe.global_time_secs.to_string());
-    result_record.with_output("MB_sent", res_unsafe.mb_sent.to_string());
-    result_record.with_output("MB_per_sec", (res_unsafe.mb_sent / res_unsafe.global_time_secs).to_string());
-    result_record.with_output("array_type", "unsafe".into());
-    if my_pe == 0 {
+    trials.record(&mut result_record);
+    let (_, res_unsafe) = trials.median_trial();
+    Verification::run(&world, verify_mode, unsafe { counts.local_as_slice() }, l_num_updates)
+        .record(&mut result_record);
+
+    result_record.with_output("run_mode", "unsafe");
+    result_record.with_output("updates_total", l_num_updates * num_pes);
+    result_record.with_output("updates_per_pe", l_num_updates);
+    result_record.with_output("table_size_per_pe", COUNTS_LOCAL_LEN);
+    result_record.with_output("local_run_time (secs)", res_unsafe.local_run_time_secs);
+    result_record.with_output("local_mups", res_unsafe.mups);
+    result_record.with_output("global_run_time (secs)", res_unsafe.global_time_secs);
+    result_record.with_output("MB_sent", res_unsafe.mb_sent);
+    result_record.with_output("MB_per_sec", res_unsafe.mb_sent / res_unsafe.global_time_secs);
+    result_record.with_output("array_type", "unsafe");
+    if let Some(result_record) = result_record.aggregate(&world) {
         result_record.write(results_file);
         println!("Finished 'unsafe' run mode");
     }
 
-    world.block_on(unsafe { counts.dist_iter_mut().for_each(|x| *x = 0).spawn() });
-    counts.barrier();
-
     let mut result_record = benchmark_record::BenchmarkInformation::new();
     let counts = counts.into_local_lock().block();
-    let res_local_lock = histo(
-        "local_lock",
-        counts.clone().into(),
-        &rand_index,
-        &world,
-        my_pe,
-        num_pes,
-        l_num_updates,
-        1,
-        res_unsafe.mb_sent,
+    let trials = runner.run(
+        || {
+            world.block_on(counts.dist_iter_mut().for_each(|x| *x = 0));
+            counts.barrier();
+        },
+        || {
+            histo(
+                "local_lock",
+                counts.clone().into(),
+                &rand_index,
+                &world,
+                my_pe,
+                num_pes,
+                l_num_updates,
+                1,
+                world.MB_sent(),
+            )
+        },
     );
-
-    result_record.with_output("run_mode", "local_lock".into());
-    result_record.with_output("local_run_time (secs)", res_local_lock.local_run_time_secs.to_string());
-    result_record.with_output("local_mups", res_local_lock.mups.to_string());
-    result_record.with_output("global_run_time (secs)", res_local_lock.global_time_secs.to_string());
-    result_record.with_output("MB_sent", res_local_lock.mb_sent.to_string());
-    result_record.with_output("MB_per_sec", (res_local_lock.mb_sent / res_local_lock.global_time_secs).to_string());
-    result_record.with_output("array_type", "local_lock".into());
-
-    if my_pe == 0 {
+    trials.record(&mut result_record);
+    let (_, res_local_lock) = trials.median_trial();
+    let local_counts = counts.read_local_data().block();
+    Verification::run(&world, verify_mode, &local_counts, l_num_updates).record(&mut result_record);
+    drop(local_counts);
+
+    result_record.with_output("run_mode", "local_lock");
+    result_record.with_output("local_run_time (secs)", res_local_lock.local_run_time_secs);
+    result_record.with_output("local_mups", res_local_lock.mups);
+    result_record.with_output("global_run_time (secs)", res_local_lock.global_time_secs);
+    result_record.with_output("MB_sent", res_local_lock.mb_sent);
+    result_record.with_output("MB_per_sec", res_local_lock.mb_sent / res_local_lock.global_time_secs);
+    result_record.with_output("array_type", "local_lock");
+
+    if let Some(result_record) = result_record.aggregate(&world) {
         result_record.write(results_file);
         println!("Finished 'local_lock' run mode");
     }
-    world.block_on(counts.dist_iter_mut().for_each(|x| *x = 0));
-    counts.barrier();
 
     let mut result_record = benchmark_record::BenchmarkInformation::new();
     let counts = counts.into_atomic().block();
-    let res_atomic = histo(
-        "atomic",
-        counts.clone().into(),
-        &rand_index,
-        &world,
-        my_pe,
-        num_pes,
-        l_num_updates,
-        1,
-        res_local_lock.mb_sent,
+    let trials = runner.run(
+        || {
+            world.block_on(counts.dist_iter().for_each(|x| x.store(0)));
+            counts.barrier();
+        },
+        || {
+            histo(
+                "atomic",
+                counts.clone().into(),
+                &rand_index,
+                &world,
+                my_pe,
+                num_pes,
+                l_num_updates,
+                1,
+                world.MB_sent(),
+            )
+        },
     );
-    
-    result_record.with_output("run_mode", "atomic".into());
-    result_record.with_output("local_run_time (secs)", res_atomic.local_run_time_secs.to_string());
-    result_record.with_output("local_mups", res_atomic.mups.to_string());
-    result_record.with_output("global_run_time (secs)", res_atomic.global_time_secs.to_string());
-    result_record.with_output("MB_sent", res_atomic.mb_sent.to_string());
-    result_record.with_output("MB_per_sec", (res_atomic.mb_sent / res_atomic.global_time_secs).to_string());
-    result_record.with_output("array_type", "atomic".into());
-
-
-    if my_pe == 0 {
+    trials.record(&mut result_record);
+    let (_, res_atomic) = trials.median_trial();
+    let local_counts = counts.local_data().iter().map(|x| x.load()).collect::<Vec<usize>>();
+    Verification::run(&world, verify_mode, &local_counts, l_num_updates).record(&mut result_record);
+
+    result_record.with_output("run_mode", "atomic");
+    result_record.with_output("local_run_time (secs)", res_atomic.local_run_time_secs);
+    result_record.with_output("local_mups", res_atomic.mups);
+    result_record.with_output("global_run_time (secs)", res_atomic.global_time_secs);
+    result_record.with_output("MB_sent", res_atomic.mb_sent);
+    result_record.with_output("MB_per_sec", res_atomic.mb_sent / res_atomic.global_time_secs);
+    result_record.with_output("array_type", "atomic");
+
+    if let Some(result_record) = result_record.aggregate(&world) {
         result_record.write(results_file);
         println!("Finished 'atomic' run mode");
     }
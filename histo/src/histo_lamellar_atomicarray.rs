@@ -6,6 +6,7 @@ use rand::prelude::*;
 use std::sync::Arc;
 use std::time::Instant;
 use benchmark_record;
+use histo::verify::{Verification, VerifyMode};
 
 fn histo(counts: &AtomicArray<usize>, rand_index: &ReadOnlyArray<usize>) {
     let _ = counts.batch_add(rand_index.local_data(), 1).spawn();
@@ -13,6 +14,13 @@ fn histo(counts: &AtomicArray<usize>, rand_index: &ReadOnlyArray<usize>) {
 
 //===== HISTO END ======
 
+// timings of a single trial, relative to the start of the trial
+struct TrialTimes {
+    issue_secs: f64,
+    local_run_secs: f64,
+    mb_sent: f64,
+}
+
 const COUNTS_LOCAL_LEN: usize = 1000000; //100_000_000; //this will be 800MB on each pe
                                          // srun -N <num nodes> target/release/histo_lamellar_array <num updates>
 fn main() {
@@ -28,9 +36,9 @@ fn main() {
 
     let mut result_record = benchmark_record::BenchmarkInformation::new();
 
-    result_record.with_output("updates_total", (l_num_updates * num_pes).to_string());
-    result_record.with_output("updates_per_pe", l_num_updates.to_string());
-    result_record.with_output("table_size_per_pe", COUNTS_LOCAL_LEN.to_string());
+    result_record.with_output("updates_total", l_num_updates * num_pes);
+    result_record.with_output("updates_per_pe", l_num_updates);
+    result_record.with_output("table_size_per_pe", COUNTS_LOCAL_LEN);
 
     let unsafe_counts = UnsafeArray::<usize>::new(
         world.team(),
@@ -64,28 +72,49 @@ fn main() {
     let rand_index = rand_index.into_read_only().block();
     world.barrier();
 
-    let now = Instant::now();
-    histo(&counts, &rand_index);
-    result_record.with_output("issue_time (sec)", now.elapsed().as_secs_f64().to_string());
-    
-    counts.wait_all();
-    let local_run = now.elapsed();
-    
-    result_record.with_output("local_run_time (secs)", local_run.as_secs_f64().to_string());
-    result_record.with_output("local_mups", ((l_num_updates as f64 / 1_000_000.0) / local_run.as_secs_f64()).to_string());
-
-    counts.barrier();
-    let global_time = now.elapsed().as_secs_f64();
-    
-    let mb_sent = world.MB_sent();
-    result_record.with_output("global_run_time (secs)", global_time.to_string());
-    result_record.with_output("MB_sent", mb_sent.to_string());
-    result_record.with_output("MB_per_sec", (mb_sent / global_time).to_string());
-    result_record.with_output("MUPS", (((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time).to_string());
-    result_record.with_output("gb_per_s_injection_rate", ((8.0 * (l_num_updates * 2) as f64 * 1.0E-9) / global_time).to_string());
-
-    // println!("pe {:?} sum {:?}", my_pe, world.block_on(counts.sum()));
-    if my_pe == 0 {
+    let trials = benchmark_record::Runner::from_env().run(
+        || {
+            //reset the counts between trials
+            world.block_on(counts.dist_iter().for_each(|x| x.store(0)));
+            counts.barrier();
+        },
+        || {
+            let mb_start = world.MB_sent();
+            let now = Instant::now();
+            histo(&counts, &rand_index);
+            let issue_secs = now.elapsed().as_secs_f64();
+
+            counts.wait_all();
+            let local_run_secs = now.elapsed().as_secs_f64();
+            counts.barrier();
+            TrialTimes {
+                issue_secs,
+                local_run_secs,
+                mb_sent: world.MB_sent() - mb_start,
+            }
+        },
+    );
+    trials.record(&mut result_record);
+    let (global_time, times) = trials.median_trial();
+
+    result_record.with_output("issue_time (sec)", times.issue_secs);
+    result_record.with_output("local_run_time (secs)", times.local_run_secs);
+    result_record.with_output("local_mups", (l_num_updates as f64 / 1_000_000.0) / times.local_run_secs);
+
+    let mb_sent = times.mb_sent;
+    result_record.with_output("global_run_time (secs)", global_time);
+    result_record.with_output("MB_sent", mb_sent);
+    result_record.with_output("MB_per_sec", mb_sent / global_time);
+    result_record.with_output("MUPS", ((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time);
+    result_record.with_output("gb_per_s_injection_rate", (8.0 * (l_num_updates * 2) as f64 * 1.0E-9) / global_time);
+
+    // check the table left by the last trial
+    let local_counts = counts.local_data().iter().map(|x| x.load()).collect::<Vec<usize>>();
+    result_record.with_output("pe_sum", local_counts.iter().sum::<usize>());
+    let verification = Verification::run(&world, VerifyMode::from_env(), &local_counts, l_num_updates);
+    verification.record(&mut result_record);
+
+    if let Some(result_record) = result_record.aggregate(&world) {
         result_record.write(&benchmark_record::default_output_path("benchmarking"));
         println!("Benchmark Results:");
         result_record.display(Some(3));
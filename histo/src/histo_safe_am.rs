@@ -6,6 +6,7 @@ use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 use benchmark_record;
+use histo::verify::{Verification, VerifyMode};
 
 const COUNTS_LOCAL_LEN: usize = 10000000;
 
@@ -79,6 +80,14 @@ fn histo(
 
 //===== HISTO END ======
 
+// timings of a single trial, relative to the start of the trial
+struct TrialTimes {
+    issue_secs: f64,
+    launch_task_secs: f64,
+    local_run_secs: f64,
+    mb_sent: f64,
+}
+
 // srun -N <num nodes> target/release/histo <num updates>
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -115,38 +124,68 @@ fn main() {
         }
     }
 
-    //create multiple launch tasks, that iterated through portions of rand_index in parallel
-
-    world.barrier();
-    let now = Instant::now();
-    let launch_tasks = histo(l_num_updates, num_threads, &world, &rand_index, &counts);
-
-    result_record.with_output("issue_time (secs)", now.elapsed().as_secs_f64().to_string());
-
-    world.block_on(async move {
-        for task in launch_tasks {
-            task.await;
-        }
-    });
-    result_record.with_output("launch_task_time (secs)", now.elapsed().as_secs_f64().to_string());
-
-    world.wait_all();
-    result_record.with_output("local_run_time (secs)", now.elapsed().as_secs_f64().to_string());
-    result_record.with_output("local_mups", ((l_num_updates as f64 / 1_000_000.0) / now.elapsed().as_secs_f64()).to_string());
-
-    world.barrier();
-    let global_time = now.elapsed().as_secs_f64();
-
-    let mb_sent = world.MB_sent();
-    result_record.with_output("MUPS", (((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time).to_string());
-    result_record.with_output("global_run_time (secs)", global_time.to_string());
-    result_record.with_output("MB_sent", mb_sent.to_string());
-    result_record.with_output("MB_per_sec", (mb_sent / global_time).to_string());
-    result_record.with_output("global_mups_line", (((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time).to_string());
-
-    result_record.with_output("pe_sum", (unsafe { counts.as_slice().unwrap().iter().sum::<usize>() } as u64).to_string());
-
-    if my_pe == 0 {
+    let trials = benchmark_record::Runner::from_env().run(
+        || {
+            //reset the counts between trials
+            unsafe {
+                for elem in counts.as_mut_slice().unwrap().iter_mut() {
+                    *elem = 0;
+                }
+            }
+            world.barrier();
+        },
+        || {
+            let mb_start = world.MB_sent();
+            let now = Instant::now();
+            //create multiple launch tasks, that iterated through portions of rand_index in parallel
+            let launch_tasks = histo(l_num_updates, num_threads, &world, &rand_index, &counts);
+            let issue_secs = now.elapsed().as_secs_f64();
+
+            world.block_on(async move {
+                for task in launch_tasks {
+                    task.await;
+                }
+            });
+            let launch_task_secs = now.elapsed().as_secs_f64();
+
+            world.wait_all();
+            let local_run_secs = now.elapsed().as_secs_f64();
+            world.barrier();
+            TrialTimes {
+                issue_secs,
+                launch_task_secs,
+                local_run_secs,
+                mb_sent: world.MB_sent() - mb_start,
+            }
+        },
+    );
+    trials.record(&mut result_record);
+    let (global_time, times) = trials.median_trial();
+
+    result_record.with_output("issue_time (secs)", times.issue_secs);
+    result_record.with_output("launch_task_time (secs)", times.launch_task_secs);
+    result_record.with_output("local_run_time (secs)", times.local_run_secs);
+    result_record.with_output("local_mups", (l_num_updates as f64 / 1_000_000.0) / times.local_run_secs);
+
+    let mb_sent = times.mb_sent;
+    result_record.with_output("MUPS", ((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time);
+    result_record.with_output("global_run_time (secs)", global_time);
+    result_record.with_output("MB_sent", mb_sent);
+    result_record.with_output("MB_per_sec", mb_sent / global_time);
+    result_record.with_output("global_mups_line", ((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time);
+
+    result_record.with_output("pe_sum", unsafe { counts.as_slice().unwrap().iter().sum::<usize>() } as u64);
+
+    // check the table left by the last trial
+    let verification = Verification::run(
+        &world,
+        VerifyMode::from_env(),
+        unsafe { counts.as_slice().unwrap() },
+        l_num_updates,
+    );
+    verification.record(&mut result_record);
+
+    if let Some(result_record) = result_record.aggregate(&world) {
         result_record.write(&benchmark_record::default_output_path("benchmarking"));
         println!("Benchmark Results:");
         result_record.display(Some(3));
@@ -0,0 +1,2 @@
+// helpers shared by the histo benchmark binaries
+pub mod verify;
@@ -0,0 +1,164 @@
+// Optional correctness check run after the timed trials of every histo benchmark.
+//
+// Selected with the HISTO_VERIFY environment variable:
+//   off  - no verification
+//   sum  - (default) a global reduction of the counts compared to l_num_updates * num_pes
+//   bins - additionally compares every local bin against a histogram recomputed from the
+//          seeded random streams of all PEs (costs num_pes * l_num_updates random draws per PE)
+//
+// All benchmarks distribute the table cyclically: global bin `idx` lives on PE `idx % num_pes`
+// at offset `idx / num_pes`, and PE `pe` draws its updates from `StdRng::seed_from_u64(pe)`.
+
+use lamellar::active_messaging::prelude::*;
+use lamellar::darc::prelude::*;
+use rand::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyMode {
+    Off,
+    Sum,
+    Bins,
+}
+
+impl VerifyMode {
+    pub fn from_env() -> VerifyMode {
+        match std::env::var("HISTO_VERIFY").as_deref() {
+            Ok("off") | Ok("0") | Ok("false") => VerifyMode::Off,
+            Ok("bins") => VerifyMode::Bins,
+            _ => VerifyMode::Sum,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerifyMode::Off => "off",
+            VerifyMode::Sum => "sum",
+            VerifyMode::Bins => "bins",
+        }
+    }
+}
+
+#[lamellar::AmData(Clone, Debug)]
+struct AddAm {
+    total: Darc<AtomicUsize>,
+    value: usize,
+}
+
+#[lamellar::am]
+impl LamellarAM for AddAm {
+    async fn exec(self) {
+        self.total.fetch_add(self.value, Ordering::SeqCst);
+    }
+}
+
+// collective: the sum of `local` over all PEs, returned on every PE
+pub fn global_sum(world: &LamellarWorld, local: usize) -> usize {
+    let total = Darc::new(world, AtomicUsize::new(0))
+        .block()
+        .expect("unable to create darc"); // created with the world team so should be valid on all pes
+    world.block_on(world.exec_am_all(AddAm {
+        total: total.clone(),
+        value: local,
+    }));
+    world.barrier(); // every PE's contribution has been added on every PE
+    total.load(Ordering::SeqCst)
+}
+
+// the expected counts of the bins owned by `my_pe`, recomputed from every PE's random stream
+pub fn reference_counts(
+    my_pe: usize,
+    num_pes: usize,
+    l_num_updates: usize,
+    table_size_per_pe: usize,
+) -> Vec<usize> {
+    let global_count = table_size_per_pe * num_pes;
+    let mut reference = vec![0; table_size_per_pe];
+    for pe in 0..num_pes {
+        let mut rng: StdRng = SeedableRng::seed_from_u64(pe as u64);
+        for _ in 0..l_num_updates {
+            let idx = rng.gen_range(0, global_count);
+            if idx % num_pes == my_pe {
+                reference[idx / num_pes] += 1;
+            }
+        }
+    }
+    reference
+}
+
+pub struct Verification {
+    pub mode: VerifyMode,
+    pub expected_updates: usize,
+    pub counted_updates: usize,
+    // only computed in `VerifyMode::Bins`
+    pub mismatched_bins: Option<usize>,
+}
+
+impl Verification {
+    // collective: must be called on every PE with the PE's local part of the table
+    pub fn run(
+        world: &LamellarWorld,
+        mode: VerifyMode,
+        local_counts: &[usize],
+        l_num_updates: usize,
+    ) -> Verification {
+        let num_pes = world.num_pes();
+        let expected_updates = l_num_updates * num_pes;
+        if mode == VerifyMode::Off {
+            return Verification {
+                mode,
+                expected_updates,
+                counted_updates: 0,
+                mismatched_bins: None,
+            };
+        }
+
+        let counted_updates = global_sum(world, local_counts.iter().sum());
+        let mismatched_bins = if mode == VerifyMode::Bins {
+            let reference =
+                reference_counts(world.my_pe(), num_pes, l_num_updates, local_counts.len());
+            let local_mismatches = reference
+                .iter()
+                .zip(local_counts)
+                .filter(|(expected, count)| expected != count)
+                .count();
+            Some(global_sum(world, local_mismatches))
+        } else {
+            None
+        };
+        Verification {
+            mode,
+            expected_updates,
+            counted_updates,
+            mismatched_bins,
+        }
+    }
+
+    // updates missing from the table (negative if the table holds more than expected)
+    pub fn lost_updates(&self) -> i64 {
+        self.expected_updates as i64 - self.counted_updates as i64
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        self.lost_updates().unsigned_abs() as f64 / self.expected_updates.max(1) as f64
+    }
+
+    pub fn verified(&self) -> bool {
+        self.lost_updates() == 0 && self.mismatched_bins.unwrap_or(0) == 0
+    }
+
+    pub fn record(&self, record: &mut benchmark_record::BenchmarkInformation) {
+        record.with_output("verify_mode", self.mode.name());
+        if self.mode == VerifyMode::Off {
+            return;
+        }
+        record.with_output("verified", self.verified());
+        record.with_output("expected_updates", self.expected_updates);
+        record.with_output("counted_updates", self.counted_updates);
+        record.with_output("lost_updates", self.lost_updates());
+        record.with_output("error_rate", self.error_rate());
+        if let Some(mismatched_bins) = self.mismatched_bins {
+            record.with_output("mismatched_bins", mismatched_bins);
+        }
+    }
+}
@@ -1,14 +1,19 @@
 use lamellar::array::prelude::*;
 use lamellar::memregion::prelude::*;
 use rand::prelude::*;
-use std::time::Instant;
 use benchmark_record::BenchmarkInformation;
+use index_gather::verify::count_mismatches;
 
 const COUNTS_LOCAL_LEN: usize = 1_000_000;
 
-fn index_gather(array: &AtomicArray<usize>, rand_index: OneSidedMemoryRegion<usize>) {
+// gathers array[rand_index[j]] into target[j]
+fn index_gather(
+    array: &AtomicArray<usize>,
+    rand_index: OneSidedMemoryRegion<usize>,
+    target: &mut [usize],
+) {
     let rand_slice = unsafe { rand_index.as_slice().expect("PE on world team") };
-    array.batch_load(rand_slice).block();
+    target.copy_from_slice(&array.batch_load(rand_slice).block());
 }
 
 fn main() {
@@ -25,7 +30,7 @@ fn main() {
 
     // === Initialize Benchmark Record ===
     let mut bench = BenchmarkInformation::new();
-    bench.with_output("updates_total", (l_num_updates * num_pes).to_string());
+    bench.with_output("updates_total", l_num_updates * num_pes);
 
     let global_count = COUNTS_LOCAL_LEN * num_pes;
 
@@ -62,29 +67,49 @@ fn main() {
     }
 
     // === Benchmark timing ===
-    let now = Instant::now();
-    index_gather(&array, rand_index);
-    array.wait_all();
-    world.barrier();
-
-    let duration = now.elapsed().as_secs_f64();
+    // gathered values, in request order
+    let target = std::cell::RefCell::new(vec![usize::MAX; l_num_updates]);
+    let trials = benchmark_record::Runner::from_env().run(
+        || {
+            // a trial that skips a position leaves the sentinel, not the previous trial's value
+            target.borrow_mut().fill(usize::MAX);
+            world.barrier();
+        },
+        || {
+            let mb_start = world.MB_sent();
+            index_gather(&array, rand_index.clone(), &mut target.borrow_mut());
+            array.wait_all();
+            world.barrier();
+            world.MB_sent() - mb_start
+        },
+    );
+    trials.record(&mut bench);
+    let (duration, mb_sent) = trials.median_trial();
+    let mb_sent = *mb_sent;
 
     // === Collect Results ===
-    bench.with_output("updates_per_pe", l_num_updates.to_string());
-    bench.with_output("num_pes", num_pes.to_string());
-    bench.with_output("global_time_secs", duration.to_string());
+    bench.with_output("updates_per_pe", l_num_updates);
+    bench.with_output("num_pes", num_pes);
+    bench.with_output("global_time_secs", duration);
 
     let global_mups =
         ((l_num_updates * num_pes) as f64 / 1_000_000.0) / duration;
-    bench.with_output("MUPS", global_mups.to_string());
+    bench.with_output("MUPS", global_mups);
 
-    let mb_sent = world.MB_sent();
-    bench.with_output("MB_sent", mb_sent.to_string());
-    bench.with_output("MB_per_sec", (mb_sent / duration).to_string());
-    bench.with_output("gb_s_injection_rate", (8.0 * (l_num_updates * 2) as f64 * 1.0E-9 / duration).to_string());
+    bench.with_output("MB_sent", mb_sent);
+    bench.with_output("MB_per_sec", mb_sent / duration);
+    bench.with_output("gb_s_injection_rate", 8.0 * (l_num_updates * 2) as f64 * 1.0E-9 / duration);
 
-    if my_pe == 0 {
+    // --- verification: the table holds array[i] = i, so every gathered value is its index ---
+    let mismatches = count_mismatches(&target.borrow(), unsafe { rand_index.as_slice().unwrap() });
+    bench.with_output("gather_mismatches", mismatches);
+
+    if let Some(mut bench) = bench.aggregate(&world) {
+        let total_mismatches = bench.pe_total("gather_mismatches").unwrap_or(0.0) as u64;
+        bench.with_output("total_gather_mismatches", total_mismatches);
+        bench.with_output("verified", total_mismatches == 0);
         println!("Global time: {:.3}s, MUPS: {:.3}", duration, global_mups);
         bench.write(&benchmark_record::default_output_path("benchmarking"));
     }
-}
\ No newline at end of file
+    assert_eq!(mismatches, 0, "PE {my_pe} gathered {mismatches} wrong values");
+}
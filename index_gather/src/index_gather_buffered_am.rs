@@ -2,8 +2,8 @@ use lamellar::active_messaging::prelude::*;
 use lamellar::memregion::prelude::*;
 use rand::prelude::*;
 use std::future::Future;
-use std::time::Instant;
 use benchmark_record::BenchmarkInformation;
+use index_gather::verify::count_mismatches;
 
 const COUNTS_LOCAL_LEN: usize = 1_000_000; // this will be 800MB on each PE
 
@@ -29,6 +29,7 @@ impl LamellarAM for IndexGatherBufferedAM {
 #[lamellar::AmLocalData(Clone, Debug)]
 struct LaunchAm {
     rand_index: OneSidedMemoryRegion<usize>,
+    target: OneSidedMemoryRegion<usize>, // same range as rand_index, receives the gathered values
     counts: SharedMemoryRegion<usize>,
     buffer_amt: usize,
 }
@@ -39,16 +40,21 @@ impl LamellarAM for LaunchAm {
         let num_pes = lamellar::num_pes;
         let mut buffs: std::vec::Vec<std::vec::Vec<usize>> =
             vec![Vec::with_capacity(self.buffer_amt); num_pes];
+        // position in rand_index of every buffered request, to store the results in request order
+        let mut positions: std::vec::Vec<std::vec::Vec<usize>> =
+            vec![Vec::with_capacity(self.buffer_amt); num_pes];
+        let target = unsafe { self.target.as_mut_slice().unwrap() };
         let task_group = LamellarTaskGroup::new(lamellar::team.clone());
 
-        for idx in unsafe { self.rand_index.as_slice().unwrap() } {
+        for (j, idx) in unsafe { self.rand_index.as_slice().unwrap() }.iter().enumerate() {
             let rank = idx % num_pes;
             let offset = idx / num_pes;
 
             buffs[rank].push(offset);
+            positions[rank].push(j);
             if buffs[rank].len() >= self.buffer_amt {
                 let buff = buffs[rank].clone();
-                task_group
+                let vals = task_group
                     .exec_am_pe(
                         rank,
                         IndexGatherBufferedAM {
@@ -57,7 +63,11 @@ impl LamellarAM for LaunchAm {
                         },
                     )
                     .await;
+                for (pos, val) in positions[rank].iter().zip(vals) {
+                    target[*pos] = val;
+                }
                 buffs[rank].clear();
+                positions[rank].clear();
             }
         }
 
@@ -65,7 +75,7 @@ impl LamellarAM for LaunchAm {
         for rank in 0..num_pes {
             let buff = buffs[rank].clone();
             if !buff.is_empty() {
-                task_group
+                let vals = task_group
                     .exec_am_pe(
                         rank,
                         IndexGatherBufferedAM {
@@ -74,6 +84,9 @@ impl LamellarAM for LaunchAm {
                         },
                     )
                     .await;
+                for (pos, val) in positions[rank].iter().zip(vals) {
+                    target[*pos] = val;
+                }
             }
         }
     }
@@ -84,6 +97,7 @@ fn histo(
     num_threads: usize,
     world: &LamellarWorld,
     rand_index: &OneSidedMemoryRegion<usize>,
+    target: &OneSidedMemoryRegion<usize>,
     counts: &SharedMemoryRegion<usize>,
     buffer_amt: usize,
 ) -> Vec<impl Future<Output = ()>> {
@@ -94,6 +108,7 @@ fn histo(
         let end = ((tid + 1) as f32 * slice_size).round() as usize;
         launch_tasks.push(world.exec_am_local(LaunchAm {
             rand_index: rand_index.sub_region(start..end),
+            target: target.sub_region(start..end),
             counts: counts.clone(),
             buffer_amt,
         }));
@@ -133,18 +148,20 @@ fn main() {
 
     // === Initialize Benchmark Record ===
     let mut bench = BenchmarkInformation::new();
-    bench.with_output("updates_total", (l_num_updates * num_pes).to_string());
-    bench.with_output("updates_per_pe", l_num_updates.to_string());
-    bench.with_output("table_size_per_pe", COUNTS_LOCAL_LEN.to_string());
+    bench.with_output("updates_total", l_num_updates * num_pes);
+    bench.with_output("updates_per_pe", l_num_updates);
+    bench.with_output("table_size_per_pe", COUNTS_LOCAL_LEN);
 
     let rand_index = world.alloc_one_sided_mem_region(l_num_updates);
+    let target = world.alloc_one_sided_mem_region(l_num_updates);
     let mut rng: StdRng = SeedableRng::seed_from_u64(my_pe as u64);
 
     let counts = counts.block();
 
     unsafe {
-        for elem in counts.as_mut_slice().unwrap().iter_mut() {
-            *elem = 0;
+        // the table entry at (pe, offset) is global index offset * num_pes + pe, store that index
+        for (offset, elem) in counts.as_mut_slice().unwrap().iter_mut().enumerate() {
+            *elem = offset * num_pes + my_pe;
         }
         for elem in rand_index.as_mut_slice().unwrap().iter_mut() {
             // NOTE: rand 0.8+ API
@@ -153,49 +170,68 @@ fn main() {
     }
 
     // === Execute benchmark ===
-    world.barrier();
-    let now = Instant::now();
-
-    let launch_tasks = histo(
-        l_num_updates,
-        num_threads,
-        &world,
-        &rand_index,
-        &counts,
-        buffer_amt,
+    let trials = benchmark_record::Runner::from_env().run(
+        || {
+            // a trial that skips a position leaves the sentinel, not the previous trial's value
+            unsafe { target.as_mut_slice().unwrap().fill(usize::MAX) };
+            world.barrier();
+        },
+        || {
+            let mb_start = world.MB_sent();
+            let launch_tasks = histo(
+                l_num_updates,
+                num_threads,
+                &world,
+                &rand_index,
+                &target,
+                &counts,
+                buffer_amt,
+            );
+
+            world.block_on(async move {
+                for task in launch_tasks {
+                    task.await;
+                }
+            });
+
+            world.wait_all();
+            world.barrier();
+            world.MB_sent() - mb_start
+        },
     );
-
-    world.block_on(async move {
-        for task in launch_tasks {
-            task.await;
-        }
-    });
-
-    world.wait_all();
-    world.barrier();
-
-    let global_time = now.elapsed().as_secs_f64();
+    trials.record(&mut bench);
+    let (global_time, mb_sent) = trials.median_trial();
+    let mb_sent = *mb_sent;
 
     // === Collect metrics ===
-    bench.with_output("num_pes", num_pes.to_string());
-    bench.with_output("num_threads", num_threads.to_string());
-    bench.with_output("global_execution_time_secs", global_time.to_string());
+    bench.with_output("num_pes", num_pes);
+    bench.with_output("num_threads", num_threads);
+    bench.with_output("global_execution_time_secs", global_time);
 
     let global_mups = ((l_num_updates * num_pes) as f64 / 1_000_000.0) / global_time;
-    bench.with_output("MUPS", global_mups.to_string());
+    bench.with_output("MUPS", global_mups);
 
-    let mb_sent = world.MB_sent();
-    bench.with_output("MB_sent", mb_sent.to_string());
-    bench.with_output("MB_per_sec", (mb_sent / global_time).to_string());
-    bench.with_output("GB_s_injection_rate", (8.0 * (l_num_updates * 2) as f64 * 1.0E-9 / global_time).to_string());
+    bench.with_output("MB_sent", mb_sent);
+    bench.with_output("MB_per_sec", mb_sent / global_time);
+    bench.with_output("GB_s_injection_rate", 8.0 * (l_num_updates * 2) as f64 * 1.0E-9 / global_time);
  
 
     // Optional: sanity metric (sum of counts)
     let pe_sum: u64 = unsafe { counts.as_slice().unwrap().iter().sum::<usize>() as u64 };
-    bench.with_output("pe_sum", pe_sum.to_string());
-
-    if my_pe == 0 {
+    bench.with_output("pe_sum", pe_sum);
+
+    // === Verification: the table holds its own global index, so every gathered value is its index ===
+    let mismatches = unsafe {
+        count_mismatches(target.as_slice().unwrap(), rand_index.as_slice().unwrap())
+    };
+    bench.with_output("gather_mismatches", mismatches);
+
+    if let Some(mut bench) = bench.aggregate(&world) {
+        let total_mismatches = bench.pe_total("gather_mismatches").unwrap_or(0.0) as u64;
+        bench.with_output("total_gather_mismatches", total_mismatches);
+        bench.with_output("verified", total_mismatches == 0);
         println!("Global time: {:.3}s, MUPS: {:.3}", global_time, global_mups);
         bench.write(&benchmark_record::default_output_path("benchmarking"));
     }
+    assert_eq!(mismatches, 0, "PE {my_pe} gathered {mismatches} wrong values");
 }
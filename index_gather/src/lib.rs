@@ -0,0 +1,2 @@
+// helpers shared by the index_gather benchmark binaries
+pub mod verify;
@@ -0,0 +1,13 @@
+// Correctness check run after the timed trials of every index_gather benchmark.
+//
+// Every benchmark fills its table so that the entry at global index `i` holds `i`, so a correct
+// gather returns, at every position, the index it requested.
+
+/// Number of positions `j` where `target[j] != rand_index[j]`.
+pub fn count_mismatches(target: &[usize], rand_index: &[usize]) -> usize {
+    target
+        .iter()
+        .zip(rand_index)
+        .filter(|(gathered, index)| gathered != index)
+        .count()
+}
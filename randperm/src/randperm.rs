@@ -5,6 +5,105 @@ use benchmark_record::BenchmarkInformation;
 
 const DEFAULT_GLOBAL_COUNT: usize = 1000;
 const DEFAULT_TARGET_FACTOR: usize = 10;
+// size of the permutations used by the (optional) uniformity test
+const UNIFORMITY_COUNT: usize = 8;
+
+// empty the target array (usize::MAX marks an empty slot)
+fn reset(world: &LamellarWorld, target_array: &AtomicArray<usize>) {
+    world.block_on(target_array.dist_iter().for_each(|x| x.store(usize::MAX)));
+    world.barrier();
+}
+
+// throw every local dart at a random slot of the target array until they all stick
+fn permute(
+    world: &LamellarWorld,
+    target_array: &AtomicArray<usize>,
+    local_darts: &[usize],
+    rng: &mut StdRng,
+) {
+    let target_len = target_array.len();
+    // launch initial set of darts, and collect any that didn't stick
+    let rand_index = (0..local_darts.len())
+        .map(|_| rng.gen_range(0, target_len)) // rand 0.6 signature
+        .collect::<Vec<usize>>();
+    let mut remaining_darts = world
+        .block_on(target_array.batch_compare_exchange(&rand_index, usize::MAX, local_darts))
+        .iter()
+        .enumerate()
+        .filter_map(|(i, elem)| match elem {
+            Ok(_val) => None,                   // dart stuck
+            Err(_prev) => Some(local_darts[i]), // collision: try again
+        })
+        .collect::<Vec<usize>>();
+
+    // continue launching remaining darts until they all stick
+    while !remaining_darts.is_empty() {
+        let rand_index = (0..remaining_darts.len())
+            .map(|_| rng.gen_range(0, target_len))
+            .collect::<Vec<usize>>();
+
+        remaining_darts = world
+            .block_on(target_array.batch_compare_exchange(&rand_index, usize::MAX, &remaining_darts))
+            .iter()
+            .enumerate()
+            .filter_map(|(i, elem)| match elem {
+                Ok(_val) => None,
+                Err(_prev) => Some(remaining_darts[i]),
+            })
+            .collect::<Vec<usize>>();
+    }
+
+    world.wait_all();
+    world.barrier();
+}
+
+// filter out the empty (MAX) entries and gather the permutation into a ReadOnlyArray
+fn collect(world: &LamellarWorld, target_array: &AtomicArray<usize>) -> ReadOnlyArray<usize> {
+    world.block_on(
+        target_array
+            .dist_iter()
+            .filter_map(|elem| {
+                let elem = elem.load();
+                if elem < usize::MAX { Some(elem) } else { None }
+            })
+            .collect::<ReadOnlyArray<usize>>(lamellar::array::Distribution::Block),
+    )
+}
+
+// collective: check that the collected array holds every value of 0..global_count exactly once,
+// by counting the occurrences of each value in a distributed AtomicArray.
+// Returns the number of local errors: values out of range plus local counters that are not 1.
+fn validate_permutation(
+    world: &LamellarWorld,
+    the_array: &ReadOnlyArray<usize>,
+    global_count: usize,
+) -> usize {
+    let seen = AtomicArray::<usize>::new(
+        world.team(),
+        global_count,
+        lamellar::array::Distribution::Block,
+    )
+    .block();
+    world.block_on(seen.dist_iter().for_each(|x| x.store(0)));
+    world.barrier();
+
+    let (in_range, out_of_range): (Vec<usize>, Vec<usize>) = the_array
+        .local_data()
+        .iter()
+        .partition(|val| **val < global_count);
+    if !in_range.is_empty() {
+        world.block_on(seen.batch_add(in_range.as_slice(), 1));
+    }
+    seen.wait_all();
+    seen.barrier();
+
+    let wrong_counts = seen
+        .local_data()
+        .iter()
+        .filter(|count| count.load() != 1)
+        .count();
+    out_of_range.len() + wrong_counts
+}
 
 fn main() {
     // --- world / args ---
@@ -13,7 +112,7 @@ fn main() {
     let my_pe = world.my_pe();
     let num_pes = world.num_pes();
 
-    // args: <global_count> <target_factor>
+    // args: <global_count> <target_factor> [uniformity_runs]
     let global_count = args
         .get(1)
         .and_then(|s| s.parse::<usize>().ok())
@@ -24,12 +123,18 @@ fn main() {
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(DEFAULT_TARGET_FACTOR);
 
+    // number of small permutations for the uniformity test, 0 disables it
+    let uniformity_runs = args
+        .get(3)
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+
     // --- benchmark record ---
     let mut bench = BenchmarkInformation::new();
-    bench.with_output("num_pes", num_pes.to_string());
-    bench.with_output("global_count", global_count.to_string());
-    bench.with_output("target_factor", target_factor.to_string());
-    bench.with_output("table_size_total", (global_count * target_factor).to_string());
+    bench.with_output("num_pes", num_pes);
+    bench.with_output("global_count", global_count);
+    bench.with_output("target_factor", target_factor);
+    bench.with_output("table_size_total", global_count * target_factor);
 
     // --- array setup ---
     let darts_array = UnsafeArray::<usize>::new(
@@ -71,89 +176,116 @@ fn main() {
     // local darts slice
     let local_darts = darts_array.local_data();
 
-    world.barrier();
-    let now = Instant::now();
-
-    // ====== perform the actual random permute ====== //
-    let rand_index = (0..local_darts.len())
-        .map(|_| rng.gen_range(0, global_count * target_factor)) // rand 0.6 signature
-        .collect::<Vec<usize>>();
-
-    // launch initial set of darts, and collect any that didn't stick
-    let mut remaining_darts = world
-        .block_on(target_array.batch_compare_exchange(&rand_index, usize::MAX, local_darts))
-        .iter()
-        .enumerate()
-        .filter_map(|(i, elem)| match elem {
-            Ok(_val) => None,                   // dart stuck
-            Err(_prev) => Some(local_darts[i]), // collision: try again
-        })
-        .collect::<Vec<usize>>();
-
-    // continue launching remaining darts until they all stick
-    while !remaining_darts.is_empty() {
-        let rand_index = (0..remaining_darts.len())
-            .map(|_| rng.gen_range(0, global_count * target_factor))
-            .collect::<Vec<usize>>();
-
-        remaining_darts = world
-            .block_on(target_array.batch_compare_exchange(&rand_index, usize::MAX, &remaining_darts))
-            .iter()
-            .enumerate()
-            .filter_map(|(i, elem)| match elem {
-                Ok(_val) => None,
-                Err(_prev) => Some(remaining_darts[i]),
-            })
-            .collect::<Vec<usize>>();
-    }
-
-    world.wait_all();
-    world.barrier();
-
-    let permute_secs = now.elapsed().as_secs_f64();
+    let trials = benchmark_record::Runner::from_env().run(
+        || reset(&world, &target_array),
+        || {
+            let mb_start = world.MB_sent();
+            // ====== perform the actual random permute ====== //
+            permute(&world, &target_array, local_darts, &mut rng);
+            world.MB_sent() - mb_start
+        },
+    );
+    trials.record(&mut bench);
+    let (permute_secs, mb_sent) = trials.median_trial();
+    let mb_sent = *mb_sent;
 
     // Collect: filter out MAX entries and gather to a ReadOnlyArray
     let collect_start = Instant::now();
-    let the_array = world.block_on(
-        target_array
-            .dist_iter()
-            .filter_map(|elem| {
-                let elem = elem.load();
-                if elem < usize::MAX { Some(elem) } else { None }
-            })
-            .collect::<ReadOnlyArray<usize>>(lamellar::array::Distribution::Block),
-    );
+    let the_array = collect(&world, &target_array);
     let collect_secs = collect_start.elapsed().as_secs_f64();
 
     // Global metrics
     let global_time = permute_secs; // total permute time measured above
-    bench.with_output("permute_time_secs", format!("{:.6}", permute_secs));
-    bench.with_output("collect_time_secs", format!("{:.6}", collect_secs));
-    bench.with_output("global_time_secs", format!("{:.6}", global_time));
+    bench.with_output("permute_time_secs", permute_secs);
+    bench.with_output("collect_time_secs", collect_secs);
+    bench.with_output("global_time_secs", global_time);
 
     let total_updates = global_count; // one write per element across PEs
     let mups = (total_updates as f64 / 1_000_000.0) / global_time.max(1e-12);
-    bench.with_output("MUPS", format!("{:.6}", mups));
+    bench.with_output("MUPS", mups);
 
-    let mb_sent = world.MB_sent();
-    bench.with_output("MB_sent", format!("{:.6}", mb_sent));
-    bench.with_output("MB_per_sec", format!("{:.6}", mb_sent / global_time.max(1e-12)));
+    bench.with_output("MB_sent", mb_sent);
+    bench.with_output("MB_per_sec", mb_sent / global_time.max(1e-12));
 
     // optional correctness check
     if my_pe == 0 {
-        if let Some(sum) = world.block_on(the_array.sum()) {
-            let expected = (global_count * (global_count + 1) / 2) - global_count; // n(n-1)/2
-            bench.with_output("reduced_sum", (sum as u64).to_string());
-            bench.with_output("expected_sum", (expected as u64).to_string());
-            bench.with_output("sum_match", (sum == expected).to_string());
-        } else {
-            bench.with_output("reduced_sum", "null".into());
-            bench.with_output("expected_sum", "null".into());
-            bench.with_output("sum_match", "false".into());
+        let sum = world.block_on(the_array.sum());
+        let expected = (global_count * (global_count + 1) / 2) - global_count; // n(n-1)/2
+        bench.with_output("reduced_sum", sum.map(|sum| sum as u64));
+        bench.with_output("expected_sum", sum.map(|_| expected as u64));
+        bench.with_output("sum_match", sum == Some(expected));
+    }
+
+    // full validation: every value of 0..global_count appears exactly once
+    bench.with_output("result_len", the_array.len());
+    let permutation_errors = validate_permutation(&world, &the_array, global_count);
+    bench.with_output("permutation_errors", permutation_errors);
+
+    // optional uniformity test: over many small permutations, every value should land in
+    // every position equally often (position-vs-value chi-square test)
+    if uniformity_runs > 0 {
+        let small_darts = UnsafeArray::<usize>::new(
+            world.team(),
+            UNIFORMITY_COUNT,
+            lamellar::array::Distribution::Block,
+        )
+        .block();
+        world.block_on(unsafe {
+            small_darts
+                .dist_iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = i)
+        });
+        let small_darts = small_darts.into_read_only().block();
+        let small_target = AtomicArray::<usize>::new(
+            world.team(),
+            UNIFORMITY_COUNT * target_factor,
+            lamellar::array::Distribution::Block,
+        )
+        .block();
+        let positions = (0..UNIFORMITY_COUNT).collect::<Vec<usize>>();
+
+        // tally[position][value], only used on PE 0
+        let mut tally = vec![vec![0usize; UNIFORMITY_COUNT]; UNIFORMITY_COUNT];
+        for _ in 0..uniformity_runs {
+            reset(&world, &small_target);
+            permute(&world, &small_target, small_darts.local_data(), &mut rng);
+            let perm = collect(&world, &small_target);
+            if my_pe == 0 {
+                let values = world.block_on(perm.batch_load(positions.as_slice()));
+                for (position, value) in values.into_iter().enumerate() {
+                    tally[position][value] += 1;
+                }
+            }
+            world.barrier();
+        }
+
+        if my_pe == 0 {
+            let expected = uniformity_runs as f64 / UNIFORMITY_COUNT as f64;
+            let chi_square = tally
+                .iter()
+                .flatten()
+                .map(|observed| (*observed as f64 - expected).powi(2) / expected)
+                .sum::<f64>();
+            let df = (UNIFORMITY_COUNT - 1) * (UNIFORMITY_COUNT - 1);
+            let p_value = benchmark_record::stats::chi_square_sf(chi_square, df);
+            bench.with_output("uniformity_runs", uniformity_runs);
+            bench.with_output("uniformity_size", UNIFORMITY_COUNT);
+            bench.with_output("uniformity_chi_square", chi_square);
+            bench.with_output("uniformity_df", df);
+            bench.with_output("uniformity_p_value", p_value);
+            bench.with_output("uniform", p_value >= 0.01);
         }
     }
 
-    if my_pe == 0 {
+    if let Some(mut bench) = bench.aggregate(&world) {
+        let total_errors = bench.pe_total("permutation_errors").unwrap_or(0.0) as u64;
+        bench.with_output("total_permutation_errors", total_errors);
+        bench.with_output(
+            "verified",
+            the_array.len() == global_count && total_errors == 0,
+        );
+
         let result_path = benchmark_record::default_output_path("benchmarking");
         println!(
             "PE {my_pe}: permute {:.6}s, collect {:.6}s, MUPS {:.6} -> {:?}",
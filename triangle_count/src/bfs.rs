@@ -0,0 +1,476 @@
+use lamellar::active_messaging::prelude::*;
+use lamellar::darc::prelude::*;
+use lamellar_graph::cli::GraphArgs;
+use lamellar_graph::{Graph, GraphData};
+use rand::prelude::*;
+use std::sync::Mutex;
+use std::time::Instant;
+
+const UNVISITED: u32 = u32::MAX;
+// direction-optimizing switches (Beamer et al.): go bottom-up once the frontier has more than
+// 1 / ALPHA of the unexplored edges, and back top-down once it has fewer than 1 / BETA of the nodes
+const ALPHA: usize = 14;
+const BETA: usize = 24;
+
+// the bfs tree restricted to the local nodes, indexed by position among the local nodes
+struct BfsState {
+    nodes: Vec<u32>,
+    parents: Vec<u32>,
+    levels: Vec<u32>,
+    // local nodes discovered in the current level
+    next: Vec<u32>,
+}
+
+impl BfsState {
+    fn new(nodes: Vec<u32>) -> BfsState {
+        let num_local = nodes.len();
+        BfsState {
+            nodes: nodes,
+            parents: vec![UNVISITED; num_local],
+            levels: vec![UNVISITED; num_local],
+            next: vec![],
+        }
+    }
+
+    fn visit(&mut self, index: usize, parent: u32, level: u32) {
+        if self.parents[index] == UNVISITED {
+            self.parents[index] = parent;
+            self.levels[index] = level;
+            self.next.push(self.nodes[index]);
+        }
+    }
+}
+
+#[lamellar::AmData]
+struct VisitAm {
+    graph: Darc<GraphData>,
+    state: Darc<Mutex<BfsState>>,
+    level: u32,
+    visits: Vec<(u32, u32)>, // (node, parent)
+}
+
+#[lamellar::am]
+impl LamellarAM for VisitAm {
+    async fn exec() {
+        let partitioner = self.graph.partitioner();
+        let mut state = self.state.lock().unwrap();
+        for (node, parent) in &self.visits {
+            state.visit(partitioner.local_index(*node), *parent, self.level);
+        }
+    }
+}
+
+// sets the values of the given nodes, used to share the frontier and the levels with every pe
+#[lamellar::AmData]
+struct NodeValuesAm {
+    values: Darc<Mutex<Vec<u32>>>,
+    node_values: Vec<(u32, u32)>,
+}
+
+#[lamellar::am]
+impl LamellarAM for NodeValuesAm {
+    async fn exec() {
+        let mut values = self.values.lock().unwrap();
+        for (node, value) in &self.node_values {
+            values[*node as usize] = *value;
+        }
+    }
+}
+
+// collective: every pe sets `values[node] = value` on every pe for its `node_values`
+fn all_set(world: &LamellarWorld, values: &Darc<Mutex<Vec<u32>>>, node_values: Vec<(u32, u32)>) {
+    for batch in node_values.chunks(10000) {
+        let _ = world
+            .exec_am_all(NodeValuesAm {
+                values: values.clone(),
+                node_values: batch.to_vec(),
+            })
+            .spawn();
+    }
+    world.wait_all();
+    world.barrier();
+}
+
+// one level expanding the frontier: every frontier node sends its neighbors to their owners
+fn top_down(
+    world: &LamellarWorld,
+    graph: &Graph,
+    state: &Darc<Mutex<BfsState>>,
+    frontier: &[u32],
+    level: u32,
+    buf_size: usize,
+) -> usize {
+    let data = graph.data();
+    let partitioner = graph.partitioner();
+    let mut buffers = vec![vec![]; graph.num_pes()];
+    let mut examined = 0;
+    let send = |pe: usize, visits: Vec<(u32, u32)>| {
+        let _ = world
+            .exec_am_pe(
+                pe,
+                VisitAm {
+                    graph: data.clone(),
+                    state: state.clone(),
+                    level: level,
+                    visits: visits,
+                },
+            )
+            .spawn();
+    };
+    for node in frontier {
+        for neighbor in data.real_neighbors(node) {
+            examined += 1;
+            let pe = partitioner.owner(*neighbor);
+            buffers[pe].push((*neighbor, *node));
+            if buffers[pe].len() >= buf_size {
+                send(pe, std::mem::take(&mut buffers[pe]));
+            }
+        }
+    }
+    for (pe, visits) in buffers.into_iter().enumerate() {
+        if !visits.is_empty() {
+            send(pe, visits);
+        }
+    }
+    world.wait_all();
+    world.barrier();
+    examined
+}
+
+// one level searching a parent in the frontier for every unvisited local node
+fn bottom_up(
+    world: &LamellarWorld,
+    graph: &Graph,
+    state: &Darc<Mutex<BfsState>>,
+    in_frontier: &Darc<Mutex<Vec<u32>>>,
+    frontier: &[u32],
+    level: u32,
+) -> usize {
+    // every pe needs the whole frontier
+    in_frontier.lock().unwrap().fill(0);
+    world.barrier();
+    all_set(
+        world,
+        in_frontier,
+        frontier.iter().map(|n| (*n, 1)).collect(),
+    );
+
+    let data = graph.data();
+    let in_frontier = in_frontier.lock().unwrap();
+    let mut state = state.lock().unwrap();
+    let mut examined = 0;
+    for index in 0..state.nodes.len() {
+        if state.parents[index] != UNVISITED {
+            continue;
+        }
+        for neighbor in data.real_neighbors(&state.nodes[index]) {
+            examined += 1;
+            if in_frontier[*neighbor as usize] != 0 {
+                state.visit(index, *neighbor, level);
+                break;
+            }
+        }
+    }
+    drop((state, in_frontier));
+    world.barrier();
+    examined
+}
+
+struct BfsRun {
+    levels: u32,
+    top_down_levels: u32,
+    bottom_up_levels: u32,
+    // input edges examined while searching
+    examined: usize,
+}
+
+// collective: the bfs tree of `root` in `state`
+fn bfs(
+    world: &LamellarWorld,
+    graph: &Graph,
+    state: &Darc<Mutex<BfsState>>,
+    in_frontier: &Darc<Mutex<Vec<u32>>>,
+    root: u32,
+    direction_optimizing: bool,
+    buf_size: usize,
+) -> BfsRun {
+    let data = graph.data();
+    if graph.node_is_local(&root) {
+        let index = graph.partitioner().local_index(root);
+        state.lock().unwrap().visit(index, root, 0);
+    }
+    let mut run = BfsRun {
+        levels: 0,
+        top_down_levels: 0,
+        bottom_up_levels: 0,
+        examined: 0,
+    };
+    let mut unexplored_edges = 2 * graph.num_edges();
+    let mut is_bottom_up = false;
+    loop {
+        let frontier = std::mem::take(&mut state.lock().unwrap().next);
+        let frontier_size = graph.global_sum(frontier.len());
+        if frontier_size == 0 {
+            break;
+        }
+        let local_edges: usize = frontier.iter().map(|n| data.real_neighbors(n).len()).sum();
+        let frontier_edges = graph.global_sum(local_edges);
+        unexplored_edges = unexplored_edges.saturating_sub(frontier_edges);
+        if direction_optimizing {
+            if !is_bottom_up && frontier_edges > unexplored_edges / ALPHA {
+                is_bottom_up = true;
+            } else if is_bottom_up && frontier_size < graph.num_nodes() / BETA {
+                is_bottom_up = false;
+            }
+        }
+        run.levels += 1;
+        let examined = if is_bottom_up {
+            run.bottom_up_levels += 1;
+            bottom_up(world, graph, state, in_frontier, &frontier, run.levels)
+        } else {
+            run.top_down_levels += 1;
+            top_down(world, graph, state, &frontier, run.levels, buf_size)
+        };
+        run.examined += examined;
+    }
+    run.examined = graph.global_sum(run.examined);
+    run
+}
+
+// collective: check the bfs tree of `root` with the Graph500 rules, returns the number of
+// violations found by this pe (printing the first one)
+fn validate(
+    world: &LamellarWorld,
+    graph: &Graph,
+    state: &Darc<Mutex<BfsState>>,
+    root: u32,
+) -> usize {
+    let levels = Darc::new(world, Mutex::new(vec![UNVISITED; graph.num_nodes()]))
+        .block()
+        .unwrap();
+    let state = state.lock().unwrap();
+    let node_levels = state
+        .nodes
+        .iter()
+        .zip(state.levels.iter())
+        .filter(|(_, level)| **level != UNVISITED)
+        .map(|(node, level)| (*node, *level))
+        .collect();
+    all_set(world, &levels, node_levels);
+
+    let data = graph.data();
+    let levels = levels.lock().unwrap();
+    let mut errors = vec![];
+    for (index, node) in state.nodes.iter().enumerate() {
+        let (parent, level) = (state.parents[index], state.levels[index]);
+        let neighbors = data.real_neighbors(node);
+        if *node == root {
+            // the root is its own parent, at level 0
+            if parent != root || level != 0 {
+                errors.push(format!("root {root} has parent {parent} at level {level}"));
+            }
+        } else if parent != UNVISITED {
+            // tree edges are input edges between consecutive levels (so the tree has no cycle)
+            if neighbors.binary_search(&parent).is_err() {
+                errors.push(format!("tree edge ({node}, {parent}) is not an input edge"));
+            } else if levels[parent as usize] == UNVISITED || levels[parent as usize] + 1 != level {
+                errors.push(format!(
+                    "node {node} at level {level} has parent {parent} at level {}",
+                    levels[parent as usize]
+                ));
+            }
+        }
+        for neighbor in neighbors {
+            let neighbor_level = levels[*neighbor as usize];
+            // the tree spans the connected component, and input edges span at most one level
+            if (level == UNVISITED) != (neighbor_level == UNVISITED) {
+                errors.push(format!(
+                    "edge ({node}, {neighbor}) leaves the tree (levels {level}, {neighbor_level})"
+                ));
+            } else if level != UNVISITED && level.abs_diff(neighbor_level) > 1 {
+                errors.push(format!(
+                    "edge ({node}, {neighbor}) spans levels {level} and {neighbor_level}"
+                ));
+            }
+        }
+    }
+    if let Some(error) = errors.first() {
+        eprintln!(
+            "PE {}: root {root}: {} validation errors, first: {error}",
+            graph.my_pe(),
+            errors.len()
+        );
+    }
+    drop(state);
+    world.barrier();
+    errors.len()
+}
+
+// collective: `num_roots` distinct random nodes with at least one neighbor, the same on every pe.
+// The candidates drawn from the seeded rng are tested in batches with one reduction each, the
+// batches doubling up to `MAX_ROOT_BATCH`, so a graph with few connected nodes needs few
+// collectives.
+fn pick_roots(graph: &Graph, num_roots: usize, seed: u64) -> Vec<u32> {
+    const MAX_ROOT_BATCH: usize = 1 << 16;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut roots = vec![];
+    // give up on graphs with too few connected nodes
+    let max_attempts = match graph.num_nodes() {
+        0 => 0,
+        num_nodes => 64 * num_roots + num_nodes,
+    };
+    let mut attempts = 0;
+    let mut batch_size = 64;
+    while roots.len() < num_roots && attempts < max_attempts {
+        let draws = std::cmp::min(batch_size, max_attempts - attempts);
+        attempts += draws;
+        batch_size = std::cmp::min(2 * batch_size, MAX_ROOT_BATCH);
+        let candidates = (0..draws)
+            .map(|_| rng.gen_range(0, graph.num_nodes()) as u32)
+            .collect::<Vec<_>>();
+        let local_connected = candidates
+            .iter()
+            .map(|c| {
+                (graph.node_is_local(c) && !graph.data().real_neighbors(c).is_empty()) as usize
+            })
+            .collect();
+        let connected = graph.global_sums(local_connected);
+        for (candidate, connected) in candidates.into_iter().zip(connected) {
+            if roots.len() < num_roots && connected > 0 && !roots.contains(&candidate) {
+                roots.push(candidate);
+            }
+        }
+    }
+    roots
+}
+
+const USAGE: &str = "\
+usage: bfs <graph> [--graph map|csr] [--partition <strategy>] [--roots <n>] [--seed <seed>]
+           [--direction-optimizing] [--buf-size <n>] [--no-validate]";
+
+fn main() {
+    let mut args = GraphArgs::new(USAGE);
+    let mut num_roots = 16;
+    let mut seed = 0;
+    let mut direction_optimizing = false;
+    let mut buf_size = 10000;
+    let mut do_validate = true;
+    while let Some(option) = args.next_option() {
+        match option.as_str() {
+            "--roots" => num_roots = args.value("--roots"),
+            "--seed" => seed = args.value("--seed"),
+            "--buf-size" => buf_size = args.value("--buf-size"),
+            "--direction-optimizing" => direction_optimizing = true,
+            "--no-validate" => do_validate = false,
+            _ => args.usage_error(&format!("unexpected argument {option}")),
+        }
+    }
+
+    let world = lamellar::LamellarWorldBuilder::new().build();
+    let my_pe = world.my_pe();
+    let graph = args.load(&world);
+
+    let mut bench = args.benchmark(&graph);
+    bench.with_output("direction_optimizing", direction_optimizing);
+    bench.with_output("buf_size", buf_size);
+    bench.with_output("seed", seed);
+
+    let roots = pick_roots(&graph, num_roots, seed);
+    if roots.len() < num_roots && my_pe == 0 {
+        eprintln!(
+            "only found {} of {num_roots} roots with neighbors",
+            roots.len()
+        );
+    }
+    bench.with_output("num_roots", roots.len());
+
+    let local_nodes = graph.partitioner().local_nodes(my_pe);
+    let in_frontier = Darc::new(&world, Mutex::new(vec![0; graph.num_nodes()]))
+        .block()
+        .unwrap();
+    let mut times = vec![];
+    let mut teps = vec![];
+    let mut validation_errors = 0;
+    let (mut top_down_levels, mut bottom_up_levels) = (0, 0);
+    for root in &roots {
+        let state = Darc::new(&world, Mutex::new(BfsState::new(local_nodes.clone())))
+            .block()
+            .unwrap();
+        world.barrier();
+        let timer = Instant::now();
+        let run = bfs(
+            &world,
+            &graph,
+            &state,
+            &in_frontier,
+            *root,
+            direction_optimizing,
+            buf_size,
+        );
+        let secs = timer.elapsed().as_secs_f64();
+
+        // Graph500: the traversed edges are the input edges of the component of the root
+        let data = graph.data();
+        let local_degrees: usize = {
+            let state = state.lock().unwrap();
+            state
+                .nodes
+                .iter()
+                .zip(state.parents.iter())
+                .filter(|(_, parent)| **parent != UNVISITED)
+                .map(|(node, _)| data.real_neighbors(node).len())
+                .sum()
+        };
+        let edges = graph.global_sum(local_degrees) / 2;
+        let root_teps = edges as f64 / secs.max(1e-12);
+        if do_validate {
+            validation_errors += graph.global_sum(validate(&world, &graph, &state, *root));
+        }
+        if my_pe == 0 {
+            println!(
+                "root {root}: {} levels ({} top-down, {} bottom-up), {edges} edges, {} examined, {secs:.6} s, {root_teps:.3e} TEPS",
+                run.levels, run.top_down_levels, run.bottom_up_levels, run.examined
+            );
+        }
+        top_down_levels += run.top_down_levels;
+        bottom_up_levels += run.bottom_up_levels;
+        times.push(secs);
+        teps.push(root_teps);
+    }
+
+    // Graph500 reports the harmonic mean of the TEPS over the roots
+    let harmonic_mean = if teps.is_empty() {
+        0.0
+    } else {
+        teps.len() as f64 / teps.iter().map(|t| 1.0 / t).sum::<f64>()
+    };
+    if my_pe == 0 {
+        println!("TEPS harmonic mean: {harmonic_mean:.3e}");
+        if do_validate {
+            println!(
+                "validation {}",
+                if validation_errors == 0 {
+                    "passed"
+                } else {
+                    "FAILED"
+                }
+            );
+        }
+    }
+    bench.with_output("bfs_time_secs", times.clone());
+    bench.with_output(
+        "bfs_time_mean_secs",
+        times.iter().sum::<f64>() / times.len().max(1) as f64,
+    );
+    bench.with_output("teps", teps);
+    bench.with_output("teps_harmonic_mean", harmonic_mean);
+    bench.with_output("top_down_levels", top_down_levels);
+    bench.with_output("bottom_up_levels", bottom_up_levels);
+    if do_validate {
+        bench.with_output("validated", validation_errors == 0);
+    }
+
+    if let Some(bench) = bench.aggregate(&world) {
+        bench.write(&benchmark_record::default_output_path("benchmarking"));
+    }
+}
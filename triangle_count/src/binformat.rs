@@ -0,0 +1,357 @@
+// On-disk binary graph format.
+//
+// A graph is written as a manifest file plus one shard file per writing PE (`<path>.<shard>`).
+// Every file is a bincode stream:
+//   header    `BinHeader` (magic, version, graph description, shard index)
+//   records   `Some((node, original label, lower neighbors, weights))`, ... , `None`
+//   checksum  `u64` FNV-1a hash of all the preceding bytes
+// The manifest has no records. A shard holds the neighbors smaller than the node of every node
+// it contains, so every undirected edge is stored exactly once; the loader adds both directions.
+// Nodes without smaller neighbors only get a record if their original label differs, so the
+// graph can still be reported in the labels of the input it was converted from.
+// Version 1 records have no original label, it is the node itself.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::GraphLoadError;
+
+pub const MAGIC: [u8; 8] = *b"LMRGRAPH";
+pub const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BinHeader {
+    pub magic: [u8; 8],
+    pub version: u32,
+    pub num_nodes: u64,
+    /// Number of undirected edges of the whole graph.
+    pub num_edges: u64,
+    /// The labels are already ordered by decreasing degree.
+    pub relabeled: bool,
+    /// The records carry edge weights.
+    pub weighted: bool,
+    /// How the nodes were distributed over the writing PEs, e.g. `cyclic`.
+    pub partitioning: String,
+    pub num_shards: u32,
+    /// `None` for the manifest.
+    pub shard: Option<u32>,
+}
+
+/// `(node, its label in the original input, neighbors smaller than node, their weights)`, the
+/// weights are empty if unweighted.
+pub type Record = (u32, u32, Vec<u32>, Vec<f32>);
+
+// version 1 records, without the original label
+type RecordV1 = (u32, Vec<u32>, Vec<f32>);
+
+impl BinHeader {
+    pub fn new(
+        num_nodes: usize,
+        num_edges: usize,
+        relabeled: bool,
+        weighted: bool,
+        partitioning: &str,
+        num_shards: usize,
+    ) -> BinHeader {
+        BinHeader {
+            magic: MAGIC,
+            version: VERSION,
+            num_nodes: num_nodes as u64,
+            num_edges: num_edges as u64,
+            relabeled: relabeled,
+            weighted: weighted,
+            partitioning: partitioning.to_string(),
+            num_shards: num_shards as u32,
+            shard: None,
+        }
+    }
+
+    /// The header of one of the shards described by this manifest header.
+    pub fn for_shard(&self, shard: usize) -> BinHeader {
+        BinHeader {
+            shard: Some(shard as u32),
+            ..self.clone()
+        }
+    }
+}
+
+/// Path of a shard file of the graph whose manifest is at `path`.
+pub fn shard_path(path: &Path, shard: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{shard}"));
+    PathBuf::from(name)
+}
+
+// 64 bit FNV-1a
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+struct HashingWriter<W: Write> {
+    inner: W,
+    hash: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.hash = fnv1a(self.hash, &buf[..len]);
+        Ok(len)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct HashingReader<R: Read> {
+    inner: R,
+    hash: u64,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.hash = fnv1a(self.hash, &buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Writes one graph file: the header, the records, then the checksum on `finish`.
+pub struct BinWriter<W: Write> {
+    out: HashingWriter<W>,
+}
+
+impl BinWriter<BufWriter<File>> {
+    pub fn create(path: &Path, header: &BinHeader) -> Result<Self, GraphLoadError> {
+        BinWriter::new(BufWriter::new(File::create(path)?), header)
+    }
+}
+
+impl<W: Write> BinWriter<W> {
+    pub fn new(out: W, header: &BinHeader) -> Result<Self, GraphLoadError> {
+        let mut writer = BinWriter {
+            out: HashingWriter {
+                inner: out,
+                hash: FNV_OFFSET,
+            },
+        };
+        bincode::serialize_into(&mut writer.out, header)?;
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, record: &Record) -> Result<(), GraphLoadError> {
+        bincode::serialize_into(&mut self.out, &Some(record))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, GraphLoadError> {
+        bincode::serialize_into(&mut self.out, &None::<Record>)?;
+        let hash = self.out.hash;
+        bincode::serialize_into(&mut self.out.inner, &hash)?;
+        self.out.inner.flush()?;
+        Ok(self.out.inner)
+    }
+}
+
+/// Reads one graph file, validating the header on `new` and the checksum after the last record.
+pub struct BinReader<R: Read> {
+    input: HashingReader<R>,
+    header: BinHeader,
+    done: bool,
+}
+
+impl BinReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, GraphLoadError> {
+        BinReader::new(BufReader::new(File::open(path)?)).map_err(|e| match e {
+            GraphLoadError::InvalidBinary(e) => {
+                GraphLoadError::InvalidBinary(format!("{}: {e}", path.display()))
+            }
+            e => e,
+        })
+    }
+}
+
+impl<R: Read> BinReader<R> {
+    pub fn new(input: R) -> Result<Self, GraphLoadError> {
+        let mut input = HashingReader {
+            inner: input,
+            hash: FNV_OFFSET,
+        };
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(GraphLoadError::InvalidBinary(
+                "not a lamellar graph file (bad magic number)".to_string(),
+            ));
+        }
+        let version: u32 = bincode::deserialize_from(&mut input)?;
+        if version == 0 || version > VERSION {
+            return Err(GraphLoadError::InvalidBinary(format!(
+                "unsupported version {version} (expected 1 to {VERSION})"
+            )));
+        }
+        let rest: BinHeaderRest = bincode::deserialize_from(&mut input)?;
+        let header = BinHeader {
+            magic: magic,
+            version: version,
+            num_nodes: rest.num_nodes,
+            num_edges: rest.num_edges,
+            relabeled: rest.relabeled,
+            weighted: rest.weighted,
+            partitioning: rest.partitioning,
+            num_shards: rest.num_shards,
+            shard: rest.shard,
+        };
+        Ok(BinReader {
+            input: input,
+            header: header,
+            done: false,
+        })
+    }
+
+    pub fn header(&self) -> &BinHeader {
+        &self.header
+    }
+
+    /// The next record, `None` once the checksum has been verified.
+    pub fn next_record(&mut self) -> Result<Option<Record>, GraphLoadError> {
+        if self.done {
+            return Ok(None);
+        }
+        let record: Option<Record> = if self.header.version == 1 {
+            let record: Option<RecordV1> = bincode::deserialize_from(&mut self.input)?;
+            record.map(|(node, neighbors, weights)| (node, node, neighbors, weights))
+        } else {
+            bincode::deserialize_from(&mut self.input)?
+        };
+        if record.is_none() {
+            self.done = true;
+            let expected = self.input.hash;
+            let checksum: u64 = bincode::deserialize_from(&mut self.input.inner)?;
+            if checksum != expected {
+                return Err(GraphLoadError::InvalidBinary(format!(
+                    "checksum mismatch ({checksum:#x} stored, {expected:#x} computed)"
+                )));
+            }
+        }
+        Ok(record)
+    }
+}
+
+// the header fields after the magic number and version, which are checked first so a foreign
+// or newer file is reported as such instead of as a decoding error
+#[derive(Deserialize)]
+struct BinHeaderRest {
+    num_nodes: u64,
+    num_edges: u64,
+    relabeled: bool,
+    weighted: bool,
+    partitioning: String,
+    num_shards: u32,
+    shard: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard_bytes(header: &BinHeader, records: &[Record]) -> Vec<u8> {
+        let mut writer = BinWriter::new(vec![], header).unwrap();
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn read_all(bytes: &[u8]) -> Result<Vec<Record>, GraphLoadError> {
+        let mut reader = BinReader::new(bytes)?;
+        let mut records = vec![];
+        while let Some(record) = reader.next_record()? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let header = BinHeader::new(6, 4, true, true, "cyclic", 2).for_shard(1);
+        let records = vec![
+            (3, 5, vec![0, 1], vec![1.0, 2.5]),
+            (4, 4, vec![2], vec![-1.0]),
+            (5, 0, vec![], vec![]),
+        ];
+        let bytes = shard_bytes(&header, &records);
+        assert_eq!(&bytes[..8], &MAGIC);
+        let reader = BinReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.header(), &header);
+        assert_eq!(read_all(&bytes).unwrap(), records);
+        assert_eq!(shard_path(Path::new("g.bin"), 3), PathBuf::from("g.bin.3"));
+    }
+
+    #[test]
+    fn version_1_records_keep_their_node_as_label() {
+        let mut header = BinHeader::new(4, 3, true, false, "cyclic", 1).for_shard(0);
+        header.version = 1;
+        let records: [RecordV1; 2] = [(2, vec![0, 1], vec![]), (3, vec![2], vec![])];
+        let mut out = HashingWriter {
+            inner: vec![],
+            hash: FNV_OFFSET,
+        };
+        bincode::serialize_into(&mut out, &header).unwrap();
+        for record in records {
+            bincode::serialize_into(&mut out, &Some(record)).unwrap();
+        }
+        bincode::serialize_into(&mut out, &None::<RecordV1>).unwrap();
+        let hash = out.hash;
+        bincode::serialize_into(&mut out.inner, &hash).unwrap();
+        assert_eq!(
+            read_all(&out.inner).unwrap(),
+            vec![(2, 2, vec![0, 1], vec![]), (3, 3, vec![2], vec![])]
+        );
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut bytes = shard_bytes(&BinHeader::new(1, 0, false, false, "cyclic", 1), &[]);
+        bytes[0] = b'X';
+        assert!(matches!(
+            BinReader::new(&bytes[..]),
+            Err(GraphLoadError::InvalidBinary(_))
+        ));
+    }
+
+    #[test]
+    fn future_version_is_rejected() {
+        let mut header = BinHeader::new(1, 0, false, false, "cyclic", 1);
+        header.version = VERSION + 1;
+        let bytes = shard_bytes(&header, &[]);
+        match BinReader::new(&bytes[..]) {
+            Err(GraphLoadError::InvalidBinary(e)) => assert!(e.contains("unsupported version")),
+            _ => panic!("a newer version must be rejected"),
+        }
+    }
+
+    #[test]
+    fn flipped_byte_fails_the_checksum() {
+        let header = BinHeader::new(4, 2, false, false, "block", 1).for_shard(0);
+        let bytes = shard_bytes(&header, &[(2, 2, vec![0, 1], vec![])]);
+        // the low byte of the neighbor 1, before the empty weights (8 byte length), the `None`
+        // marker and the 8 checksum bytes, so the record still decodes
+        let mut corrupted = bytes.clone();
+        let at = bytes.len() - 8 - 1 - 8 - 4;
+        corrupted[at] ^= 1;
+        match read_all(&corrupted) {
+            Err(GraphLoadError::InvalidBinary(e)) => assert!(e.contains("checksum mismatch")),
+            other => panic!("expected a checksum mismatch, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Command line handling shared by the graph benchmarks: the graph and the `--graph` and
+// `--partition` options, usage errors, and loading the graph on every pe.
+
+use benchmark_record::BenchmarkInformation;
+use lamellar::active_messaging::prelude::*;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{Graph, GraphType, LoadOptions};
+
+/// The command line of a graph benchmark, `<graph> [--graph map|csr] [--partition <strategy>]`
+/// followed by the options of the benchmark, which are returned by `next_option`.
+pub struct GraphArgs {
+    usage: &'static str,
+    program: String,
+    args: std::env::Args,
+    pub file: Option<String>,
+    pub graph_type: GraphType,
+    pub options: LoadOptions,
+}
+
+impl GraphArgs {
+    /// The arguments of this process, `usage` is printed with every usage error and for `--help`.
+    pub fn new(usage: &'static str) -> GraphArgs {
+        let mut args = std::env::args();
+        let program = args.next().unwrap_or_default();
+        let program = std::path::Path::new(&program)
+            .file_name()
+            .map_or(program.clone(), |name| name.to_string_lossy().to_string());
+        GraphArgs {
+            usage: usage,
+            program: program,
+            args: args,
+            file: None,
+            graph_type: GraphType::MapGraph,
+            options: LoadOptions::default(),
+        }
+    }
+
+    /// The next option of the benchmark, `None` once every argument has been read. The graph and
+    /// the common options are consumed on the way.
+    pub fn next_option(&mut self) -> Option<String> {
+        while let Some(arg) = self.args.next() {
+            match arg.as_str() {
+                "--graph" => self.graph_type = self.value("--graph"),
+                "--partition" => self.options.partitioning = self.value("--partition"),
+                "-h" | "--help" => {
+                    println!("{}", self.usage);
+                    std::process::exit(0);
+                }
+                _ if !arg.starts_with("--") && self.file.is_none() => self.file = Some(arg),
+                _ => return Some(arg),
+            }
+        }
+        None
+    }
+
+    /// The value following `option`, a usage error if it is missing or invalid.
+    pub fn value<T: FromStr>(&mut self, option: &str) -> T
+    where
+        T::Err: Display,
+    {
+        match self.args.next() {
+            Some(value) => value.parse().unwrap_or_else(|e| {
+                self.usage_error(&format!("invalid value {value} for {option}: {e}"))
+            }),
+            None => self.usage_error(&format!("missing value for {option}")),
+        }
+    }
+
+    /// Print `message` and the usage, and exit.
+    pub fn usage_error(&self, message: &str) -> ! {
+        eprintln!("{}: {message}\n\n{}", self.program, self.usage);
+        std::process::exit(2);
+    }
+
+    /// Collective: load the graph. Every pe gets the same error, so they all exit here with it
+    /// instead of waiting at a barrier.
+    pub fn load(&self, world: &LamellarWorld) -> Graph {
+        let file = match &self.file {
+            Some(file) => file,
+            None => self.usage_error("missing input graph"),
+        };
+        match Graph::with_options(file, self.graph_type, self.options.clone(), world.clone()) {
+            Ok(graph) => {
+                if world.my_pe() == 0 {
+                    println!("num nodes {:?}", graph.num_nodes());
+                }
+                graph
+            }
+            Err(e) => {
+                eprintln!("PE {}: unable to load graph {file}: {e}", world.my_pe());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// A benchmark record holding the size of `graph` and how it is stored.
+    pub fn benchmark(&self, graph: &Graph) -> BenchmarkInformation {
+        let mut bench = BenchmarkInformation::new();
+        bench.with_output("num_pes", graph.num_pes());
+        bench.with_output("num_nodes", graph.num_nodes());
+        bench.with_output("num_edges", graph.num_edges());
+        bench.with_output("graph_type", self.graph_type.name());
+        bench.with_output("partitioner", graph.partitioner().strategy().name());
+        bench
+    }
+}
@@ -0,0 +1,250 @@
+// Per vertex triangle counts and clustering coefficients. The triangle counting kernels attribute
+// every triangle they find to its three vertices in a distributed counter array laid out like the
+// partition (see `Partitioner::aligned_index`), so every pe reads the counts of its own nodes
+// without communication. The nodes are reported with their input labels from the id map kept by
+// the `Graph`, whose `to_original` array uses the same layout.
+
+use crate::intersect::PreparedList;
+use crate::{load, Graph, GraphData, GraphLoadError, Partitioner};
+use lamellar::array::prelude::*;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Collective: a zeroed counter per node, at index `Partitioner::aligned_index(node)`.
+pub fn vertex_counters(graph: &Graph) -> AtomicArray<usize> {
+    let len = graph.num_pes() * graph.partitioner().max_local_nodes().max(1);
+    AtomicArray::<usize>::new(graph.team(), len, Distribution::Block).block()
+}
+
+/// Count the triangles `(node_0, node_1, node_2)` closed by the local nodes `node_1` of
+/// `neighbors` (the lower neighbors of `node_0`, prepared as `neighbors_0`), and add every one
+/// of them to the counts of its three vertices. Returns the number of triangles found.
+pub fn attribute_triangles(
+    graph: &GraphData,
+    node_0: u32,
+    neighbors: &[u32],
+    neighbors_0: &PreparedList,
+    counts: &mut HashMap<u32, usize>,
+) -> usize {
+    let lists = neighbors
+        .iter()
+        .filter(|n| graph.node_is_local(n))
+        .map(|node_1| (*node_1, graph.lower_neighbors(node_1)));
+    attribute(node_0, lists, neighbors_0, counts)
+}
+
+// `attribute_triangles` given the local nodes `node_1` with their lower neighbors
+fn attribute<'a>(
+    node_0: u32,
+    lists: impl Iterator<Item = (u32, &'a [u32])>,
+    neighbors_0: &PreparedList,
+    counts: &mut HashMap<u32, usize>,
+) -> usize {
+    let mut total = 0;
+    for (node_1, neighbors_1) in lists {
+        let mut found = 0;
+        neighbors_0.for_each_common(neighbors_1, |node_2| {
+            *counts.entry(node_2).or_default() += 1;
+            found += 1;
+        });
+        if found > 0 {
+            *counts.entry(node_1).or_default() += found;
+            total += found;
+        }
+    }
+    if total > 0 {
+        *counts.entry(node_0).or_default() += total;
+    }
+    total
+}
+
+/// Add `counts` to the counters of `vertex_counters`, in one batch.
+pub async fn add_counts(
+    counters: &AtomicArray<usize>,
+    partitioner: &Partitioner,
+    counts: HashMap<u32, usize>,
+) {
+    if counts.is_empty() {
+        return;
+    }
+    let (indices, values): (Vec<usize>, Vec<usize>) = counts
+        .into_iter()
+        .map(|(node, count)| (partitioner.aligned_index(node), count))
+        .unzip();
+    counters.batch_add(indices, values).await;
+}
+
+/// Local clustering coefficient of a node: the fraction of the pairs of its `degree` neighbors
+/// that are connected, 0 for nodes with less than two neighbors.
+pub fn local_coefficient(triangles: usize, degree: usize) -> f64 {
+    if degree < 2 {
+        0.0
+    } else {
+        2.0 * triangles as f64 / (degree as f64 * (degree - 1) as f64)
+    }
+}
+
+/// Triangle and wedge (path of length two) totals of the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClusteringSummary {
+    pub triangles: u64,
+    pub wedges: u64,
+}
+
+impl ClusteringSummary {
+    /// Global clustering coefficient (transitivity), `3 * triangles / wedges`, 0 without wedges.
+    pub fn global_coefficient(&self) -> f64 {
+        if self.wedges == 0 {
+            0.0
+        } else {
+            3.0 * self.triangles as f64 / self.wedges as f64
+        }
+    }
+}
+
+/// Collective: the totals of the per vertex counts, and if `path` is given, write one line
+/// `<input label>\t<triangles>\t<local coefficient>` per node to it (`Graph::original_id`, no
+/// lookup leaves the pe), the pes appending their nodes in turn. Either every pe succeeds or every
+/// pe returns an error.
+pub fn vertex_statistics(
+    graph: &Graph,
+    counters: &AtomicArray<usize>,
+    path: Option<&str>,
+) -> Result<ClusteringSummary, GraphLoadError> {
+    let data = graph.data();
+    let local = counters.local_data();
+    let rows = graph
+        .partitioner()
+        .local_nodes(graph.my_pe())
+        .into_iter()
+        .enumerate()
+        .map(|(i, node)| (node, local.at(i).load(), data.real_neighbors(&node).len()))
+        .collect::<Vec<_>>();
+    // every triangle is counted at its three vertices
+    let triangles = graph.global_sum(rows.iter().map(|(_, t, _)| t).sum()) / 3;
+    let wedges = graph.global_sum(
+        rows.iter()
+            .map(|(_, _, d)| d * d.saturating_sub(1) / 2)
+            .sum(),
+    );
+
+    let mut res = Ok(());
+    if let Some(path) = path {
+        let write = || -> Result<(), GraphLoadError> {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(graph.my_pe() == 0)
+                .append(graph.my_pe() != 0)
+                .open(Path::new(path))?;
+            let mut out = BufWriter::new(file);
+            if graph.my_pe() == 0 {
+                writeln!(out, "# vertex\ttriangles\tclustering")?;
+            }
+            for (node, triangles, degree) in &rows {
+                writeln!(
+                    out,
+                    "{}\t{triangles}\t{:.6}",
+                    graph.original_id(node),
+                    local_coefficient(*triangles, *degree)
+                )?;
+            }
+            out.flush()?;
+            Ok(())
+        };
+        for pe in 0..graph.num_pes() {
+            if pe == graph.my_pe() {
+                res = write();
+            }
+            graph.barrier();
+        }
+    }
+    load::agree(&graph.world, res)?;
+    Ok(ClusteringSummary {
+        triangles: triangles as u64,
+        wedges: wedges as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intersect::IntersectKernel;
+    use crate::partition::PartitionStrategy;
+    use crate::reference;
+
+    #[test]
+    fn local_coefficients() {
+        assert_eq!(local_coefficient(0, 0), 0.0);
+        assert_eq!(local_coefficient(0, 1), 0.0);
+        assert_eq!(local_coefficient(0, 2), 0.0);
+        assert_eq!(local_coefficient(1, 2), 1.0);
+        assert_eq!(local_coefficient(3, 4), 0.5);
+    }
+
+    #[test]
+    fn global_coefficients() {
+        let summary = |triangles, wedges| ClusteringSummary { triangles, wedges };
+        assert_eq!(summary(0, 0).global_coefficient(), 0.0);
+        assert_eq!(summary(0, 5).global_coefficient(), 0.0);
+        // a triangle has three wedges, all closed
+        assert_eq!(summary(1, 3).global_coefficient(), 1.0);
+        assert_eq!(summary(1, 6).global_coefficient(), 0.5);
+    }
+
+    #[test]
+    fn vertex_counts_match_the_sequential_lists() {
+        let generator = "rmat:scale=7,ef=8,seed=2".parse().unwrap();
+        let lists = reference::generated_lower_neighbor_lists(&generator);
+        let num_nodes = lists.len();
+        let mut adjacent = vec![vec![]; num_nodes];
+        for (node, lower) in lists.iter().enumerate() {
+            for neighbor in lower {
+                adjacent[node].push(*neighbor);
+                adjacent[*neighbor as usize].push(node as u32);
+            }
+        }
+        // the triangles of a node are the edges between its neighbors
+        let expected = adjacent
+            .iter()
+            .map(|neighbors| {
+                let pairs = neighbors
+                    .iter()
+                    .flat_map(|a| neighbors.iter().map(move |b| (a, b)));
+                pairs
+                    .filter(|(a, b)| a < b && lists[**b as usize].contains(a))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        let triangles = reference::count_triangles(&lists);
+        assert!(triangles > 0);
+        assert_eq!(expected.iter().sum::<usize>() as u64, 3 * triangles);
+
+        // every pe attributes the triangles closed by its local nodes `node_1`
+        let degrees = adjacent.iter().map(|n| n.len() as u32).collect::<Vec<_>>();
+        let partitioner = Partitioner::new(PartitionStrategy::DegreeBalanced, 3, &degrees);
+        for kernel in [IntersectKernel::Merge, IntersectKernel::Hash] {
+            let mut counts = HashMap::new();
+            for pe in 0..3 {
+                for (node_0, neighbors) in lists.iter().enumerate() {
+                    let local = neighbors
+                        .iter()
+                        .filter(|n| partitioner.owns(pe, **n))
+                        .map(|n| (*n, lists[*n as usize].as_slice()));
+                    attribute(
+                        node_0 as u32,
+                        local,
+                        &kernel.prepare(neighbors),
+                        &mut counts,
+                    );
+                }
+            }
+            let counts = (0..num_nodes as u32)
+                .map(|node| counts.get(&node).copied().unwrap_or(0))
+                .collect::<Vec<_>>();
+            assert_eq!(counts, expected, "{}", kernel.name());
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use lamellar::active_messaging::prelude::*;
+use lamellar::array::prelude::*;
+use lamellar::darc::prelude::*;
+use lamellar_graph::cli::GraphArgs;
+use lamellar_graph::{reference, Graph, GraphData};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    // every node whose label dropped sends it to its neighbors until no label changes
+    LabelPropagation,
+    // min hooking of the roots of the endpoints of every edge, then pointer jumping, on a
+    // distributed parent array
+    ShiloachVishkin,
+}
+
+impl Algorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::LabelPropagation => "lp",
+            Algorithm::ShiloachVishkin => "sv",
+        }
+    }
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lp" | "label-propagation" => Ok(Algorithm::LabelPropagation),
+            "sv" | "shiloach-vishkin" | "afforest" => Ok(Algorithm::ShiloachVishkin),
+            _ => Err(format!("unknown algorithm {s} (expected lp or sv)")),
+        }
+    }
+}
+
+// the labels of the local nodes, indexed by position among the local nodes
+struct LabelState {
+    labels: Vec<u32>,
+    changed: Vec<bool>,
+    // local indices of the nodes whose label dropped in the current round
+    next: Vec<usize>,
+}
+
+#[lamellar::AmData]
+struct LabelsAm {
+    graph: Darc<GraphData>,
+    state: Darc<Mutex<LabelState>>,
+    labels: Vec<(u32, u32)>, // (node, label of one of its neighbors)
+}
+
+#[lamellar::am]
+impl LamellarAM for LabelsAm {
+    async fn exec() {
+        let partitioner = self.graph.partitioner();
+        let mut state = self.state.lock().unwrap();
+        for (node, label) in &self.labels {
+            let index = partitioner.local_index(*node);
+            if *label < state.labels[index] {
+                state.labels[index] = *label;
+                if !state.changed[index] {
+                    state.changed[index] = true;
+                    state.next.push(index);
+                }
+            }
+        }
+    }
+}
+
+struct Run {
+    // (node, smallest node of its component) of the local nodes
+    labels: Vec<(u32, u32)>,
+    iterations: usize,
+    jump_iterations: usize,
+}
+
+// collective: min label propagation with buffered active messages
+fn label_propagation(world: &LamellarWorld, graph: &Graph, nodes: &[u32], buf_size: usize) -> Run {
+    let data = graph.data();
+    let partitioner = graph.partitioner();
+    let state = Darc::new(
+        world,
+        Mutex::new(LabelState {
+            labels: nodes.to_vec(),
+            changed: vec![false; nodes.len()],
+            next: vec![],
+        }),
+    )
+    .block()
+    .unwrap();
+    let send = |pe: usize, labels: Vec<(u32, u32)>| {
+        let _ = world
+            .exec_am_pe(
+                pe,
+                LabelsAm {
+                    graph: data.clone(),
+                    state: state.clone(),
+                    labels: labels,
+                },
+            )
+            .spawn();
+    };
+    let mut active = (0..nodes.len()).collect::<Vec<_>>();
+    let mut iterations = 0;
+    while graph.global_sum(active.len()) > 0 {
+        iterations += 1;
+        let labels = {
+            let state = state.lock().unwrap();
+            active
+                .iter()
+                .map(|index| (nodes[*index], state.labels[*index]))
+                .collect::<Vec<_>>()
+        };
+        let mut buffers = vec![vec![]; graph.num_pes()];
+        for (node, label) in labels {
+            for neighbor in data.real_neighbors(&node) {
+                // the label of a node is at most the node, so smaller neighbors keep theirs
+                if *neighbor > label {
+                    let pe = partitioner.owner(*neighbor);
+                    buffers[pe].push((*neighbor, label));
+                    if buffers[pe].len() >= buf_size {
+                        send(pe, std::mem::take(&mut buffers[pe]));
+                    }
+                }
+            }
+        }
+        for (pe, labels) in buffers.into_iter().enumerate() {
+            if !labels.is_empty() {
+                send(pe, labels);
+            }
+        }
+        world.wait_all();
+        world.barrier();
+        let mut state = state.lock().unwrap();
+        active = std::mem::take(&mut state.next);
+        for index in &active {
+            state.changed[*index] = false;
+        }
+    }
+    let state = state.lock().unwrap();
+    Run {
+        labels: nodes
+            .iter()
+            .copied()
+            .zip(state.labels.iter().copied())
+            .collect(),
+        iterations: iterations,
+        jump_iterations: 0,
+    }
+}
+
+// the current parents of `nodes`
+fn load_parents(world: &LamellarWorld, parents: &AtomicArray<u32>, nodes: &[u32]) -> Vec<u32> {
+    if nodes.is_empty() {
+        return vec![];
+    }
+    let indices = nodes.iter().map(|n| *n as usize).collect::<Vec<_>>();
+    world.block_on(parents.batch_load(indices))
+}
+
+// collective: parents[node] = min(parents[node], parent) for every (node, parent)
+fn lower_parents(world: &LamellarWorld, parents: &AtomicArray<u32>, updates: Vec<(u32, u32)>) {
+    if !updates.is_empty() {
+        let (indices, values): (Vec<usize>, Vec<u32>) = updates
+            .into_iter()
+            .map(|(node, parent)| (node as usize, parent))
+            .unzip();
+        world.block_on(parents.batch_fetch_min(indices, values));
+    }
+    world.wait_all();
+    world.barrier();
+}
+
+// collective: Shiloach-Vishkin style hooking and pointer jumping. Every parent is at most its node
+// and only ever decreases, so the parents always form a forest whose roots are the smallest
+// nodes of their trees.
+fn shiloach_vishkin(world: &LamellarWorld, graph: &Graph, nodes: &[u32]) -> Run {
+    let data = graph.data();
+    let parents =
+        AtomicArray::<u32>::new(world.team(), graph.num_nodes(), Distribution::Block).block();
+    if !nodes.is_empty() {
+        let indices = nodes.iter().map(|n| *n as usize).collect::<Vec<_>>();
+        world.block_on(parents.batch_store(indices, nodes.to_vec()));
+    }
+    world.barrier(); // every node starts as its own root
+
+    // every undirected edge once, from its larger endpoint
+    let (upper, lower): (Vec<u32>, Vec<u32>) = nodes
+        .iter()
+        .flat_map(|node| data.lower_neighbors(node).iter().map(|n| (*node, *n)))
+        .unzip();
+
+    let mut iterations = 0;
+    let mut jump_iterations = 0;
+    loop {
+        // hook the larger root of the endpoints of every edge below the smaller one
+        let upper_roots = load_parents(world, &parents, &upper);
+        let lower_roots = load_parents(world, &parents, &lower);
+        let hooks = upper_roots
+            .iter()
+            .zip(lower_roots.iter())
+            .filter(|(root_0, root_1)| root_0 != root_1)
+            .map(|(root_0, root_1)| {
+                (
+                    *std::cmp::max(root_0, root_1),
+                    *std::cmp::min(root_0, root_1),
+                )
+            })
+            .collect::<Vec<_>>();
+        world.barrier(); // everyone has read the roots before they move
+        if graph.global_sum(hooks.len()) == 0 {
+            break;
+        }
+        iterations += 1;
+        lower_parents(world, &parents, hooks);
+
+        // pointer jumping until every node points to its root
+        loop {
+            jump_iterations += 1;
+            let node_parents = load_parents(world, &parents, nodes);
+            let grandparents = load_parents(world, &parents, &node_parents);
+            let jumps = nodes
+                .iter()
+                .zip(node_parents.iter().zip(grandparents.iter()))
+                .filter(|(_, (parent, grandparent))| parent != grandparent)
+                .map(|(node, (_, grandparent))| (*node, *grandparent))
+                .collect::<Vec<_>>();
+            world.barrier(); // everyone has read the parents before they move
+            let num_jumps = graph.global_sum(jumps.len());
+            lower_parents(world, &parents, jumps);
+            if num_jumps == 0 {
+                break;
+            }
+        }
+    }
+    let labels = load_parents(world, &parents, nodes);
+    world.barrier();
+    Run {
+        labels: nodes.iter().copied().zip(labels).collect(),
+        iterations: iterations,
+        jump_iterations: jump_iterations,
+    }
+}
+
+#[lamellar::AmData]
+struct ComponentSizesAm {
+    sizes: Darc<Mutex<HashMap<u32, usize>>>,
+    label_sizes: Vec<(u32, usize)>,
+}
+
+#[lamellar::am]
+impl LamellarAM for ComponentSizesAm {
+    async fn exec() {
+        let mut sizes = self.sizes.lock().unwrap();
+        for (label, size) in &self.label_sizes {
+            *sizes.entry(*label).or_insert(0) += size;
+        }
+    }
+}
+
+// collective: the size of the largest component, the sizes are summed on the owner of the label
+fn largest_component(world: &LamellarWorld, graph: &Graph, labels: &[(u32, u32)]) -> usize {
+    let mut local_sizes: HashMap<u32, usize> = HashMap::new();
+    for (_, label) in labels {
+        *local_sizes.entry(*label).or_insert(0) += 1;
+    }
+    let mut pe_sizes = vec![vec![]; graph.num_pes()];
+    for (label, size) in local_sizes {
+        pe_sizes[graph.partitioner().owner(label)].push((label, size));
+    }
+    let sizes = Darc::new(world, Mutex::new(HashMap::new()))
+        .block()
+        .unwrap();
+    for (pe, label_sizes) in pe_sizes.into_iter().enumerate() {
+        if !label_sizes.is_empty() {
+            let _ = world
+                .exec_am_pe(
+                    pe,
+                    ComponentSizesAm {
+                        sizes: sizes.clone(),
+                        label_sizes: label_sizes,
+                    },
+                )
+                .spawn();
+        }
+    }
+    world.wait_all();
+    world.barrier();
+    let largest = sizes.lock().unwrap().values().copied().max().unwrap_or(0);
+    graph.global_max(largest)
+}
+
+#[lamellar::AmData]
+struct GatherLabelsAm {
+    labels: Darc<Mutex<Vec<u32>>>,
+    node_labels: Vec<(u32, u32)>,
+}
+
+#[lamellar::am]
+impl LamellarAM for GatherLabelsAm {
+    async fn exec() {
+        let mut labels = self.labels.lock().unwrap();
+        for (node, label) in &self.node_labels {
+            labels[*node as usize] = *label;
+        }
+    }
+}
+
+// collective: compare the labels with a sequential union-find on pe 0, `None` on the other pes
+fn verify(world: &LamellarWorld, graph: &Graph, labels: &[(u32, u32)]) -> Option<bool> {
+    let num_labels = if graph.my_pe() == 0 {
+        graph.num_nodes()
+    } else {
+        0
+    };
+    let gathered = Darc::new(world, Mutex::new(vec![u32::MAX; num_labels]))
+        .block()
+        .unwrap();
+    for batch in labels.chunks(10000) {
+        let _ = world
+            .exec_am_pe(
+                0,
+                GatherLabelsAm {
+                    labels: gathered.clone(),
+                    node_labels: batch.to_vec(),
+                },
+            )
+            .spawn();
+    }
+    world.wait_all();
+    world.barrier();
+    let lists = graph.gather_lower_neighbors()?;
+    let expected = reference::component_labels(&lists);
+    let gathered = gathered.lock().unwrap();
+    let mismatches = (0..graph.num_nodes())
+        .filter(|n| gathered[*n] != expected[*n])
+        .collect::<Vec<_>>();
+    if let Some(node) = mismatches.first() {
+        eprintln!(
+            "verification FAILED: {} nodes with a wrong component, e.g. node {node} labeled {} instead of {}",
+            mismatches.len(),
+            gathered[*node],
+            expected[*node]
+        );
+    }
+    Some(mismatches.is_empty())
+}
+
+const USAGE: &str = "\
+usage: connected_components <graph> [--graph map|csr] [--partition <strategy>]
+                            [--algorithm lp|sv] [--buf-size <n>] [--verify]";
+
+fn main() {
+    let mut args = GraphArgs::new(USAGE);
+    let mut algorithm = Algorithm::LabelPropagation;
+    let mut buf_size = 10000;
+    let mut do_verify = false;
+    while let Some(option) = args.next_option() {
+        match option.as_str() {
+            "--algorithm" => algorithm = args.value("--algorithm"),
+            "--buf-size" => buf_size = args.value("--buf-size"),
+            "--verify" => do_verify = true,
+            _ => args.usage_error(&format!("unexpected argument {option}")),
+        }
+    }
+
+    let world = lamellar::LamellarWorldBuilder::new().build();
+    let my_pe = world.my_pe();
+    let graph = args.load(&world);
+
+    let mut bench = args.benchmark(&graph);
+    bench.with_output("algorithm", algorithm.name());
+    if algorithm == Algorithm::LabelPropagation {
+        bench.with_output("buf_size", buf_size);
+    }
+
+    let nodes = graph.partitioner().local_nodes(my_pe);
+    world.barrier();
+    let timer = Instant::now();
+    let run = match algorithm {
+        Algorithm::LabelPropagation => label_propagation(&world, &graph, &nodes, buf_size),
+        Algorithm::ShiloachVishkin => shiloach_vishkin(&world, &graph, &nodes),
+    };
+    let secs = timer.elapsed().as_secs_f64();
+
+    // every component is labeled by its smallest node
+    let local_components = run.labels.iter().filter(|(n, label)| n == label).count();
+    let components = graph.global_sum(local_components);
+    let largest = largest_component(&world, &graph, &run.labels);
+    if my_pe == 0 {
+        println!(
+            "{components} components, largest {largest} nodes, {} iterations, {secs:.6} s",
+            run.iterations
+        );
+    }
+    bench.with_output("components", components);
+    bench.with_output("largest_component", largest);
+    bench.with_output("iterations", run.iterations);
+    if algorithm == Algorithm::ShiloachVishkin {
+        bench.with_output("jump_iterations", run.jump_iterations);
+    }
+    bench.with_output("time_secs", secs);
+    bench.with_output("MB_sent", world.MB_sent());
+
+    if do_verify {
+        let check = Instant::now();
+        if let Some(verified) = verify(&world, &graph, &run.labels) {
+            if verified {
+                println!(
+                    "verification passed ({:.3} s)",
+                    check.elapsed().as_secs_f64()
+                );
+            }
+            bench.with_output("verified", verified);
+        }
+    }
+
+    if let Some(bench) = bench.aggregate(&world) {
+        bench.write(&benchmark_record::default_output_path("benchmarking"));
+    }
+}
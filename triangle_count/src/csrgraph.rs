@@ -0,0 +1,159 @@
+use lamellar::memregion::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::partition::Partitioner;
+use crate::GraphOps;
+
+/// Compressed sparse row graph: every PE stores the neighbor lists of its local nodes (in the
+/// order of their local index in the partitioner) in one offsets array and one contiguous
+/// neighbor array in RDMA registered memory.
+///
+/// While the graph is being distributed the neighbor lists of the local nodes are staged as plain
+/// vectors with `stage_neighbors`, `finalize` packs them into the CSR arrays. The offsets and the
+/// handle to the neighbor array of the other PEs are then registered with `add_remote_shard`, so
+/// `lamellar_neighbors` works for any node without communication: every PE keeps a copy of all
+/// the offsets (one `usize` per node). No registered memory is allocated per node or per lookup.
+pub struct CsrGraph {
+    team: Arc<LamellarTeam>,
+    num_nodes: usize,
+    staged: HashMap<u32, Vec<u32>>,
+    local_nodes: Vec<u32>,
+    partitioner: Arc<Partitioner>,
+    // (offsets, neighbors) of every pe, indexed by pe
+    shards: Vec<Option<(Vec<usize>, OneSidedMemoryRegion<u32>)>>,
+}
+
+pub struct CsrGraphIter<'a> {
+    iter: std::slice::Iter<'a, u32>,
+}
+
+impl CsrGraph {
+    pub fn new(team: Arc<LamellarTeam>) -> CsrGraph {
+        let num_pes = team.num_pes();
+        CsrGraph {
+            team: team,
+            num_nodes: 0,
+            staged: HashMap::new(),
+            local_nodes: vec![],
+            partitioner: Arc::new(Partitioner::cyclic(num_pes)),
+            shards: vec![None; num_pes],
+        }
+    }
+
+    /// Stage the sorted neighbor list of a local node, until `finalize`.
+    pub fn stage_neighbors(&mut self, node: u32, neighbors: Vec<u32>) {
+        self.staged.insert(node, neighbors);
+    }
+
+    /// Iterates over the nodes local to this PE, in increasing order.
+    pub fn iter(&self) -> CsrGraphIter<'_> {
+        CsrGraphIter {
+            iter: self.local_nodes.iter(),
+        }
+    }
+
+    /// Pack the staged neighbor lists into the CSR arrays, returning the offsets and the handle to
+    /// the neighbor array to share with the other PEs. Must be called once every node has been
+    /// added.
+    pub fn finalize(&mut self) -> (Vec<usize>, OneSidedMemoryRegion<u32>) {
+        let my_pe = self.team.team_pe_id().unwrap();
+        self.local_nodes = self.partitioner.local_nodes(my_pe);
+
+        let mut offsets = Vec::with_capacity(self.local_nodes.len() + 1);
+        offsets.push(0);
+        for node in &self.local_nodes {
+            let len = self.staged.get(node).map_or(0, |n| n.len());
+            offsets.push(offsets[offsets.len() - 1] + len);
+        }
+        let num_neighbors = offsets[offsets.len() - 1];
+
+        let lmr_neighbors = self
+            .team
+            .alloc_one_sided_mem_region::<u32>(std::cmp::max(num_neighbors, 1));
+        unsafe {
+            let neigh_slice = lmr_neighbors.as_mut_slice().unwrap();
+            for (i, node) in self.local_nodes.iter().enumerate() {
+                if let Some(neighs) = self.staged.remove(node) {
+                    neigh_slice[offsets[i]..offsets[i + 1]].copy_from_slice(&neighs);
+                }
+            }
+        }
+        self.staged = HashMap::new();
+        self.shards[my_pe] = Some((offsets.clone(), lmr_neighbors.clone()));
+        (offsets, lmr_neighbors)
+    }
+
+    /// Register the offsets and the neighbor array of another PE.
+    pub fn add_remote_shard(
+        &mut self,
+        pe: usize,
+        offsets: Vec<usize>,
+        neighbors: OneSidedMemoryRegion<u32>,
+    ) {
+        self.shards[pe] = Some((offsets, neighbors));
+    }
+
+    fn local_index(&self, node: &u32) -> usize {
+        self.partitioner.local_index(*node)
+    }
+}
+
+impl GraphOps for CsrGraph {
+    fn neighbors(&self, node: &u32) -> std::slice::Iter<'_, u32> {
+        if !self.node_is_local(node) {
+            panic!(
+                "node {:?} is not local to pe {:?}",
+                node,
+                self.team.world_pe_id()
+            );
+        }
+        if *node as usize >= self.num_nodes {
+            panic!("node {:?} does not exist in graph", node);
+        }
+        let my_pe = self.team.team_pe_id().unwrap();
+        let (offsets, neighbors) = match &self.shards[my_pe] {
+            Some((offsets, neighbors)) => (offsets, unsafe { neighbors.as_slice().unwrap() }),
+            None => panic!("csr graph has not been finalized"),
+        };
+        let i = self.local_index(node);
+        neighbors[offsets[i]..offsets[i + 1]].iter()
+    }
+    fn lamellar_neighbors(&self, node: &u32) -> OneSidedMemoryRegion<u32> {
+        if *node as usize >= self.num_nodes {
+            panic!("node {:?} does not exist in graph", node);
+        }
+        let pe = self.partitioner.owner(*node);
+        let (offsets, neighbors) = self.shards[pe]
+            .as_ref()
+            .expect("csr graph has not been finalized");
+        let i = self.local_index(node);
+        neighbors.sub_region(offsets[i]..offsets[i + 1])
+    }
+
+    fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    fn node_is_local(&self, node: &u32) -> bool {
+        self.partitioner
+            .owns(self.team.team_pe_id().unwrap(), *node)
+    }
+
+    fn set_partitioner(&mut self, partitioner: Arc<Partitioner>) {
+        // the partitioner covers every node, so the other pes never announce theirs
+        self.num_nodes = partitioner.num_nodes();
+        self.partitioner = partitioner;
+    }
+
+    fn partitioner(&self) -> &Partitioner {
+        &self.partitioner
+    }
+}
+
+impl<'a> Iterator for CsrGraphIter<'a> {
+    type Item = &'a u32;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
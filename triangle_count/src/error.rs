@@ -0,0 +1,100 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Why a graph could not be loaded. `Graph::new` returns the same outcome on every PE: a PE that
+/// failed reports its own error, the others report `OtherPe` with the message of the lowest
+/// failing PE.
+#[derive(Debug)]
+pub enum GraphLoadError {
+    /// The file extension is not one of the supported formats.
+    UnknownFormat(PathBuf),
+    /// A line that could not be parsed (line numbers start at 1).
+    MalformedLine {
+        path: PathBuf,
+        line: usize,
+        content: String,
+    },
+    /// A MatrixMarket header with a different number of rows and columns.
+    NonSquareHeader {
+        rows: usize,
+        cols: usize,
+    },
+    /// A MatrixMarket file that is not a square coordinate matrix with a supported field and
+    /// symmetry, or whose entry count does not match its header.
+    InvalidMatrixMarket(String),
+    /// A node id (as written in the input) outside of the declared number of nodes.
+    VertexOutOfRange {
+        vertex: usize,
+        num_nodes: usize,
+    },
+    /// A binary graph file that does not decode.
+    InvalidBinary(String),
+    /// An invalid `gen:` graph generator argument.
+    InvalidGenerator(String),
+    Io(std::io::Error),
+    /// Loading failed on another PE.
+    OtherPe {
+        pe: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for GraphLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphLoadError::UnknownFormat(path) => write!(
+                f,
+                "unknown graph format for {} (expected .tsv, .mm, .el, .edges, .txt or .bin)",
+                path.display()
+            ),
+            GraphLoadError::MalformedLine {
+                path,
+                line,
+                content,
+            } => write!(
+                f,
+                "{}:{line}: malformed line {:?}",
+                path.display(),
+                content.trim_end()
+            ),
+            GraphLoadError::NonSquareHeader { rows, cols } => {
+                write!(f, "matrix is not square ({rows} rows, {cols} columns)")
+            }
+            GraphLoadError::InvalidMatrixMarket(e) => write!(f, "invalid MatrixMarket file: {e}"),
+            GraphLoadError::VertexOutOfRange { vertex, num_nodes } => write!(
+                f,
+                "vertex {vertex} is out of range (the graph has {num_nodes} nodes)"
+            ),
+            GraphLoadError::InvalidBinary(e) => write!(f, "invalid binary graph: {e}"),
+            GraphLoadError::InvalidGenerator(e) => write!(f, "invalid graph generator: {e}"),
+            GraphLoadError::Io(e) => write!(f, "{e}"),
+            GraphLoadError::OtherPe { pe, message } => {
+                write!(f, "loading failed on pe {pe}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphLoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GraphLoadError {
+    fn from(e: std::io::Error) -> Self {
+        GraphLoadError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for GraphLoadError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => GraphLoadError::Io(e),
+            e => GraphLoadError::InvalidBinary(e.to_string()),
+        }
+    }
+}
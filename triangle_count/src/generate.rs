@@ -0,0 +1,328 @@
+use rand::prelude::*;
+
+/// Random edges are generated in chunks of this many edges, each chunk with its own rng seeded
+/// from the generator seed and the chunk index, so the generated graph does not depend on the
+/// number of PEs (PE `p` generates the chunks `p, p + num_pes, ...`).
+pub const EDGES_PER_CHUNK: usize = 1 << 16;
+
+/// A deterministic synthetic graph, selected with a `gen:<kind>:<key>=<value>,...` graph argument:
+/// - `gen:rmat:scale=18,ef=16[,a=0.57,b=0.19,c=0.19][,seed=0]` (also `kronecker`/`graph500`)
+/// - `gen:er:n=100000,m=1600000[,seed=0]` or `gen:er:n=100000,p=0.0001`
+/// - `gen:lattice:rows=100[,cols=100][,diagonals=true]`
+/// - `gen:clique:n=100`
+///
+/// Self loops and duplicate edges are dropped when the graph is built.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Generator {
+    /// R-MAT / Graph500 Kronecker graph with `2^scale` nodes and `edge_factor * 2^scale` edges.
+    /// The defaults are the Graph500 initiator probabilities (d = 1 - a - b - c).
+    Rmat {
+        scale: u32,
+        edge_factor: usize,
+        a: f64,
+        b: f64,
+        c: f64,
+        seed: u64,
+    },
+    /// Erdős–Rényi G(n, m): `edges` edges with uniformly random endpoints.
+    ErdosRenyi {
+        nodes: usize,
+        edges: usize,
+        seed: u64,
+    },
+    /// `rows x cols` grid, each cell optionally split by a diagonal (a triangular lattice).
+    Lattice {
+        rows: usize,
+        cols: usize,
+        diagonals: bool,
+    },
+    /// Complete graph on `nodes` nodes.
+    Clique { nodes: usize },
+}
+
+impl Generator {
+    pub fn num_nodes(&self) -> usize {
+        match self {
+            Generator::Rmat { scale, .. } => 1 << scale,
+            Generator::ErdosRenyi { nodes, .. } => *nodes,
+            Generator::Lattice { rows, cols, .. } => rows * cols,
+            Generator::Clique { nodes } => *nodes,
+        }
+    }
+
+    /// Number of independent chunks of edges.
+    pub fn num_chunks(&self) -> usize {
+        match self {
+            Generator::Rmat {
+                scale, edge_factor, ..
+            } => (edge_factor << scale).div_ceil(EDGES_PER_CHUNK),
+            Generator::ErdosRenyi { edges, .. } => edges.div_ceil(EDGES_PER_CHUNK),
+            Generator::Lattice { rows, .. } => *rows, // one row per chunk
+            Generator::Clique { nodes } => *nodes,    // one node per chunk
+        }
+    }
+
+    /// The edges of one chunk (undirected, possibly with self loops and duplicates).
+    pub fn edges(&self, chunk: usize) -> Vec<(u32, u32)> {
+        match self {
+            Generator::Rmat {
+                scale,
+                edge_factor,
+                a,
+                b,
+                c,
+                seed,
+            } => {
+                let mut rng = chunk_rng(*seed, chunk);
+                (0..chunk_len(edge_factor << scale, chunk))
+                    .map(|_| {
+                        let (mut e0, mut e1) = (0u32, 0u32);
+                        for _ in 0..*scale {
+                            let r: f64 = rng.gen();
+                            e0 <<= 1;
+                            e1 <<= 1;
+                            if r < *a {
+                            } else if r < a + b {
+                                e1 |= 1;
+                            } else if r < a + b + c {
+                                e0 |= 1;
+                            } else {
+                                e0 |= 1;
+                                e1 |= 1;
+                            }
+                        }
+                        (e0, e1)
+                    })
+                    .collect()
+            }
+            Generator::ErdosRenyi { nodes, edges, seed } => {
+                let mut rng = chunk_rng(*seed, chunk);
+                (0..chunk_len(*edges, chunk))
+                    .map(|_| {
+                        (
+                            rng.gen_range(0, *nodes) as u32,
+                            rng.gen_range(0, *nodes) as u32,
+                        )
+                    })
+                    .collect()
+            }
+            Generator::Lattice {
+                rows,
+                cols,
+                diagonals,
+            } => {
+                let node = |r: usize, c: usize| (r * cols + c) as u32;
+                let r = chunk;
+                let mut edges = vec![];
+                for c in 0..*cols {
+                    if c + 1 < *cols {
+                        edges.push((node(r, c), node(r, c + 1)));
+                    }
+                    if r + 1 < *rows {
+                        edges.push((node(r, c), node(r + 1, c)));
+                        if *diagonals && c + 1 < *cols {
+                            edges.push((node(r, c), node(r + 1, c + 1)));
+                        }
+                    }
+                }
+                edges
+            }
+            Generator::Clique { nodes } => ((chunk + 1)..*nodes)
+                .map(|other| (chunk as u32, other as u32))
+                .collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for Generator {
+    type Err = String;
+
+    /// Parse `<kind>:<key>=<value>,...` (the graph argument without the `gen:` prefix).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+        let mut values = std::collections::HashMap::new();
+        for param in params.split(',').filter(|p| !p.is_empty()) {
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| format!("expected <key>=<value> in generator parameter {param}"))?;
+            values.insert(key.trim(), value.trim());
+        }
+        let mut params = Params { kind, values };
+        let generator = match kind {
+            "rmat" | "kronecker" | "graph500" => Generator::Rmat {
+                scale: params.required("scale")?,
+                edge_factor: params.optional("ef", 16)?,
+                a: params.optional("a", 0.57)?,
+                b: params.optional("b", 0.19)?,
+                c: params.optional("c", 0.19)?,
+                seed: params.optional("seed", 0)?,
+            },
+            "er" | "erdos_renyi" => {
+                let nodes: usize = params.required("n")?;
+                let edges = match params.optional::<f64>("p", -1.0)? {
+                    p if p >= 0.0 => {
+                        (p * nodes as f64 * (nodes as f64 - 1.0) / 2.0).round() as usize
+                    }
+                    _ => params.required("m")?,
+                };
+                Generator::ErdosRenyi {
+                    nodes,
+                    edges,
+                    seed: params.optional("seed", 0)?,
+                }
+            }
+            "lattice" | "grid" => {
+                let rows = params.required("rows")?;
+                Generator::Lattice {
+                    rows,
+                    cols: params.optional("cols", rows)?,
+                    diagonals: params.optional("diagonals", false)?,
+                }
+            }
+            "clique" | "complete" => Generator::Clique {
+                nodes: params.required("n")?,
+            },
+            _ => {
+                return Err(format!(
+                    "unknown graph generator {kind} (expected rmat, er, lattice or clique)"
+                ))
+            }
+        };
+        params.finish()?;
+        if let Generator::Rmat { scale, a, b, c, .. } = &generator {
+            if *scale > 31 || a + b + c > 1.0 {
+                return Err(format!("invalid rmat parameters in {s}"));
+            }
+        }
+        if let Generator::ErdosRenyi { nodes, edges, .. } = &generator {
+            if *nodes == 0 && *edges > 0 {
+                return Err(format!(
+                    "invalid er parameters in {s} (edges without nodes)"
+                ));
+            }
+        }
+        if generator.num_nodes() > u32::MAX as usize {
+            return Err(format!("too many nodes in {s}"));
+        }
+        Ok(generator)
+    }
+}
+
+struct Params<'a> {
+    kind: &'a str,
+    values: std::collections::HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Params<'a> {
+    fn optional<T: std::str::FromStr>(&mut self, key: &str, default: T) -> Result<T, String> {
+        match self.values.remove(key) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid value {value} for {}:{key}", self.kind)),
+            None => Ok(default),
+        }
+    }
+    fn required<T: std::str::FromStr>(&mut self, key: &str) -> Result<T, String> {
+        match self.values.remove(key) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid value {value} for {}:{key}", self.kind)),
+            None => Err(format!(
+                "missing parameter {key} for generator {}",
+                self.kind
+            )),
+        }
+    }
+    fn finish(self) -> Result<(), String> {
+        match self.values.keys().next() {
+            Some(key) => Err(format!(
+                "unknown parameter {key} for generator {}",
+                self.kind
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn chunk_rng(seed: u64, chunk: usize) -> StdRng {
+    SeedableRng::seed_from_u64((seed << 32) ^ chunk as u64)
+}
+
+// number of edges in a chunk of a random graph with `total` edges
+fn chunk_len(total: usize, chunk: usize) -> usize {
+    std::cmp::min(
+        EDGES_PER_CHUNK,
+        total.saturating_sub(chunk * EDGES_PER_CHUNK),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(s: &str) -> String {
+        s.parse::<Generator>().unwrap_err()
+    }
+
+    #[test]
+    fn parses_generators() {
+        assert_eq!(
+            "er:n=10,m=20,seed=3".parse(),
+            Ok(Generator::ErdosRenyi {
+                nodes: 10,
+                edges: 20,
+                seed: 3
+            })
+        );
+        assert_eq!(
+            "grid:rows=2".parse(),
+            Ok(Generator::Lattice {
+                rows: 2,
+                cols: 2,
+                diagonals: false
+            })
+        );
+        assert_eq!("er:n=0,m=0".parse::<Generator>().unwrap().num_chunks(), 0);
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        assert!(parse_err("foo:n=3").contains("unknown graph generator"));
+        assert!(parse_err("clique:n").contains("expected <key>=<value>"));
+        assert!(parse_err("clique:n=x").contains("invalid value x"));
+        assert!(parse_err("clique:m=3").contains("missing parameter n"));
+        assert!(parse_err("clique:n=3,x=1").contains("unknown parameter x"));
+        assert!(parse_err("rmat:scale=32").contains("invalid rmat parameters"));
+        assert!(parse_err("rmat:scale=4,a=0.5,b=0.3,c=0.3").contains("invalid rmat parameters"));
+        assert!(parse_err("er:n=0,m=5").contains("invalid er parameters"));
+        assert!(parse_err("lattice:rows=65536,cols=65537").contains("too many nodes"));
+    }
+
+    #[test]
+    fn edges_do_not_depend_on_the_chunk_assignment() {
+        let generator: Generator = "er:n=1000,m=200000,seed=7".parse().unwrap();
+        let num_chunks = generator.num_chunks();
+        assert_eq!(num_chunks, 4);
+        // the chunks of `num_pes` pes, pe `p` generating the chunks `p, p + num_pes, ...`
+        let edges_on = |num_pes: usize| {
+            let mut edges = (0..num_pes)
+                .flat_map(|pe| (pe..num_chunks).step_by(num_pes))
+                .flat_map(|chunk| generator.edges(chunk))
+                .collect::<Vec<_>>();
+            edges.sort_unstable();
+            edges
+        };
+        let edges = edges_on(1);
+        assert_eq!(edges.len(), 200000);
+        assert_eq!(edges, edges_on(3));
+        assert_eq!(edges, edges_on(8));
+        assert_ne!(edges, {
+            let other: Generator = "er:n=1000,m=200000,seed=8".parse().unwrap();
+            let mut edges = (0..num_chunks)
+                .flat_map(|chunk| other.edges(chunk))
+                .collect::<Vec<_>>();
+            edges.sort_unstable();
+            edges
+        });
+    }
+}
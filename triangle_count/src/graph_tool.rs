@@ -0,0 +1,337 @@
+use lamellar::active_messaging::prelude::*;
+use lamellar::darc::prelude::*;
+use lamellar_graph::{Graph, GraphType, LoadOptions};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+const USAGE: &str = "\
+usage: graph_tool <command> <graph> [<output>] [--weights] [--keep-labels] [--partition <strategy>]
+                  [--graph map|csr]
+
+commands:
+  convert <graph> <output>     write the graph in the format of the output extension, keeping the node ids
+  relabel <graph> <output>     convert, relabeling the nodes by decreasing degree
+  symmetrize <graph> <output>  convert, writing both directions of every edge
+  stats <graph>                print node, edge and degree statistics and the per-PE load
+
+<graph> is any graph accepted by the benchmarks (.tsv, .mm, .el/.edges/.txt, .bin or gen:...).
+Loading always drops self loops and duplicate edges. Output formats: .bin, .mm, .tsv and
+.el/.edges/.txt (0-based `src dst` rows).
+  --weights      keep the edge weights of MatrixMarket and binary graphs
+  --keep-labels  stats: report the load of the input labeling instead of the degree ordering
+  --partition    cyclic (default), block, degree or edge: the partitioning used while loading,
+                 recorded in .bin outputs and reported by stats";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Convert,
+    Relabel,
+    Symmetrize,
+    Stats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Bin,
+    MatrixMarket,
+    Tsv,
+    EdgeList,
+}
+
+impl OutputFormat {
+    fn from_path(path: &str) -> Option<OutputFormat> {
+        match Path::new(path).extension()?.to_str()? {
+            "bin" => Some(OutputFormat::Bin),
+            "mm" => Some(OutputFormat::MatrixMarket),
+            "tsv" => Some(OutputFormat::Tsv),
+            "el" | "edges" | "txt" => Some(OutputFormat::EdgeList),
+            _ => None,
+        }
+    }
+}
+
+// the real neighbors of a local node (without the placeholder of nodes without neighbors) and
+// their weights
+fn edges_of<'a>(graph: &'a Graph, node: &u32) -> impl Iterator<Item = (u32, Option<f32>)> + 'a {
+    let num_nodes = graph.num_nodes() as u32;
+    let weights = graph.weights(node);
+    graph
+        .neighbors(node)
+        .copied()
+        .filter(move |n| *n < num_nodes)
+        .enumerate()
+        .map(move |(i, n)| (n, weights.map(|w| w[i])))
+}
+
+fn local_nodes(graph: &Graph) -> impl Iterator<Item = u32> + '_ {
+    (0..graph.num_nodes() as u32).filter(|n| graph.node_is_local(n))
+}
+
+// write this pe's part of a text graph, every edge once (lower neighbors) or in both directions
+fn write_text(
+    graph: &Graph,
+    out: &mut impl Write,
+    format: OutputFormat,
+    both_directions: bool,
+) -> std::io::Result<()> {
+    // an adjacency listing always has both directions
+    let both_directions = both_directions || format == OutputFormat::Tsv;
+    for node in local_nodes(graph) {
+        for (neighbor, weight) in edges_of(graph, &node) {
+            if !both_directions && neighbor > node {
+                break; // neighbors are sorted
+            }
+            match (format, weight) {
+                (OutputFormat::MatrixMarket, Some(w)) => {
+                    writeln!(out, "{} {} {w}", node + 1, neighbor + 1)?
+                }
+                (OutputFormat::MatrixMarket, None) => {
+                    writeln!(out, "{} {}", node + 1, neighbor + 1)?
+                }
+                (OutputFormat::Tsv, _) => writeln!(out, "{}\t{}", neighbor + 1, node + 1)?,
+                (OutputFormat::EdgeList, Some(w)) => writeln!(out, "{neighbor} {node} {w}")?,
+                (OutputFormat::EdgeList, None) => writeln!(out, "{neighbor} {node}")?,
+                (OutputFormat::Bin, _) => unreachable!("binary graphs are written by dump_to_bin"),
+            }
+        }
+    }
+    Ok(())
+}
+
+// collective: the pes append their part of the file in turn, pe 0 truncates it and writes the header
+fn save_text(
+    graph: &Graph,
+    path: &str,
+    format: OutputFormat,
+    both_directions: bool,
+) -> std::io::Result<()> {
+    let mut res = Ok(());
+    for pe in 0..graph.num_pes() {
+        if pe == graph.my_pe() {
+            res = (|| {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(pe != 0)
+                    .truncate(pe == 0)
+                    .open(path)?;
+                let mut out = BufWriter::new(file);
+                if pe == 0 && format == OutputFormat::MatrixMarket {
+                    let field = if graph.is_weighted() {
+                        "real"
+                    } else {
+                        "pattern"
+                    };
+                    let (symmetry, entries) = if both_directions {
+                        ("general", 2 * graph.num_edges())
+                    } else {
+                        ("symmetric", graph.num_edges())
+                    };
+                    writeln!(out, "%%MatrixMarket matrix coordinate {field} {symmetry}")?;
+                    writeln!(out, "{} {} {entries}", graph.num_nodes(), graph.num_nodes())?;
+                }
+                write_text(graph, &mut out, format, both_directions)?;
+                out.flush()
+            })();
+        }
+        graph.barrier();
+    }
+    res
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct PeStats {
+    nodes: usize,
+    // directed entries, i.e. the sum of the degrees
+    entries: usize,
+    max_degree: usize,
+    // histogram[0] counts degree 0, histogram[k] degrees in [2^(k-1), 2^k)
+    histogram: Vec<usize>,
+}
+
+#[lamellar::AmData]
+struct StatsAM {
+    stats: Darc<Mutex<Vec<PeStats>>>,
+    pe: usize,
+    local: PeStats,
+}
+
+#[lamellar::am]
+impl LamellarAM for StatsAM {
+    async fn exec() {
+        self.stats.lock().unwrap()[self.pe] = self.local.clone();
+    }
+}
+
+fn local_stats(graph: &Graph) -> PeStats {
+    let mut stats = PeStats::default();
+    for node in local_nodes(graph) {
+        let degree = edges_of(graph, &node).count();
+        let bucket = (usize::BITS - degree.leading_zeros()) as usize;
+        if stats.histogram.len() <= bucket {
+            stats.histogram.resize(bucket + 1, 0);
+        }
+        stats.histogram[bucket] += 1;
+        stats.nodes += 1;
+        stats.entries += degree;
+        stats.max_degree = std::cmp::max(stats.max_degree, degree);
+    }
+    stats
+}
+
+// collective: gather the statistics of every pe on pe 0 and print them there
+fn print_stats(world: &LamellarWorld, graph: &Graph) {
+    let stats = Darc::new(world, Mutex::new(vec![PeStats::default(); world.num_pes()]))
+        .block()
+        .unwrap();
+    world.block_on(world.exec_am_pe(
+        0,
+        StatsAM {
+            stats: stats.clone(),
+            pe: world.my_pe(),
+            local: local_stats(graph),
+        },
+    ));
+    world.barrier();
+    if world.my_pe() != 0 {
+        return;
+    }
+    let stats = stats.lock().unwrap();
+    let num_nodes = graph.num_nodes();
+    let num_edges = graph.num_edges();
+    println!("nodes: {num_nodes}");
+    println!("edges: {num_edges}");
+    println!("weighted: {}", graph.is_weighted());
+    println!("ordered by degree: {}", graph.is_relabeled());
+    println!(
+        "max degree: {}",
+        stats.iter().map(|s| s.max_degree).max().unwrap_or(0)
+    );
+    println!(
+        "mean degree: {:.3}",
+        2.0 * num_edges as f64 / std::cmp::max(num_nodes, 1) as f64
+    );
+    println!("degree distribution:");
+    let buckets = stats.iter().map(|s| s.histogram.len()).max().unwrap_or(0);
+    for bucket in 0..buckets {
+        let count: usize = stats
+            .iter()
+            .map(|s| s.histogram.get(bucket).copied().unwrap_or(0))
+            .sum();
+        let range = match bucket {
+            0 => "0".to_string(),
+            1 => "1".to_string(),
+            _ => format!("{}-{}", 1usize << (bucket - 1), (1usize << bucket) - 1),
+        };
+        println!("  {range:>15}: {count}");
+    }
+    println!(
+        "per-PE load ({} partitioning):",
+        graph.partitioner().strategy().name()
+    );
+    for (pe, s) in stats.iter().enumerate() {
+        println!("  pe {pe}: {} nodes, {} entries", s.nodes, s.entries);
+    }
+    let max_entries = stats.iter().map(|s| s.entries).max().unwrap_or(0);
+    let mean_entries = 2.0 * num_edges as f64 / stats.len() as f64;
+    if mean_entries > 0.0 {
+        println!(
+            "entry imbalance (max / mean): {:.3}",
+            max_entries as f64 / mean_entries
+        );
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("graph_tool: {message}\n\n{USAGE}");
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut graph_type = GraphType::MapGraph;
+    let mut options = LoadOptions::default();
+    let mut args: Vec<String> = vec![];
+    let mut arg_iter = std::env::args().skip(1);
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--graph" => {
+                graph_type = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e: String| usage_error(&e))
+            }
+            "--partition" => {
+                options.partitioning = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e: String| usage_error(&e))
+            }
+            "--weights" => options.keep_weights = true,
+            "--keep-labels" => options.keep_labels = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => args.push(arg),
+        }
+    }
+    let command = match args.first().map(String::as_str) {
+        Some("convert") => Command::Convert,
+        Some("relabel") => Command::Relabel,
+        Some("symmetrize") => Command::Symmetrize,
+        Some("stats") => Command::Stats,
+        Some(command) => usage_error(&format!("unknown command {command}")),
+        None => usage_error("missing command"),
+    };
+    let input = args
+        .get(1)
+        .unwrap_or_else(|| usage_error("missing input graph"));
+    let output = match command {
+        Command::Stats => None,
+        _ => {
+            let output = args
+                .get(2)
+                .unwrap_or_else(|| usage_error("missing output path"));
+            let format = OutputFormat::from_path(output)
+                .unwrap_or_else(|| usage_error(&format!("unknown output format for {output}")));
+            Some((output, format))
+        }
+    };
+    match command {
+        Command::Convert | Command::Symmetrize => options.keep_labels = true,
+        Command::Relabel => options.keep_labels = false,
+        Command::Stats => {}
+    }
+
+    let world = lamellar::LamellarWorldBuilder::new().build();
+    let my_pe = world.my_pe();
+    let graph = match Graph::with_options(input, graph_type, options, world.clone()) {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("PE {my_pe}: unable to load graph {input}: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let (output, format) = match output {
+        Some(output) => output,
+        None => return print_stats(&world, &graph),
+    };
+    let res = match format {
+        OutputFormat::Bin => graph.dump_to_bin(output).map_err(|e| e.to_string()),
+        _ => save_text(&graph, output, format, command == Command::Symmetrize)
+            .map_err(|e| e.to_string()),
+    };
+    match res {
+        Ok(()) if my_pe == 0 => println!("wrote {output}"),
+        Ok(()) => {}
+        Err(e) => {
+            eprintln!("PE {my_pe}: unable to write {output}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
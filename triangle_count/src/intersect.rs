@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// the adaptive kernel gallops through the longer list when it is this many times longer
+const GALLOP_RATIO: usize = 32;
+
+/// Kernels counting the common elements of two sorted, duplicate free neighbor lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IntersectKernel {
+    /// Linear merge of the two lists, `O(a + b)`.
+    #[default]
+    Merge,
+    /// Exponential then binary search of the elements of the shorter list in the longer one,
+    /// `O(a log(b / a))`, for skewed degrees.
+    Galloping,
+    /// Probe a hash set of one list with the elements of the other.
+    Hash,
+    /// Probe a bitmap spanning the values of one list with the elements of the other.
+    Bitmap,
+    /// Galloping when one list is much longer than the other, merge otherwise.
+    Adaptive,
+}
+
+impl IntersectKernel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntersectKernel::Merge => "merge",
+            IntersectKernel::Galloping => "galloping",
+            IntersectKernel::Hash => "hash",
+            IntersectKernel::Bitmap => "bitmap",
+            IntersectKernel::Adaptive => "adaptive",
+        }
+    }
+
+    /// Number of elements in both lists.
+    pub fn count(&self, a: &[u32], b: &[u32]) -> usize {
+        self.prepare(a).count(b)
+    }
+
+    /// Build the lookup structure of the kernel (if any) for `list`, to intersect it with many
+    /// other lists.
+    pub fn prepare<'a>(&self, list: &'a [u32]) -> PreparedList<'a> {
+        let lookup = match self {
+            IntersectKernel::Hash => Lookup::Hash(list.iter().copied().collect()),
+            IntersectKernel::Bitmap => Lookup::Bitmap(Bitmap::new(list)),
+            _ => Lookup::None,
+        };
+        PreparedList {
+            kernel: *self,
+            list: list,
+            lookup: lookup,
+        }
+    }
+}
+
+impl std::str::FromStr for IntersectKernel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(IntersectKernel::Merge),
+            "galloping" | "gallop" | "binary" => Ok(IntersectKernel::Galloping),
+            "hash" => Ok(IntersectKernel::Hash),
+            "bitmap" => Ok(IntersectKernel::Bitmap),
+            "adaptive" => Ok(IntersectKernel::Adaptive),
+            _ => Err(format!(
+                "unknown intersection kernel {s} (expected merge, galloping, hash, bitmap or adaptive)"
+            )),
+        }
+    }
+}
+
+/// A list ready to be intersected with other lists by a kernel.
+pub struct PreparedList<'a> {
+    kernel: IntersectKernel,
+    list: &'a [u32],
+    lookup: Lookup,
+}
+
+enum Lookup {
+    None,
+    Hash(HashSet<u32>),
+    Bitmap(Bitmap),
+}
+
+impl PreparedList<'_> {
+    /// Number of elements of `other` (sorted, duplicate free) in the prepared list.
+    pub fn count(&self, other: &[u32]) -> usize {
+        let mut count = 0;
+        self.for_each_common(other, |_| count += 1);
+        count
+    }
+
+    /// Call `f` with every element of `other` (sorted, duplicate free) in the prepared list, in
+    /// increasing order.
+    pub fn for_each_common(&self, other: &[u32], mut f: impl FnMut(u32)) {
+        match (&self.lookup, self.kernel) {
+            (Lookup::Hash(set), _) => other.iter().filter(|n| set.contains(n)).for_each(|n| f(*n)),
+            (Lookup::Bitmap(bitmap), _) => other
+                .iter()
+                .filter(|n| bitmap.contains(**n))
+                .for_each(|n| f(*n)),
+            (_, IntersectKernel::Galloping) => galloping(self.list, other, f),
+            (_, IntersectKernel::Adaptive) => {
+                let (short, long) = if self.list.len() < other.len() {
+                    (self.list, other)
+                } else {
+                    (other, self.list)
+                };
+                if long.len() >= GALLOP_RATIO * short.len() {
+                    galloping(short, long, f)
+                } else {
+                    merge(short, long, f)
+                }
+            }
+            _ => merge(self.list, other, f),
+        }
+    }
+}
+
+fn merge(a: &[u32], b: &[u32], mut f: impl FnMut(u32)) {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            i += 1;
+        } else if a[i] > b[j] {
+            j += 1;
+        } else {
+            f(a[i]);
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+fn galloping(a: &[u32], b: &[u32], mut f: impl FnMut(u32)) {
+    let (short, mut long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    for x in short {
+        // find a bound past x by doubling the step, then binary search below it
+        let mut bound = 1;
+        while bound < long.len() && long[bound - 1] < *x {
+            bound *= 2;
+        }
+        let bound = std::cmp::min(bound, long.len());
+        let pos = long[..bound].partition_point(|y| y < x);
+        if pos < long.len() && long[pos] == *x {
+            f(*x);
+            long = &long[pos + 1..];
+        } else {
+            long = &long[pos..];
+        }
+        if long.is_empty() {
+            break;
+        }
+    }
+}
+
+struct Bitmap {
+    first: u32,
+    words: Vec<u64>,
+}
+
+impl Bitmap {
+    fn new(list: &[u32]) -> Bitmap {
+        let (first, last) = match (list.first(), list.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => {
+                return Bitmap {
+                    first: 0,
+                    words: vec![],
+                }
+            }
+        };
+        let mut words = vec![0u64; (last - first) as usize / 64 + 1];
+        for n in list {
+            let bit = (n - first) as usize;
+            words[bit / 64] |= 1 << (bit % 64);
+        }
+        Bitmap {
+            first: first,
+            words: words,
+        }
+    }
+
+    fn contains(&self, n: u32) -> bool {
+        match n.checked_sub(self.first) {
+            Some(bit) => self
+                .words
+                .get(bit as usize / 64)
+                .is_some_and(|word| word & (1 << (bit % 64)) != 0),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    const KERNELS: [IntersectKernel; 5] = [
+        IntersectKernel::Merge,
+        IntersectKernel::Galloping,
+        IntersectKernel::Hash,
+        IntersectKernel::Bitmap,
+        IntersectKernel::Adaptive,
+    ];
+
+    fn naive_count(a: &[u32], b: &[u32]) -> usize {
+        a.iter().filter(|n| b.contains(n)).count()
+    }
+
+    // `count` in both argument orders, and through a list prepared once
+    fn assert_counts(a: &[u32], b: &[u32], expected: usize) {
+        for kernel in KERNELS {
+            assert_eq!(kernel.count(a, b), expected, "{}", kernel.name());
+            assert_eq!(kernel.count(b, a), expected, "{}", kernel.name());
+            assert_eq!(kernel.prepare(a).count(b), expected, "{}", kernel.name());
+        }
+    }
+
+    #[test]
+    fn empty_lists() {
+        assert_counts(&[], &[], 0);
+        assert_counts(&[], &[0, 1, 2], 0);
+        assert_counts(&[7], &[], 0);
+    }
+
+    #[test]
+    fn disjoint_lists() {
+        let evens = (0..100).map(|n| 2 * n).collect::<Vec<u32>>();
+        let odds = (0..100).map(|n| 2 * n + 1).collect::<Vec<u32>>();
+        let above = (200..300).collect::<Vec<u32>>();
+        assert_counts(&evens, &odds, 0);
+        assert_counts(&evens, &above, 0);
+        assert_counts(&evens[..1], &odds, 0);
+        assert_counts(&evens, &evens, evens.len());
+    }
+
+    #[test]
+    fn adaptive_counts_on_both_sides_of_the_gallop_ratio() {
+        for short_len in [1, 2, 5, 16] {
+            let threshold = GALLOP_RATIO * short_len;
+            for long_len in [threshold - 1, threshold, threshold + 1] {
+                let long = (0..long_len as u32).map(|n| 3 * n).collect::<Vec<_>>();
+                // every other element of the short list is in the long list
+                let short = (0..short_len)
+                    .map(|i| long[i * long_len / short_len] + (i % 2) as u32)
+                    .collect::<Vec<_>>();
+                assert_counts(&short, &long, short_len.div_ceil(2));
+            }
+        }
+    }
+
+    #[test]
+    fn kernels_agree_with_a_naive_count() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sorted_list = |len: usize| {
+            let mut list = (0..len)
+                .map(|_| rng.gen_range(0, 5000))
+                .collect::<Vec<u32>>();
+            list.sort_unstable();
+            list.dedup();
+            list
+        };
+        for trial in 0..200 {
+            let a = sorted_list(trial % 50);
+            // every third pair is skewed enough for the adaptive kernel to gallop
+            let b = sorted_list(if trial % 3 == 0 { 3000 } else { 50 });
+            assert_counts(&a, &b, naive_count(&a, &b));
+        }
+    }
+
+    #[test]
+    fn kernels_list_the_common_elements() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for trial in 0..100 {
+            let mut lists = [trial % 40, if trial % 2 == 0 { 2000 } else { 40 }].map(|len| {
+                let mut list = (0..len)
+                    .map(|_| rng.gen_range(0, 3000))
+                    .collect::<Vec<u32>>();
+                list.sort_unstable();
+                list.dedup();
+                list
+            });
+            let expected = lists[1]
+                .iter()
+                .copied()
+                .filter(|n| lists[0].contains(n))
+                .collect::<Vec<_>>();
+            for _ in 0..2 {
+                for kernel in KERNELS {
+                    let mut common = vec![];
+                    kernel
+                        .prepare(&lists[0])
+                        .for_each_common(&lists[1], |n| common.push(n));
+                    assert_eq!(common, expected, "{}", kernel.name());
+                }
+                lists.swap(0, 1);
+            }
+        }
+    }
+}
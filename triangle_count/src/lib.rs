@@ -1,22 +1,35 @@
 use lamellar::active_messaging::prelude::*;
+use lamellar::array::prelude::*;
 use lamellar::darc::prelude::*;
 use lamellar::memregion::prelude::*;
-use std::error::Error;
 use std::path::Path;
 use std::sync::Arc;
 // use std::marker::PhantomData;
 
 use std::collections::HashMap;
-use std::collections::HashSet;
 
-use std::fs::File;
 // use std::io::Write;
-use std::io::{BufRead, BufReader, BufWriter};
-
-use bincode;
 
+pub mod binformat;
+pub mod cli;
+pub mod clustering;
+pub mod csrgraph;
+mod error;
+pub mod generate;
+pub mod intersect;
+mod load;
 pub mod mapgraph;
+pub mod matrix_market;
+pub mod partition;
+pub mod reference;
+use crate::binformat::{BinHeader, BinReader, BinWriter};
+use crate::csrgraph::{CsrGraph, CsrGraphIter};
+pub use crate::error::GraphLoadError;
+use crate::generate::Generator;
+pub use crate::intersect::IntersectKernel;
+use crate::load::{Adjacency, EdgeBuffers, IdMap, NeighborList, ShuffleEdgesAM, TextFormat};
 use crate::mapgraph::{MapGraph, MapGraphIter};
+pub use crate::partition::{PartitionStrategy, Partitioner};
 
 pub trait Element:
     'static
@@ -40,64 +53,50 @@ impl<
 {
 }
 
-#[derive(Debug, serde::Deserialize, Eq, PartialEq)]
-struct Edge {
-    e0: u32,
-    e1: u32,
-}
-
-#[derive(Clone)]
-enum EdgeList {
-    Vec(Vec<u32>),
-    Set(HashSet<u32>),
-}
-
-impl EdgeList {
-    fn len(&self) -> usize {
-        match self {
-            EdgeList::Vec(vec) => vec.len(),
-            EdgeList::Set(set) => set.len(),
-        }
-    }
-    fn push(&mut self, val: u32) {
-        match self {
-            EdgeList::Vec(vec) => vec.push(val),
-            EdgeList::Set(set) => {
-                set.insert(val);
-            }
-        }
-    }
-    fn iter(&self) -> Box<dyn Iterator<Item = &u32> + '_> {
-        match self {
-            EdgeList::Vec(vec) => Box::new(vec.iter()),
-            EdgeList::Set(set) => Box::new(set.iter()),
-        }
-    }
-}
-
 trait GraphOps {
-    fn add_local_neighbors(
-        &mut self,
-        node: u32,
-        neighbors: OneSidedMemoryRegion<u32>,
-    ) -> OneSidedMemoryRegion<u32>;
-    fn add_remote_neighbors(&mut self, node: u32, neighbors: OneSidedMemoryRegion<u32>);
     fn neighbors(&self, node: &u32) -> std::slice::Iter<'_, u32>;
     fn lamellar_neighbors(&self, node: &u32) -> OneSidedMemoryRegion<u32>;
     fn num_nodes(&self) -> usize;
     fn node_is_local(&self, node: &u32) -> bool;
+    // set once the degrees are known, before any neighbors are added
+    fn set_partitioner(&mut self, partitioner: Arc<Partitioner>);
+    fn partitioner(&self) -> &Partitioner;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GraphType {
     MapGraph,
+    Csr,
+}
+
+impl GraphType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GraphType::MapGraph => "map",
+            GraphType::Csr => "csr",
+        }
+    }
+}
+
+impl std::str::FromStr for GraphType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "map" | "mapgraph" => Ok(GraphType::MapGraph),
+            "csr" => Ok(GraphType::Csr),
+            _ => Err(format!("unknown graph type {s} (expected map or csr)")),
+        }
+    }
 }
 
 pub enum GraphData {
     MapGraph(MapGraph),
+    Csr(CsrGraph),
 }
 
 pub enum GraphIter<'a> {
     MapGraph(MapGraphIter<'a>),
+    Csr(CsrGraphIter<'a>),
 }
 
 impl<'a> Iterator for GraphIter<'a> {
@@ -105,43 +104,46 @@ impl<'a> Iterator for GraphIter<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             GraphIter::MapGraph(iter) => iter.next(),
+            GraphIter::Csr(iter) => iter.next(),
         }
     }
 }
 
 impl GraphOps for GraphData {
-    fn add_local_neighbors(
-        &mut self,
-        node: u32,
-        neighbors: OneSidedMemoryRegion<u32>,
-    ) -> OneSidedMemoryRegion<u32> {
-        match self {
-            GraphData::MapGraph(graph) => graph.add_local_neighbors(node, neighbors),
-        }
-    }
-    fn add_remote_neighbors(&mut self, node: u32, neighbors: OneSidedMemoryRegion<u32>) {
-        match self {
-            GraphData::MapGraph(graph) => graph.add_remote_neighbors(node, neighbors),
-        }
-    }
     fn neighbors(&self, node: &u32) -> std::slice::Iter<'_, u32> {
         match self {
             GraphData::MapGraph(graph) => graph.neighbors(node),
+            GraphData::Csr(graph) => graph.neighbors(node),
         }
     }
     fn lamellar_neighbors(&self, node: &u32) -> OneSidedMemoryRegion<u32> {
         match self {
             GraphData::MapGraph(graph) => graph.lamellar_neighbors(node),
+            GraphData::Csr(graph) => graph.lamellar_neighbors(node),
         }
     }
     fn num_nodes(&self) -> usize {
         match self {
             GraphData::MapGraph(graph) => graph.num_nodes(),
+            GraphData::Csr(graph) => graph.num_nodes(),
         }
     }
     fn node_is_local(&self, node: &u32) -> bool {
         match self {
             GraphData::MapGraph(graph) => graph.node_is_local(node),
+            GraphData::Csr(graph) => graph.node_is_local(node),
+        }
+    }
+    fn set_partitioner(&mut self, partitioner: Arc<Partitioner>) {
+        match self {
+            GraphData::MapGraph(graph) => graph.set_partitioner(partitioner),
+            GraphData::Csr(graph) => graph.set_partitioner(partitioner),
+        }
+    }
+    fn partitioner(&self) -> &Partitioner {
+        match self {
+            GraphData::MapGraph(graph) => graph.partitioner(),
+            GraphData::Csr(graph) => graph.partitioner(),
         }
     }
 }
@@ -150,62 +152,47 @@ impl GraphData {
     pub fn iter(&self) -> GraphIter<'_> {
         match self {
             GraphData::MapGraph(graph) => GraphIter::MapGraph(graph.iter()),
+            GraphData::Csr(graph) => GraphIter::Csr(graph.iter()),
         }
     }
     pub fn neighbors_iter(&self, node: &u32) -> std::slice::Iter<'_, u32> {
         match self {
             GraphData::MapGraph(graph) => graph.neighbors(node),
+            GraphData::Csr(graph) => graph.neighbors(node),
         }
     }
+    /// The neighbors of a local node that are smaller than it, in increasing order.
+    pub fn lower_neighbors(&self, node: &u32) -> &[u32] {
+        let neighbors = self.neighbors_iter(node).as_slice();
+        &neighbors[..neighbors.partition_point(|n| n < node)]
+    }
+    /// The neighbors of a local node, without the placeholder entry of nodes without neighbors.
+    pub fn real_neighbors(&self, node: &u32) -> &[u32] {
+        let neighbors = self.neighbors_iter(node).as_slice();
+        let num_nodes = GraphOps::num_nodes(self);
+        &neighbors[..neighbors.partition_point(|n| (*n as usize) < num_nodes)]
+    }
+    /// The assignment of the nodes to the pes.
+    pub fn partitioner(&self) -> &Partitioner {
+        GraphOps::partitioner(self)
+    }
     pub fn local_neighbors(&self, node: &u32) -> OneSidedMemoryRegion<u32> {
         match self {
             GraphData::MapGraph(graph) => graph.lamellar_neighbors(node),
+            GraphData::Csr(graph) => graph.lamellar_neighbors(node),
         }
     }
     pub fn node_is_local(&self, node: &u32) -> bool {
         match self {
             GraphData::MapGraph(graph) => graph.node_is_local(node),
+            GraphData::Csr(graph) => graph.node_is_local(node),
         }
     }
-}
-
-#[lamellar::AmLocalData]
-struct RelabelMapAm {
-    start_index: usize,
-    nodes: Vec<u32>,
-    relabeled: OneSidedMemoryRegion<u32>,
-}
-#[lamellar::local_am]
-impl LamellarAM for RelabelMapAm {
-    async fn exec() {
-        let relabled = unsafe { self.relabeled.as_mut_slice().unwrap() };
-        for (i, node) in self.nodes.iter().enumerate() {
-            relabled[*node as usize] = (i + self.start_index) as u32;
-        }
-    }
-}
-
-#[lamellar::AmLocalData]
-struct RelabelAm {
-    nodes: Vec<(EdgeList, OneSidedMemoryRegion<u32>, usize)>,
-    relabeled: OneSidedMemoryRegion<u32>,
-}
-#[lamellar::local_am]
-impl LamellarAM for RelabelAm {
-    async fn exec() {
-        let relabled = unsafe { self.relabeled.as_slice().unwrap() };
-        for nodes in &self.nodes {
-            let old_nodes = &nodes.0;
-            let new_nodes = unsafe { nodes.1.as_mut_slice().unwrap() };
-            if old_nodes.len() == 0 {
-                new_nodes[0] = (self.relabeled.len() + 1) as u32;
-            } else {
-                // for i in 0..old_nodes.len() {
-                for (i, old_node) in old_nodes.iter().enumerate() {
-                    new_nodes[i] = relabled[*old_node as usize];
-                }
-                new_nodes.sort_unstable();
-            }
+    // backends that build their final layout once every node is known return what the other pes need to read it
+    fn finalize(&mut self) -> Option<(Vec<usize>, OneSidedMemoryRegion<u32>)> {
+        match self {
+            GraphData::MapGraph(_) => None,
+            GraphData::Csr(graph) => Some(graph.finalize()),
         }
     }
 }
@@ -222,8 +209,10 @@ impl LamellarAM for LocalNeighborsAM {
         let mut remotes: Vec<(u32, OneSidedMemoryRegion<u32>)> = vec![];
         {
             let mut graph = self.graph.write().await;
-            for (node, neighbors) in &self.node_and_neighbors {
-                remotes.push((*node, graph.add_local_neighbors(*node, neighbors.clone())));
+            if let GraphData::MapGraph(graph) = &mut *graph {
+                for (node, neighbors) in &self.node_and_neighbors {
+                    remotes.push((*node, graph.add_local_neighbors(*node, neighbors.clone())));
+                }
             }
         }
         lamellar::world
@@ -244,229 +233,465 @@ struct RemoteNeighborsAM {
 impl LamellarAM for RemoteNeighborsAM {
     async fn exec() {
         let mut graph = self.graph.write().await;
+        if let GraphData::MapGraph(graph) = &mut *graph {
+            for (node, neighbors) in &self.node_and_neighbors {
+                graph.add_remote_neighbors(*node, neighbors.clone());
+            }
+        }
+    }
+}
+
+#[lamellar::AmData]
+struct StageNeighborsAM {
+    graph: LocalRwDarc<GraphData>,
+    node_and_neighbors: Vec<(u32, Vec<u32>)>,
+}
+#[lamellar::am]
+impl LamellarAM for StageNeighborsAM {
+    async fn exec() {
+        let mut graph = self.graph.write().await;
+        if let GraphData::Csr(graph) = &mut *graph {
+            for (node, neighbors) in &self.node_and_neighbors {
+                graph.stage_neighbors(*node, neighbors.clone());
+            }
+        }
+    }
+}
+
+#[lamellar::AmData]
+struct CsrShardAM {
+    graph: LocalRwDarc<GraphData>,
+    pe: usize,
+    offsets: Vec<usize>,
+    neighbors: OneSidedMemoryRegion<u32>,
+}
+#[lamellar::am]
+impl LamellarAM for CsrShardAM {
+    async fn exec() {
+        let mut graph = self.graph.write().await;
+        if let GraphData::Csr(graph) = &mut *graph {
+            graph.add_remote_shard(self.pe, self.offsets.clone(), self.neighbors.clone());
+        }
+    }
+}
+
+#[lamellar::AmData]
+struct WeightsAM {
+    weights: LocalRwDarc<HashMap<u32, Vec<f32>>>,
+    node_and_weights: Vec<(u32, Vec<f32>)>,
+}
+#[lamellar::am]
+impl LamellarAM for WeightsAM {
+    async fn exec() {
+        let mut weights = self.weights.write().await;
+        for (node, node_weights) in &self.node_and_weights {
+            weights.insert(*node, node_weights.clone());
+        }
+    }
+}
+
+// gathers the lower neighbor lists of the graph on pe 0 for the reference computations
+const REFERENCE_BATCH: usize = 10000;
+
+#[lamellar::AmData]
+struct LowerNeighborsAM {
+    lists: LocalRwDarc<Vec<Vec<u32>>>,
+    node_and_neighbors: Vec<(u32, Vec<u32>)>,
+}
+#[lamellar::am]
+impl LamellarAM for LowerNeighborsAM {
+    async fn exec() {
+        let mut lists = self.lists.write().await;
         for (node, neighbors) in &self.node_and_neighbors {
-            graph.add_remote_neighbors(*node, neighbors.clone());
+            lists[*node as usize] = neighbors.clone();
         }
     }
 }
 
+/// Options for `Graph::with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Keep the values of MatrixMarket `integer` and `real` matrices as edge weights.
+    pub keep_weights: bool,
+    /// Keep the input node ids instead of relabeling the nodes by decreasing degree.
+    pub keep_labels: bool,
+    /// How the nodes are assigned to the PEs.
+    pub partitioning: PartitionStrategy,
+}
+
 #[derive(Clone)]
 pub struct Graph {
     graph: Darc<GraphData>,
+    // weights of the neighbors of the local nodes, if kept
+    weights: Option<Darc<HashMap<u32, Vec<f32>>>>,
+    // input and internal label of every node
+    ids: IdMap,
+    num_edges: usize,
+    // the labels are ordered by decreasing degree
+    relabeled: bool,
     world: LamellarWorld,
     pub my_pe: usize,
 }
 
 impl Graph {
-    pub fn new(fpath: &str, graph_type: GraphType, world: LamellarWorld) -> Graph {
+    /// Collective: load (or generate) the graph, relabel it by degree and distribute it.
+    /// Either every PE returns the graph or every PE returns an error.
+    pub fn new(
+        fpath: &str,
+        graph_type: GraphType,
+        world: LamellarWorld,
+    ) -> Result<Graph, GraphLoadError> {
+        Graph::with_options(fpath, graph_type, LoadOptions::default(), world)
+    }
+
+    /// Collective: `new` with non default load options.
+    pub fn with_options(
+        fpath: &str,
+        graph_type: GraphType,
+        options: LoadOptions,
+        world: LamellarWorld,
+    ) -> Result<Graph, GraphLoadError> {
         let my_pe = world.my_pe();
         let graph = match graph_type {
-            _map_graph => GraphData::MapGraph(MapGraph::new(world.team().clone())),
+            GraphType::MapGraph => GraphData::MapGraph(MapGraph::new(world.team().clone())),
+            GraphType::Csr => GraphData::Csr(CsrGraph::new(world.team().clone())),
         };
 
         // TODO: Should new be made async to 'await' instead?
         let graph = LocalRwDarc::new(world.team(), graph).block().unwrap(); // we are creating with the world team so should be valid on all pes
+        let weights = LocalRwDarc::new(world.team(), HashMap::new())
+            .block()
+            .unwrap();
 
-        Graph::load(fpath, &world, &graph).expect("error reading graph");
+        let (num_edges, weighted, relabeled, ids) =
+            Graph::load(fpath, &options, &world, &graph, &weights)?;
+        Graph::finalize(&world, &graph);
         if my_pe == 0 {
             println!("Done loading graph!");
         }
+        let weights = weights.into_darc().block();
         let g = Graph {
             world: world,
             graph: graph.into_darc().block(),
+            weights: if weighted { Some(weights) } else { None },
+            ids: ids,
+            num_edges: num_edges,
+            relabeled: relabeled,
             my_pe: my_pe,
         };
         if my_pe == 0 {
             println!("Done creating graph!");
         }
         g.barrier();
-        g
+        Ok(g)
     }
 
+    // collective: read or generate the edges on all pes, relabel the nodes by degree and
+    // distribute the neighbor lists (and weights) to their owners. Returns the number of
+    // undirected edges, whether the graph is weighted, whether it is ordered by degree and the
+    // map between the input and internal labels.
     fn load(
         fpath: &str,
+        options: &LoadOptions,
         world: &LamellarWorld,
         graph: &LocalRwDarc<GraphData>,
-    ) -> Result<(), Box<dyn Error>> {
-        if world.my_pe() == 0 {
-            if !Graph::parse(fpath, b'\t', world, graph).is_ok() {
-                Graph::parse(fpath, b' ', world, graph)?;
+        weights: &LocalRwDarc<HashMap<u32, Vec<f32>>>,
+    ) -> Result<(usize, bool, bool, IdMap), GraphLoadError> {
+        let my_pe = world.my_pe();
+        let start = std::time::Instant::now();
+        let text = |format| load::read_text(world, Path::new(fpath), format, options.keep_weights);
+        let gen_spec = fpath.strip_prefix("gen:");
+        // `by_degree`: the input is not ordered by degree yet
+        let (adjacency, num_nodes, by_degree, weighted) = if let Some(spec) = gen_spec {
+            let generator: Generator = spec.parse().map_err(GraphLoadError::InvalidGenerator)?;
+            (
+                Graph::generate(&generator, world),
+                generator.num_nodes(),
+                true,
+                false,
+            )
+        } else {
+            let path = Path::new(fpath);
+            match path.extension().and_then(|ext| ext.to_str()).unwrap_or("") {
+                "bin" => {
+                    let (adjacency, header) = Graph::read_bin(path, world, options.keep_weights)?;
+                    (
+                        adjacency,
+                        header.num_nodes as usize,
+                        !header.relabeled,
+                        header.weighted && options.keep_weights,
+                    )
+                }
+                "mm" => {
+                    let text = text(TextFormat::MatrixMarket)?;
+                    (text.adjacency, text.num_nodes, true, text.weighted)
+                }
+                "tsv" => {
+                    let text = text(TextFormat::Tsv)?;
+                    (text.adjacency, text.num_nodes, true, text.weighted)
+                }
+                "el" | "edges" | "txt" => {
+                    let text = text(TextFormat::EdgeList)?;
+                    (text.adjacency, text.num_nodes, true, text.weighted)
+                }
+                _ => return Err(GraphLoadError::UnknownFormat(path.to_path_buf())),
             }
+        };
+        // every edge is stored in both directions, without self loops or duplicates
+        let num_edges = load::global_sum(world, adjacency.read().block().num_entries()) / 2;
+        if my_pe == 0 {
+            println!("read time: {:?}", start.elapsed().as_secs_f64());
+            println!("num_nodes {num_nodes} num_edges {num_edges}");
         }
-        world.barrier();
-        Ok(())
-    }
 
-    fn parse(
-        fpath: &str,
-        delim: u8,
-        world: &LamellarWorld,
-        graph: &LocalRwDarc<GraphData>,
-    ) -> Result<usize, Box<dyn Error>> {
-        let path = Path::new(&fpath);
+        let start = std::time::Instant::now();
+        let relabel = by_degree && !options.keep_labels;
+        let neighbor_lists = load::relabel(world, &adjacency.read().block(), num_nodes, relabel);
+        drop(adjacency);
+        if my_pe == 0 {
+            println!("reorder time: {:?}", start.elapsed().as_secs_f64());
+        }
 
-        let mut cur_node = 1;
-        let mut num_edges: usize = 0;
-        let mut num_nodes = 0;
+        let degrees = load::gather_degrees(world, &neighbor_lists, num_nodes);
+        let partitioner = Partitioner::new(options.partitioning, world.num_pes(), &degrees);
+        if my_pe == 0 {
+            println!(
+                "{} partitioning, edge imbalance {:.3}",
+                partitioner.strategy().name(),
+                partitioner.edge_imbalance()
+            );
+        }
+        let partitioner = Arc::new(partitioner);
+        graph.write().block().set_partitioner(partitioner.clone());
 
-        let start = std::time::Instant::now();
-        let mut indices;
-        let mut temp_neighbor_list: Vec<EdgeList>;
-        match path.extension().unwrap().to_str().unwrap() {
-            "bin" => {
-                let file = File::open(&path)?;
-                let mut rdr = BufReader::new(file);
-                num_nodes = bincode::deserialize_from(&mut rdr).unwrap();
-                temp_neighbor_list = vec![EdgeList::Vec(Vec::new()); num_nodes];
-                while let Ok(node) = bincode::deserialize_from::<_, u32>(&mut rdr) {
-                    temp_neighbor_list[node as usize] =
-                        EdgeList::Vec(bincode::deserialize_from::<_, Vec<u32>>(&mut rdr).unwrap());
-                    num_edges += temp_neighbor_list[node as usize].len();
-                    if node % 1000000 == 0 {
-                        println!("{:?} nodes loaded", node);
-                    }
-                }
-                indices = (0..num_nodes).collect::<Vec<_>>();
+        let ids = load::id_map(world, &neighbor_lists, num_nodes, &partitioner);
+        Graph::distribute(world, graph, &partitioner, weights, neighbor_lists);
+        world.barrier();
+        if my_pe == 0 {
+            println!("distribute time: {:?}", start.elapsed().as_secs_f64());
+        }
+        Ok((num_edges, weighted, relabel || !by_degree, ids))
+    }
+
+    // collective: every pe generates its share of the chunks of edges and shuffles them to their owners
+    fn generate(generator: &Generator, world: &LamellarWorld) -> LocalRwDarc<Adjacency> {
+        let adjacency = LocalRwDarc::new(world.team(), Adjacency::default())
+            .block()
+            .unwrap();
+        let mut buffers = EdgeBuffers::new(adjacency.clone(), world.num_pes());
+        let send = |batch: Option<(usize, ShuffleEdgesAM)>| {
+            if let Some((pe, am)) = batch {
+                let _ = world.exec_am_pe(pe, am).spawn();
             }
-            "mm" => {
-                let file = File::open(&path)?;
-                let rdr = BufReader::new(file);
-                let mut lines = rdr
-                    .lines()
-                    .map(|l| l.unwrap())
-                    .skip_while(|l| l.starts_with("%"));
-                let line = lines.next().unwrap();
-                // println!("header {line}");
-                let vals = line.split_whitespace().collect::<Vec<_>>();
-                assert_eq!(vals[0], vals[1]);
-                num_nodes = vals[0].parse().unwrap();
-                num_edges = vals[2].parse().unwrap();
-
-                temp_neighbor_list = vec![EdgeList::Set(HashSet::new()); num_nodes];
-
-                for line in lines.map(|l| l) {
-                    let vals = line.split_whitespace().collect::<Vec<_>>();
-                    let e0: usize = vals[0].parse::<usize>().unwrap() - 1;
-                    let e1: usize = vals[1].parse::<usize>().unwrap() - 1;
-                    temp_neighbor_list[e0].push(e1 as u32);
-                    temp_neighbor_list[e1].push(e0 as u32);
-                    if cur_node % 1000000 == 0 {
-                        println!("{:?} nodes loaded", cur_node);
-                    }
-                    cur_node += 1;
+        };
+        for chunk in (world.my_pe()..generator.num_chunks()).step_by(world.num_pes()) {
+            for (e0, e1) in generator.edges(chunk) {
+                if e0 != e1 {
+                    send(buffers.push(e0, e1));
+                    send(buffers.push(e1, e0));
                 }
-                indices = (0..num_nodes).collect::<Vec<_>>();
-                indices.sort_by_key(|&i| -(temp_neighbor_list[i].len() as isize));
-                //would be nice to do this multithreaded
             }
-            "tsv" => {
-                let mut rdr = csv::ReaderBuilder::new()
-                    .has_headers(false)
-                    .delimiter(delim)
-                    .from_path(&path)?;
-                let mut edges = EdgeList::Set(HashSet::new());
-                temp_neighbor_list = vec![];
-                for result in rdr.deserialize() {
-                    let edge: Edge = result?;
-                    if cur_node != edge.e1 {
-                        num_edges += edges.len();
-                        temp_neighbor_list.push(edges);
-
-                        cur_node = edge.e1;
-                        num_nodes += 1;
-                        edges = EdgeList::Set(HashSet::new());
-                        if cur_node % 100000 == 0 {
-                            println!("{:?} nodes loaded", cur_node);
+        }
+        for batch in buffers.flush() {
+            send(Some(batch));
+        }
+        world.wait_all();
+        world.barrier();
+        adjacency
+    }
+
+    // collective: every pe reads the manifest written by `dump_to_bin`, then the shards
+    // `my_pe, my_pe + num_pes, ...`, and shuffles both directions of every edge to their owners.
+    // Returns the adjacency and the manifest header, after checking the edge count.
+    fn read_bin(
+        path: &Path,
+        world: &LamellarWorld,
+        keep_weights: bool,
+    ) -> Result<(LocalRwDarc<Adjacency>, BinHeader), GraphLoadError> {
+        let adjacency = LocalRwDarc::new(world.team(), Adjacency::default())
+            .block()
+            .unwrap();
+        let invalid = |path: &Path, e: String| {
+            GraphLoadError::InvalidBinary(format!("{}: {e}", path.display()))
+        };
+        let read = || -> Result<BinHeader, GraphLoadError> {
+            let mut manifest = BinReader::open(path)?;
+            let header = manifest.header().clone();
+            if let Some(shard) = header.shard {
+                return Err(invalid(
+                    path,
+                    format!("shard {shard}, not a graph manifest"),
+                ));
+            }
+            if manifest.next_record()?.is_some() {
+                return Err(invalid(path, "the manifest contains records".to_string()));
+            }
+            let num_nodes = header.num_nodes as usize;
+            let weighted = header.weighted && keep_weights;
+            let mut buffers = EdgeBuffers::new(adjacency.clone(), world.num_pes());
+            let send = |batch: Option<(usize, ShuffleEdgesAM)>| {
+                if let Some((pe, am)) = batch {
+                    let _ = world.exec_am_pe(pe, am).spawn();
+                }
+            };
+            for shard in (world.my_pe()..header.num_shards as usize).step_by(world.num_pes()) {
+                let shard_path = binformat::shard_path(path, shard);
+                let mut rdr = BinReader::open(&shard_path)?;
+                if *rdr.header() != header.for_shard(shard) {
+                    return Err(invalid(
+                        &shard_path,
+                        "the header does not match the manifest".to_string(),
+                    ));
+                }
+                while let Some((node, original, neighbors, weights)) = rdr.next_record()? {
+                    if node as usize >= num_nodes || original as usize >= num_nodes {
+                        return Err(GraphLoadError::VertexOutOfRange {
+                            vertex: std::cmp::max(node, original) as usize,
+                            num_nodes: num_nodes,
+                        });
+                    }
+                    if original != node {
+                        send(buffers.push_original(node, original));
+                    }
+                    if header.weighted && weights.len() != neighbors.len() {
+                        return Err(invalid(
+                            &shard_path,
+                            format!(
+                                "node {node} has {} weights for {} neighbors",
+                                weights.len(),
+                                neighbors.len()
+                            ),
+                        ));
+                    }
+                    for (i, neighbor) in neighbors.into_iter().enumerate() {
+                        if neighbor >= node {
+                            return Err(invalid(
+                                &shard_path,
+                                format!(
+                                    "neighbor {neighbor} of node {node} is not a lower neighbor"
+                                ),
+                            ));
+                        }
+                        if weighted {
+                            send(buffers.push_weighted(node, neighbor, weights[i]));
+                            send(buffers.push_weighted(neighbor, node, weights[i]));
+                        } else {
+                            send(buffers.push(node, neighbor));
+                            send(buffers.push(neighbor, node));
                         }
                     }
-                    edges.push(edge.e0 - 1);
                 }
-
-                num_edges += edges.len();
-                temp_neighbor_list.push(edges);
-                num_nodes += 1;
-                indices = (0..num_nodes).collect::<Vec<_>>();
-                indices.sort_by_key(|&i| -(temp_neighbor_list[i].len() as isize));
-                //would be nice to do this multithreaded
             }
-            _ => {
-                panic!("unhandled file format");
+            for batch in buffers.flush() {
+                send(Some(batch));
             }
+            Ok(header)
+        };
+        let header = read();
+        world.wait_all();
+        world.barrier();
+        let header = load::agree(world, header)?;
+        let num_edges = load::global_sum(world, adjacency.read().block().num_entries()) / 2;
+        if num_edges as u64 != header.num_edges {
+            // same totals on every pe, so every pe fails
+            return Err(invalid(
+                path,
+                format!(
+                    "the header declares {} edges but {num_edges} were read",
+                    header.num_edges
+                ),
+            ));
         }
-        println!("read time: {:?}", start.elapsed().as_secs_f64());
-        println!("{num_nodes}");
-
-        let start = std::time::Instant::now();
-
-        // let mut indices = (0..num_nodes).collect::<Vec<_>>();
-
-        println!("ind len {}", indices.len());
-
-        let relabeled = world.alloc_one_sided_mem_region::<u32>(num_nodes);
-        let relabeled_slice = unsafe { relabeled.as_mut_slice().unwrap() };
+        Ok((adjacency, header))
+    }
 
-        let mut cnt = 0;
-        for (i, node) in indices.iter().enumerate() {
-            //would be nice to do this multithreaded
-            relabeled_slice[*node] = i as u32;
-            cnt += temp_neighbor_list[i].len();
+    // collective: let the backend build its final layout and share it with the other pes
+    fn finalize(world: &LamellarWorld, graph: &LocalRwDarc<GraphData>) {
+        let shard = graph.write().block().finalize();
+        if let Some((offsets, neighbors)) = shard {
+            world.block_on(world.exec_am_all(CsrShardAM {
+                graph: graph.clone(),
+                pe: world.my_pe(),
+                offsets: offsets,
+                neighbors: neighbors,
+            }));
         }
-
-        println!("num_edges {} {}", num_edges, cnt);
-        println!("reorder map time: {:?}", start.elapsed().as_secs_f64());
-
-        let mut temp_nodes = vec![];
-        let mut neigh_list = vec![];
-        let mut size = 0;
-        let mut i = 0;
-        for nodes in temp_neighbor_list.drain(..) {
-            if size > num_edges / 10 {
-                let _ = world
-                    .exec_am_local(RelabelAm {
-                        nodes: temp_nodes,
-                        relabeled: relabeled.clone(),
-                    })
-                    .spawn();
-                temp_nodes = vec![];
-                size = 0;
-            }
-            let nodes_len = nodes.len();
-            size += nodes_len;
-            let temp = world.alloc_one_sided_mem_region::<u32>(std::cmp::max(nodes_len, 1));
-            neigh_list.push(temp.clone());
-            temp_nodes.push((nodes, temp, i));
-            i += 1;
-        }
-        if size > 0 {
-            let _ = world
-                .exec_am_local(RelabelAm {
-                    nodes: temp_nodes,
-                    relabeled: relabeled.clone(),
-                })
-                .spawn();
-        }
-        println!("reorder issue time: {:?}", start.elapsed().as_secs_f64());
         world.wait_all();
-        println!("reorder time: {:?}", start.elapsed().as_secs_f64());
+        world.barrier();
+    }
 
+    // collective: send each relabeled neighbor list (and its weights) to the pe owning its node
+    fn distribute(
+        world: &LamellarWorld,
+        graph: &LocalRwDarc<GraphData>,
+        partitioner: &Partitioner,
+        weights: &LocalRwDarc<HashMap<u32, Vec<f32>>>,
+        neighbor_lists: Vec<NeighborList>,
+    ) {
         let task_group = LamellarTaskGroup::new(world.team());
         let mut pe_neigh_lists: HashMap<usize, Vec<(u32, OneSidedMemoryRegion<u32>)>> =
             HashMap::new();
+        // the csr backend packs plain lists into its arrays in `finalize`, only the map backend
+        // keeps (and announces to every pe) one registered region per node
+        let mut pe_staged_lists: HashMap<usize, Vec<(u32, Vec<u32>)>> = HashMap::new();
+        let mut pe_weights: HashMap<usize, Vec<(u32, Vec<f32>)>> = HashMap::new();
         for pe in 0..world.num_pes() {
             pe_neigh_lists.insert(pe, vec![]);
+            pe_staged_lists.insert(pe, vec![]);
+            pe_weights.insert(pe, vec![]);
+        }
+        let csr = matches!(&*graph.read().block(), GraphData::Csr(_));
+        for list in neighbor_lists {
+            let pe = partitioner.owner(list.node);
+            if csr {
+                pe_staged_lists
+                    .get_mut(&pe)
+                    .unwrap()
+                    .push((list.node, list.neighbors));
+            } else {
+                let lmr = world.alloc_one_sided_mem_region::<u32>(list.neighbors.len());
+                unsafe { lmr.as_mut_slice().unwrap().copy_from_slice(&list.neighbors) };
+                pe_neigh_lists.get_mut(&pe).unwrap().push((list.node, lmr));
+            }
+            if list.weights.len() > 0 {
+                pe_weights
+                    .get_mut(&pe)
+                    .unwrap()
+                    .push((list.node, list.weights));
+            }
+        }
+        for (pe, node_weights) in pe_weights.iter_mut() {
+            while node_weights.len() > 0 {
+                let batch = node_weights.split_off(node_weights.len().saturating_sub(10000));
+                let _ = task_group
+                    .exec_am_pe(
+                        *pe,
+                        WeightsAM {
+                            weights: weights.clone(),
+                            node_and_weights: batch,
+                        },
+                    )
+                    .spawn();
+            }
         }
-        for old_node in 0..neigh_list.len() {
-            let new_node = unsafe { relabeled.as_slice().unwrap()[old_node] as usize };
-            let pe = new_node % world.num_pes();
-            pe_neigh_lists
-                .get_mut(&pe)
-                .unwrap()
-                .push((new_node as u32, neigh_list[old_node].clone()));
+        for (pe, staged_lists) in pe_staged_lists.iter_mut() {
+            while staged_lists.len() > 0 {
+                let batch = staged_lists.split_off(staged_lists.len().saturating_sub(10000));
+                let _ = task_group
+                    .exec_am_pe(
+                        *pe,
+                        StageNeighborsAM {
+                            graph: graph.clone(),
+                            node_and_neighbors: batch,
+                        },
+                    )
+                    .spawn();
+            }
         }
 
         // let num_batches = 10;
         for (pe, neigh_lists) in pe_neigh_lists.iter_mut() {
-            let batch_size = neigh_lists.len() / 10;
+            let batch_size = std::cmp::max(neigh_lists.len() / 10, 1);
 
             while neigh_lists.len() > batch_size {
                 let _ = task_group
@@ -493,11 +718,8 @@ impl Graph {
             }
         }
 
-        println!("distribute issue time: {:?}", start.elapsed().as_secs_f64());
         task_group.wait_all();
         world.wait_all();
-        println!("distribute time: {:?}", start.elapsed().as_secs_f64());
-        Ok(num_nodes)
     }
 
     pub fn data(&self) -> Darc<GraphData> {
@@ -532,26 +754,185 @@ impl Graph {
         self.graph.num_nodes()
     }
 
+    /// Number of undirected edges, after removing self loops and duplicate entries.
+    pub fn num_edges(&self) -> usize {
+        self.num_edges
+    }
+
+    /// Whether the node ids are ordered by decreasing degree (see `LoadOptions::keep_labels`).
+    pub fn is_relabeled(&self) -> bool {
+        self.relabeled
+    }
+
+    /// Whether the graph was loaded with edge weights (see `LoadOptions::keep_weights`).
+    pub fn is_weighted(&self) -> bool {
+        self.weights.is_some()
+    }
+
+    /// Weights of the edges to the neighbors of a local node, in the order of `neighbors`, or
+    /// `None` if the graph is unweighted.
+    pub fn weights(&self, node: &u32) -> Option<&[f32]> {
+        self.weights
+            .as_ref()
+            .map(|weights| weights.get(node).map_or(&[][..], |w| w.as_slice()))
+    }
+
     pub fn node_is_local(&self, node: &u32) -> bool {
-        //probably should abstract this out to the graphops trait
-        *node as usize % self.num_pes() == self.my_pe()
-    }
-
-    pub fn dump_to_bin(&self, name: &str) {
-        let mut file = BufWriter::new(File::create(name).expect("error dumping graph"));
-        bincode::serialize_into(&mut file, &self.num_nodes()).unwrap();
-        for n0 in (0..self.num_nodes()).map(|n| n as u32) {
-            if self.node_is_local(&n0) {
-                let neighs = self
-                    .graph
-                    .neighbors_iter(&n0)
-                    .take_while(|n| n < &&n0)
-                    .collect::<Vec<_>>();
-                if neighs.len() > 0 {
-                    bincode::serialize_into(&mut file, &n0).unwrap();
-                    bincode::serialize_into(&mut file, &neighs).unwrap();
-                }
+        self.graph.node_is_local(node)
+    }
+
+    /// Label of a local node in the input (0-based, so MatrixMarket and tsv ids minus one),
+    /// before the nodes were relabeled by degree, without communication. Binary graphs keep the
+    /// labels of the input they were converted from.
+    pub fn original_id(&self, node: &u32) -> u32 {
+        self.ids.to_original.local_data()[self.partitioner().local_index(*node)]
+    }
+
+    /// The internal label of the node with label `original` in the input (see `original_id`),
+    /// from any pe. `None` if the graph has no such node.
+    pub fn to_internal(&self, original: u32) -> Option<u32> {
+        if original as usize >= self.num_nodes() {
+            return None;
+        }
+        Some(
+            self.world
+                .block_on(self.ids.to_internal.load(original as usize)),
+        )
+    }
+
+    /// The label in the input (see `original_id`) of the node `node`, from any pe. `None` if the
+    /// graph has no such node.
+    pub fn to_original(&self, node: u32) -> Option<u32> {
+        if node as usize >= self.num_nodes() {
+            None
+        } else if self.node_is_local(&node) {
+            Some(self.original_id(&node))
+        } else {
+            let index = self.partitioner().aligned_index(node);
+            Some(self.world.block_on(self.ids.to_original.load(index)))
+        }
+    }
+
+    pub fn partitioner(&self) -> &Partitioner {
+        self.graph.partitioner()
+    }
+
+    /// Collective: the sum of `local` over all pes, returned on every pe.
+    pub fn global_sum(&self, local: usize) -> usize {
+        load::global_sum(&self.world, local)
+    }
+
+    /// Collective: the element wise sums of `local`, of the same length on every pe, over all pes,
+    /// returned on every pe.
+    pub fn global_sums(&self, local: Vec<usize>) -> Vec<usize> {
+        load::global_sums(&self.world, local)
+    }
+
+    /// Collective: the max of `local` over all pes, returned on every pe.
+    pub fn global_max(&self, local: usize) -> usize {
+        load::global_max(&self.world, local)
+    }
+
+    /// Collective: count the triangles on a single pe with `reference::count_triangles`, to
+    /// validate the distributed counts. Pe 0 returns the count, the other pes `None`.
+    pub fn reference_triangle_count(&self) -> Option<u64> {
+        self.gather_lower_neighbors()
+            .map(|lists| reference::count_triangles(&lists))
+    }
+
+    /// Collective: the lower neighbor lists of all the nodes, gathered on pe 0 for the reference
+    /// computations of `reference`, so the graph has to fit in its memory. The other pes get
+    /// `None`.
+    pub fn gather_lower_neighbors(&self) -> Option<Vec<Vec<u32>>> {
+        let num_lists = if self.my_pe() == 0 {
+            self.num_nodes()
+        } else {
+            0
+        };
+        let lists = LocalRwDarc::new(self.team(), vec![vec![]; num_lists])
+            .block()
+            .unwrap();
+        let task_group = LamellarTaskGroup::new(self.team());
+        let mut batch = vec![];
+        let mut batch_len = 0;
+        for node in self.partitioner().local_nodes(self.my_pe()) {
+            let neighbors = self.graph.lower_neighbors(&node);
+            batch_len += neighbors.len() + 1;
+            batch.push((node, neighbors.to_vec()));
+            if batch_len >= REFERENCE_BATCH {
+                let _ = task_group
+                    .exec_am_pe(
+                        0,
+                        LowerNeighborsAM {
+                            lists: lists.clone(),
+                            node_and_neighbors: std::mem::take(&mut batch),
+                        },
+                    )
+                    .spawn();
+                batch_len = 0;
             }
         }
+        if !batch.is_empty() {
+            let _ = task_group
+                .exec_am_pe(
+                    0,
+                    LowerNeighborsAM {
+                        lists: lists.clone(),
+                        node_and_neighbors: batch,
+                    },
+                )
+                .spawn();
+        }
+        task_group.wait_all();
+        self.world.wait_all();
+        self.barrier();
+        if self.my_pe() != 0 {
+            return None;
+        }
+        let lists = std::mem::take(&mut *lists.write().block());
+        Some(lists)
+    }
+
+    /// Collective: write the graph in the binary format of `binformat`. Pe 0 writes the manifest
+    /// to `path` and every pe writes the lower neighbor lists of its local nodes to the shard
+    /// `path.<pe>`. Either every pe succeeds or every pe returns an error.
+    pub fn dump_to_bin(&self, path: &str) -> Result<(), GraphLoadError> {
+        let path = Path::new(path);
+        let header = BinHeader::new(
+            self.num_nodes(),
+            self.num_edges(),
+            self.relabeled,
+            self.is_weighted(),
+            self.partitioner().strategy().name(),
+            self.num_pes(),
+        );
+        let write = || -> Result<(), GraphLoadError> {
+            if self.my_pe() == 0 {
+                BinWriter::create(path, &header)?.finish()?;
+            }
+            let shard_path = binformat::shard_path(path, self.my_pe());
+            let mut writer = BinWriter::create(&shard_path, &header.for_shard(self.my_pe()))?;
+            for n0 in (0..self.num_nodes()).map(|n| n as u32) {
+                if self.node_is_local(&n0) {
+                    let neighs = self
+                        .graph
+                        .neighbors_iter(&n0)
+                        .take_while(|n| n < &&n0)
+                        .copied()
+                        .collect::<Vec<_>>();
+                    let original = self.original_id(&n0);
+                    if neighs.len() > 0 || original != n0 {
+                        let weights = self
+                            .weights(&n0)
+                            .map_or(vec![], |w| w[..neighs.len()].to_vec());
+                        writer.write(&(n0, original, neighs, weights))?;
+                    }
+                }
+            }
+            writer.finish()?;
+            Ok(())
+        };
+        let res = write();
+        load::agree(&self.world, res)
     }
 }
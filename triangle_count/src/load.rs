@@ -0,0 +1,795 @@
+// Distributed construction of the neighbor lists.
+//
+// Every input (text files, binary files and generated graphs) goes through the same steps:
+//   1. edges are read or generated on all PEs, and every directed entry `src -> dst` is shuffled
+//      with buffered active messages to the PE owning `src` in the input labeling (`src % num_pes`)
+//   2. the number of nodes and the degree histogram of every PE are exchanged, from which every PE
+//      computes the new (degree ordered) label of its own nodes, without a global sort
+//   3. the new labels are published in a cyclic ReadOnlyArray, and every PE looks up the new
+//      labels of the neighbors in its lists with `batch_load`
+// The relabeled lists are then handed to `Graph::distribute`, which sends them to the PE owning
+// the new label, and the label of every node before and after the relabeling is kept in the
+// distributed `IdMap`.
+
+use lamellar::active_messaging::prelude::*;
+use lamellar::array::prelude::*;
+use lamellar::darc::prelude::*;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::matrix_market::{self, Banner};
+use crate::{GraphLoadError, Partitioner};
+
+/// Neighbors (input labels) of the nodes owned by this PE in the input labeling, and the weight
+/// of every `src -> dst` entry if the weights are kept.
+#[derive(Default)]
+pub(crate) struct Adjacency {
+    pub(crate) neighbors: HashMap<u32, HashSet<u32>>,
+    pub(crate) weights: HashMap<(u32, u32), f32>,
+    // label in the original input of the nodes of a binary graph that were saved relabeled
+    pub(crate) originals: HashMap<u32, u32>,
+}
+
+impl Adjacency {
+    /// Number of (deduplicated) directed entries.
+    pub(crate) fn num_entries(&self) -> usize {
+        self.neighbors.values().map(|n| n.len()).sum()
+    }
+}
+
+/// A relabeled neighbor list, ready to be distributed.
+pub(crate) struct NeighborList {
+    pub(crate) node: u32,
+    // label of the node in the input
+    pub(crate) original: u32,
+    // sorted
+    pub(crate) neighbors: Vec<u32>,
+    // weight of each neighbor, empty if the weights are not kept
+    pub(crate) weights: Vec<f32>,
+}
+
+// number of directed entries buffered per destination PE before they are sent
+const SHUFFLE_BATCH: usize = 10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextFormat {
+    // 1-based `row col [value]` entries, see `matrix_market`
+    MatrixMarket,
+    // 1-based `neighbor node` rows of an adjacency listing
+    Tsv,
+    // 0-based `src dst` rows, as in SNAP edge lists
+    EdgeList,
+}
+
+#[lamellar::AmData]
+pub(crate) struct ShuffleEdgesAM {
+    adjacency: LocalRwDarc<Adjacency>,
+    edges: Vec<(u32, u32)>,
+    // empty, or the weight of every edge
+    weights: Vec<f32>,
+    // (node, original label) of binary graph records
+    originals: Vec<(u32, u32)>,
+}
+
+#[lamellar::am]
+impl LamellarAM for ShuffleEdgesAM {
+    async fn exec() {
+        let mut adjacency = self.adjacency.write().await;
+        for (src, dst) in &self.edges {
+            adjacency.neighbors.entry(*src).or_default().insert(*dst);
+        }
+        // duplicate entries keep the largest weight, so the result does not depend on arrival order
+        for (edge, weight) in self.edges.iter().zip(&self.weights) {
+            adjacency
+                .weights
+                .entry(*edge)
+                .and_modify(|w| *w = w.max(*weight))
+                .or_insert(*weight);
+        }
+        adjacency.originals.extend(self.originals.iter().copied());
+    }
+}
+
+/// Per destination PE buffers of directed entries.
+pub(crate) struct EdgeBuffers {
+    adjacency: LocalRwDarc<Adjacency>,
+    buffers: Vec<Vec<(u32, u32)>>,
+    weights: Vec<Vec<f32>>,
+    originals: Vec<Vec<(u32, u32)>>,
+}
+
+impl EdgeBuffers {
+    pub(crate) fn new(adjacency: LocalRwDarc<Adjacency>, num_pes: usize) -> EdgeBuffers {
+        EdgeBuffers {
+            adjacency: adjacency,
+            buffers: vec![vec![]; num_pes],
+            weights: vec![vec![]; num_pes],
+            originals: vec![vec![]; num_pes],
+        }
+    }
+
+    /// Queue `src -> dst`, returning the AM to send (and its PE) once the buffer is full.
+    pub(crate) fn push(&mut self, src: u32, dst: u32) -> Option<(usize, ShuffleEdgesAM)> {
+        let pe = src as usize % self.buffers.len();
+        self.buffers[pe].push((src, dst));
+        self.full(pe)
+    }
+
+    /// Queue `src -> dst` with a weight. Weighted and unweighted entries must not be mixed.
+    pub(crate) fn push_weighted(
+        &mut self,
+        src: u32,
+        dst: u32,
+        weight: f32,
+    ) -> Option<(usize, ShuffleEdgesAM)> {
+        let pe = src as usize % self.buffers.len();
+        self.buffers[pe].push((src, dst));
+        self.weights[pe].push(weight);
+        self.full(pe)
+    }
+
+    /// Queue the label of `node` in the original input, for nodes read from a binary graph.
+    pub(crate) fn push_original(
+        &mut self,
+        node: u32,
+        original: u32,
+    ) -> Option<(usize, ShuffleEdgesAM)> {
+        let pe = node as usize % self.buffers.len();
+        self.originals[pe].push((node, original));
+        self.full(pe)
+    }
+
+    fn full(&mut self, pe: usize) -> Option<(usize, ShuffleEdgesAM)> {
+        if self.buffers[pe].len() + self.originals[pe].len() >= SHUFFLE_BATCH {
+            Some((pe, self.batch(pe)))
+        } else {
+            None
+        }
+    }
+
+    /// The AMs for the partially filled buffers.
+    pub(crate) fn flush(&mut self) -> Vec<(usize, ShuffleEdgesAM)> {
+        (0..self.buffers.len())
+            .filter(|pe| self.buffers[*pe].len() + self.originals[*pe].len() > 0)
+            .map(|pe| (pe, self.batch(pe)))
+            .collect()
+    }
+
+    fn batch(&mut self, pe: usize) -> ShuffleEdgesAM {
+        ShuffleEdgesAM {
+            adjacency: self.adjacency.clone(),
+            edges: std::mem::take(&mut self.buffers[pe]),
+            weights: std::mem::take(&mut self.weights[pe]),
+            originals: std::mem::take(&mut self.originals[pe]),
+        }
+    }
+}
+
+#[lamellar::AmLocalData]
+struct ParseRangeAM {
+    path: PathBuf,
+    format: TextFormat,
+    // `Banner::default()` for files without a banner and for tsv files
+    banner: Banner,
+    keep_weights: bool,
+    start: u64,
+    end: u64,
+    // number of nodes declared in the header, if any
+    max_nodes: Option<usize>,
+    adjacency: LocalRwDarc<Adjacency>,
+}
+
+#[lamellar::local_am]
+impl LamellarAM for ParseRangeAM {
+    // returns one past the largest node id found in the range, and the number of entries read
+    async fn exec() -> Result<(usize, usize), GraphLoadError> {
+        let mut buffers = EdgeBuffers::new(self.adjacency.clone(), lamellar::world.num_pes());
+        let mut num_nodes = 0;
+        let mut num_entries = 0;
+        let send = |batch: Option<(usize, ShuffleEdgesAM)>| {
+            if let Some((pe, am)) = batch {
+                let _ = lamellar::world.exec_am_pe(pe, am).spawn();
+            }
+        };
+        let res = for_each_line(&self.path, self.start, self.end, |offset, line| {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('%') || trimmed.starts_with('#') {
+                return Ok(());
+            }
+            let entry = match self.format {
+                TextFormat::MatrixMarket => self.banner.parse_entry(trimmed),
+                TextFormat::Tsv => parse_entry(trimmed, 1).map(|(e0, e1)| (e0, e1, 1.0)),
+                TextFormat::EdgeList => parse_entry(trimmed, 0).map(|(e0, e1)| (e0, e1, 1.0)),
+            };
+            let (e0, e1, weight) = entry.ok_or_else(|| GraphLoadError::MalformedLine {
+                path: self.path.clone(),
+                line: line_number(&self.path, offset),
+                content: line.to_string(),
+            })?;
+            let max = std::cmp::max(e0, e1) as usize + 1;
+            if let Some(max_nodes) = self.max_nodes {
+                if max > max_nodes {
+                    return Err(GraphLoadError::VertexOutOfRange {
+                        vertex: max,
+                        num_nodes: max_nodes,
+                    });
+                }
+            }
+            num_nodes = std::cmp::max(num_nodes, max);
+            num_entries += 1;
+            // self loops are dropped, every other entry is added in both directions
+            let edges = self.banner.directed_edges(e0, e1, weight);
+            for (src, dst, weight) in edges.into_iter().flatten() {
+                if self.keep_weights {
+                    send(buffers.push_weighted(src, dst, weight));
+                } else {
+                    send(buffers.push(src, dst));
+                }
+            }
+            Ok(())
+        });
+        for batch in buffers.flush() {
+            send(Some(batch));
+        }
+        res.map(|_| (num_nodes, num_entries))
+    }
+}
+
+// the two node ids (starting at `first_id`) at the start of an entry, as 0-based ids
+fn parse_entry(line: &str, first_id: u32) -> Option<(u32, u32)> {
+    let mut vals = line.split_whitespace().map(|val| {
+        val.parse::<u32>()
+            .ok()
+            .and_then(|id| id.checked_sub(first_id))
+    });
+    match (vals.next(), vals.next()) {
+        (Some(Some(e0)), Some(Some(e1))) => Some((e0, e1)),
+        _ => None,
+    }
+}
+
+// call `f` with the offset and contents of every line starting in the byte range [start, end) of the file
+fn for_each_line(
+    path: &Path,
+    start: u64,
+    end: u64,
+    mut f: impl FnMut(u64, &str) -> Result<(), GraphLoadError>,
+) -> Result<(), GraphLoadError> {
+    let mut rdr = BufReader::new(File::open(path)?);
+    let mut pos = start;
+    let mut line = String::new();
+    if start > 0 {
+        // a line starting before our range belongs to the previous range
+        rdr.seek(SeekFrom::Start(start - 1))?;
+        pos = start - 1 + rdr.read_line(&mut line)? as u64;
+    }
+    while pos < end {
+        line.clear();
+        let len = rdr.read_line(&mut line)?;
+        if len == 0 {
+            break;
+        }
+        f(pos, &line)?;
+        pos += len as u64;
+    }
+    Ok(())
+}
+
+// line number (starting at 1) of the line starting at `offset`, only computed to report errors
+fn line_number(path: &Path, offset: u64) -> usize {
+    match File::open(path) {
+        Ok(file) => {
+            1 + BufReader::new(file)
+                .take(offset)
+                .bytes()
+                .filter(|b| matches!(b, Ok(b'\n')))
+                .count()
+        }
+        Err(_) => 0,
+    }
+}
+
+/// The header of a MatrixMarket file.
+struct MmHeader {
+    banner: Banner,
+    num_nodes: usize,
+    num_entries: usize,
+    // offset of the first entry
+    data_start: u64,
+}
+
+fn mm_header(path: &Path) -> Result<MmHeader, GraphLoadError> {
+    let mut rdr = BufReader::new(File::open(path)?);
+    let mut pos = 0;
+    let mut line_num = 0;
+    let mut line = String::new();
+    let mut banner = Banner::default();
+    loop {
+        line.clear();
+        let len = rdr.read_line(&mut line)?;
+        pos += len as u64;
+        line_num += 1;
+        if line_num == 1 {
+            if let Some(b) = Banner::parse(&line).map_err(GraphLoadError::InvalidMatrixMarket)? {
+                banner = b;
+            }
+        }
+        if len == 0 || !line.starts_with('%') {
+            break;
+        }
+    }
+    let (rows, cols, num_entries) =
+        matrix_market::parse_size(&line).ok_or_else(|| GraphLoadError::MalformedLine {
+            path: path.to_path_buf(),
+            line: line_num,
+            content: line.clone(),
+        })?;
+    matrix_market::check_size(rows, cols)?;
+    Ok(MmHeader {
+        banner: banner,
+        num_nodes: rows,
+        num_entries: num_entries,
+        data_start: pos,
+    })
+}
+
+/// A text file parsed by `read_text`.
+pub(crate) struct TextGraph {
+    pub(crate) adjacency: LocalRwDarc<Adjacency>,
+    pub(crate) num_nodes: usize,
+    // the adjacency holds the edge weights
+    pub(crate) weighted: bool,
+}
+
+/// Collective: parse a text file with all PEs and the local worker threads, each parsing a byte
+/// range, and shuffle the entries to their owners. Self loops are dropped and every edge is
+/// added in both directions. The values of MatrixMarket `integer` and `real` matrices are kept
+/// as edge weights if `keep_weights` is set.
+pub(crate) fn read_text(
+    world: &LamellarWorld,
+    path: &Path,
+    format: TextFormat,
+    keep_weights: bool,
+) -> Result<TextGraph, GraphLoadError> {
+    let adjacency = LocalRwDarc::new(world.team(), Adjacency::default())
+        .block()
+        .unwrap(); // we are creating with the world team so should be valid on all pes
+    let header = match format {
+        TextFormat::MatrixMarket => mm_header(path).map(Some),
+        TextFormat::Tsv | TextFormat::EdgeList => Ok(None),
+    };
+    let (banner, declared_entries) = match &header {
+        Ok(Some(header)) => (header.banner, Some(header.num_entries)),
+        _ => (Banner::default(), None),
+    };
+    let keep_weights = keep_weights && banner.has_values();
+    let size = std::fs::metadata(path).map_err(GraphLoadError::from);
+    let num_threads = match std::env::var("LAMELLAR_THREADS") {
+        Ok(n) => n.parse::<usize>().unwrap_or(1),
+        Err(_) => 1,
+    };
+
+    let local_counts = match (header, size) {
+        (Ok(header), Ok(size)) => {
+            let max_nodes = header.as_ref().map(|h| h.num_nodes);
+            let data_start = header.as_ref().map_or(0, |h| h.data_start);
+            // the data is split into num_pes * num_threads ranges, one per local AM
+            let size = size.len();
+            let num_ranges = (world.num_pes() * num_threads) as u64;
+            let range_start = |i: u64| data_start + (size - data_start) * i / num_ranges;
+            let mut reqs = vec![];
+            for tid in 0..num_threads {
+                let range = (world.my_pe() * num_threads + tid) as u64;
+                reqs.push(world.exec_am_local(ParseRangeAM {
+                    path: path.to_path_buf(),
+                    format: format,
+                    banner: banner,
+                    keep_weights: keep_weights,
+                    start: range_start(range),
+                    end: range_start(range + 1),
+                    max_nodes: max_nodes,
+                    adjacency: adjacency.clone(),
+                }));
+            }
+            world
+                .block_on(async move {
+                    let mut counts = Ok((0, 0));
+                    for req in reqs {
+                        let res = req.await;
+                        counts = counts
+                            .and_then(|(n, e)| res.map(|(m, f)| (std::cmp::max(n, m), e + f)));
+                    }
+                    counts
+                })
+                .map(|(num_nodes, num_entries)| {
+                    (
+                        std::cmp::max(num_nodes, max_nodes.unwrap_or(0)),
+                        num_entries,
+                    )
+                })
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+    world.wait_all(); // our shuffle AMs have been delivered
+    world.barrier(); // everyone's have
+    let (local_nodes, local_entries) = agree(world, local_counts)?;
+    let num_nodes = global_max(world, local_nodes);
+    let num_entries = global_sum(world, local_entries);
+    if let Some(declared) = declared_entries {
+        // same totals on every pe, so every pe fails
+        if declared != num_entries {
+            return Err(GraphLoadError::InvalidMatrixMarket(format!(
+                "the header declares {declared} entries but {num_entries} were read"
+            )));
+        }
+    }
+    Ok(TextGraph {
+        adjacency: adjacency,
+        num_nodes: num_nodes,
+        weighted: keep_weights,
+    })
+}
+
+#[lamellar::AmData]
+struct LoadErrorAM {
+    error: Darc<Mutex<Option<(usize, String)>>>,
+    pe: usize,
+    message: String,
+}
+
+#[lamellar::am]
+impl LamellarAM for LoadErrorAM {
+    async fn exec() {
+        let mut error = self.error.lock().unwrap();
+        if error.as_ref().map_or(true, |(pe, _)| self.pe < *pe) {
+            *error = Some((self.pe, self.message.clone()));
+        }
+    }
+}
+
+/// Collective: make every pe fail if loading failed on any pe, so no pe is left waiting for the
+/// others at a later barrier. A pe keeps its own error, the others get the lowest failing pe's.
+pub(crate) fn agree<T>(
+    world: &LamellarWorld,
+    result: Result<T, GraphLoadError>,
+) -> Result<T, GraphLoadError> {
+    let error = Darc::new(world, Mutex::new(None)).block().unwrap();
+    if let Err(e) = &result {
+        world.block_on(world.exec_am_all(LoadErrorAM {
+            error: error.clone(),
+            pe: world.my_pe(),
+            message: e.to_string(),
+        }));
+    }
+    world.barrier();
+    let remote = error.lock().unwrap().take();
+    match (result, remote) {
+        (Ok(_), Some((pe, message))) => Err(GraphLoadError::OtherPe { pe, message }),
+        (result, _) => result,
+    }
+}
+
+#[lamellar::AmData]
+struct MaxAM {
+    max: Darc<AtomicUsize>,
+    value: usize,
+}
+
+#[lamellar::am]
+impl LamellarAM for MaxAM {
+    async fn exec() {
+        self.max.fetch_max(self.value, Ordering::SeqCst);
+    }
+}
+
+#[lamellar::AmData]
+struct SumAM {
+    total: Darc<AtomicUsize>,
+    value: usize,
+}
+
+#[lamellar::am]
+impl LamellarAM for SumAM {
+    async fn exec() {
+        self.total.fetch_add(self.value, Ordering::SeqCst);
+    }
+}
+
+// collective: the largest `local` over all pes, returned on every pe
+pub(crate) fn global_max(world: &LamellarWorld, local: usize) -> usize {
+    let max = Darc::new(world, AtomicUsize::new(0)).block().unwrap();
+    world.block_on(world.exec_am_all(MaxAM {
+        max: max.clone(),
+        value: local,
+    }));
+    world.barrier();
+    max.load(Ordering::SeqCst)
+}
+
+// collective: the sum of `local` over all pes, returned on every pe
+pub(crate) fn global_sum(world: &LamellarWorld, local: usize) -> usize {
+    let total = Darc::new(world, AtomicUsize::new(0)).block().unwrap();
+    world.block_on(world.exec_am_all(SumAM {
+        total: total.clone(),
+        value: local,
+    }));
+    world.barrier();
+    total.load(Ordering::SeqCst)
+}
+
+#[lamellar::AmData]
+struct SumsAM {
+    totals: Darc<Mutex<Vec<usize>>>,
+    values: Vec<usize>,
+}
+
+#[lamellar::am]
+impl LamellarAM for SumsAM {
+    async fn exec() {
+        let mut totals = self.totals.lock().unwrap();
+        for (total, value) in totals.iter_mut().zip(&self.values) {
+            *total += value;
+        }
+    }
+}
+
+// collective: the element wise sums of `local` (of the same length on every pe) over all pes,
+// returned on every pe
+pub(crate) fn global_sums(world: &LamellarWorld, local: Vec<usize>) -> Vec<usize> {
+    let totals = Darc::new(world, Mutex::new(vec![0; local.len()]))
+        .block()
+        .unwrap();
+    world.block_on(world.exec_am_all(SumsAM {
+        totals: totals.clone(),
+        values: local,
+    }));
+    world.barrier();
+    let totals = std::mem::take(&mut *totals.lock().unwrap());
+    totals
+}
+
+#[lamellar::AmData]
+struct DegreesAM {
+    degrees: Darc<Mutex<Vec<u32>>>,
+    node_degrees: Vec<(u32, u32)>,
+}
+
+#[lamellar::am]
+impl LamellarAM for DegreesAM {
+    async fn exec() {
+        let mut degrees = self.degrees.lock().unwrap();
+        for (node, degree) in &self.node_degrees {
+            degrees[*node as usize] = *degree;
+        }
+    }
+}
+
+/// Collective: the degree of every node (new labels) of the graph, on every pe.
+pub(crate) fn gather_degrees(
+    world: &LamellarWorld,
+    neighbor_lists: &[NeighborList],
+    num_nodes: usize,
+) -> Vec<u32> {
+    let degrees = Darc::new(world, Mutex::new(vec![0; num_nodes]))
+        .block()
+        .unwrap();
+    let node_degrees = neighbor_lists
+        .iter()
+        .map(|list| {
+            // without the entry of nodes without neighbors
+            let degree = list.neighbors.iter().filter(|n| (**n as usize) < num_nodes);
+            (list.node, degree.count() as u32)
+        })
+        .collect::<Vec<_>>();
+    for batch in node_degrees.chunks(SHUFFLE_BATCH) {
+        let _ = world
+            .exec_am_all(DegreesAM {
+                degrees: degrees.clone(),
+                node_degrees: batch.to_vec(),
+            })
+            .spawn();
+    }
+    world.wait_all();
+    world.barrier();
+    let degrees = std::mem::take(&mut *degrees.lock().unwrap());
+    degrees
+}
+
+#[lamellar::AmData]
+struct DegreeHistogramAM {
+    histograms: Darc<Mutex<Vec<Vec<(usize, usize)>>>>,
+    pe: usize,
+    histogram: Vec<(usize, usize)>,
+}
+
+#[lamellar::am]
+impl LamellarAM for DegreeHistogramAM {
+    async fn exec() {
+        self.histograms.lock().unwrap()[self.pe] = self.histogram.clone();
+    }
+}
+
+// collective: the new label of every local node (`my_pe + i * num_pes` gets `labels[i]`), in
+// decreasing degree order. Ties are broken by pe, then by input label.
+fn degree_labels(world: &LamellarWorld, degrees: &[usize]) -> Vec<u32> {
+    let mut histogram: BTreeMap<usize, usize> = BTreeMap::new();
+    for degree in degrees {
+        *histogram.entry(*degree).or_default() += 1;
+    }
+    let histograms = Darc::new(world, Mutex::new(vec![vec![]; world.num_pes()]))
+        .block()
+        .unwrap();
+    world.block_on(world.exec_am_all(DegreeHistogramAM {
+        histograms: histograms.clone(),
+        pe: world.my_pe(),
+        histogram: histogram.into_iter().collect(),
+    }));
+    world.barrier();
+    let histograms = histograms.lock().unwrap();
+
+    // first label of each degree: the number of nodes with a higher degree, plus the number of
+    // nodes with the same degree on the lower pes
+    let mut totals: BTreeMap<usize, usize> = BTreeMap::new();
+    for histogram in histograms.iter() {
+        for (degree, count) in histogram {
+            *totals.entry(*degree).or_default() += count;
+        }
+    }
+    let mut next_label = HashMap::new();
+    let mut first = 0;
+    for (degree, count) in totals.iter().rev() {
+        next_label.insert(*degree, first);
+        first += count;
+    }
+    for histogram in &histograms[..world.my_pe()] {
+        for (degree, count) in histogram {
+            *next_label.get_mut(degree).unwrap() += count;
+        }
+    }
+
+    degrees
+        .iter()
+        .map(|degree| {
+            let label = next_label.get_mut(degree).unwrap();
+            *label += 1;
+            (*label - 1) as u32
+        })
+        .collect()
+}
+
+/// Collective: relabel the nodes owned by this pe (and their neighbors) by decreasing degree, or
+/// keep the input labels. Returns the sorted new neighbor labels of every local node (with their
+/// weights if the adjacency has any); nodes without neighbors get the single entry
+/// `num_nodes + 1`, which is larger than any node.
+pub(crate) fn relabel(
+    world: &LamellarWorld,
+    adjacency: &Adjacency,
+    num_nodes: usize,
+    by_degree: bool,
+) -> Vec<NeighborList> {
+    let my_pe = world.my_pe();
+    let num_pes = world.num_pes();
+    let local_nodes = (my_pe..num_nodes).step_by(num_pes).collect::<Vec<_>>();
+    let neighbors = local_nodes
+        .iter()
+        .map(|node| match adjacency.neighbors.get(&(*node as u32)) {
+            Some(neighbors) => neighbors.iter().copied().collect(),
+            None => vec![],
+        })
+        .collect::<Vec<Vec<u32>>>();
+    let weighted = !adjacency.weights.is_empty();
+
+    let labels = if by_degree {
+        let degrees = neighbors.iter().map(|n| n.len()).collect::<Vec<_>>();
+        degree_labels(world, &degrees)
+    } else {
+        local_nodes.iter().map(|node| *node as u32).collect()
+    };
+
+    // node `my_pe + i * num_pes` lives at local offset `i` of a cyclic array
+    let label_map = UnsafeArray::<u32>::new(world.team(), num_nodes, Distribution::Cyclic).block();
+    unsafe { label_map.local_as_mut_slice() }.copy_from_slice(&labels);
+    let label_map = label_map.into_read_only().block();
+
+    let old_neighbors = neighbors
+        .iter()
+        .flat_map(|n| n.iter().map(|node| *node as usize))
+        .collect::<Vec<_>>();
+    let new_neighbors = if old_neighbors.len() > 0 {
+        world.block_on(label_map.batch_load(old_neighbors))
+    } else {
+        vec![]
+    };
+    world.barrier(); // everyone is done reading the label map
+
+    let mut new_neighbors = new_neighbors.into_iter();
+    labels
+        .into_iter()
+        .zip(local_nodes.iter().zip(neighbors.iter()))
+        .map(|(label, (node, old))| {
+            let mut new = old
+                .iter()
+                .zip(new_neighbors.by_ref())
+                .map(|(dst, new)| {
+                    let weight = if weighted {
+                        adjacency.weights[&(*node as u32, *dst)]
+                    } else {
+                        0.0
+                    };
+                    (new, weight)
+                })
+                .collect::<Vec<_>>();
+            new.sort_unstable_by_key(|(new, _)| *new);
+            let (mut neighbors, weights): (Vec<u32>, Vec<f32>) = new.into_iter().unzip();
+            if neighbors.is_empty() {
+                neighbors.push((num_nodes + 1) as u32);
+            }
+            NeighborList {
+                node: label,
+                original: adjacency
+                    .originals
+                    .get(&(*node as u32))
+                    .copied()
+                    .unwrap_or(*node as u32),
+                neighbors: neighbors,
+                weights: if weighted { weights } else { vec![] },
+            }
+        })
+        .collect()
+}
+
+/// Both directions of the relabeling, readable from any pe. `to_internal` is indexed by the
+/// original label, `to_original` by the `Partitioner::aligned_index` of the internal label, so
+/// every pe holds the original labels of its own nodes.
+#[derive(Clone)]
+pub(crate) struct IdMap {
+    pub(crate) to_internal: ReadOnlyArray<u32>,
+    pub(crate) to_original: ReadOnlyArray<u32>,
+}
+
+/// Collective: publish the original and internal labels of the relabeled lists of this pe.
+pub(crate) fn id_map(
+    world: &LamellarWorld,
+    neighbor_lists: &[NeighborList],
+    num_nodes: usize,
+    partitioner: &Partitioner,
+) -> IdMap {
+    let to_internal = AtomicArray::<u32>::new(world.team(), num_nodes, Distribution::Block).block();
+    let to_original = AtomicArray::<u32>::new(
+        world.team(),
+        world.num_pes() * partitioner.max_local_nodes().max(1),
+        Distribution::Block,
+    )
+    .block();
+    if neighbor_lists.len() > 0 {
+        let originals = neighbor_lists
+            .iter()
+            .map(|list| list.original as usize)
+            .collect::<Vec<_>>();
+        let internals = neighbor_lists
+            .iter()
+            .map(|list| list.node)
+            .collect::<Vec<_>>();
+        world.block_on(to_internal.batch_store(originals, internals));
+        let slots = neighbor_lists
+            .iter()
+            .map(|list| partitioner.aligned_index(list.node))
+            .collect::<Vec<_>>();
+        let originals = neighbor_lists
+            .iter()
+            .map(|list| list.original)
+            .collect::<Vec<_>>();
+        world.block_on(to_original.batch_store(slots, originals));
+    }
+    world.wait_all();
+    world.barrier(); // every label is stored
+    IdMap {
+        to_internal: to_internal.into_read_only().block(),
+        to_original: to_original.into_read_only().block(),
+    }
+}
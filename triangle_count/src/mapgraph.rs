@@ -2,12 +2,14 @@ use lamellar::memregion::prelude::*;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::partition::Partitioner;
 use crate::GraphOps;
 // use crate::Element;
 
 pub struct MapGraph {
     team: Arc<LamellarTeam>,
     neighbors: HashMap<u32, OneSidedMemoryRegion<u32>>,
+    partitioner: Arc<Partitioner>,
     // num_nodes: usize,
 }
 pub struct MapGraphIter<'a> {
@@ -26,9 +28,11 @@ pub struct MapGraphIter<'a> {
 
 impl MapGraph {
     pub fn new(team: Arc<LamellarTeam>) -> MapGraph {
+        let num_pes = team.num_pes();
         MapGraph {
             team: team,
             neighbors: HashMap::new(),
+            partitioner: Arc::new(Partitioner::cyclic(num_pes)),
             // num_nodes: 0,
         }
     }
@@ -37,17 +41,9 @@ impl MapGraph {
             iter: self.neighbors.keys(),
         }
     }
-    // pub fn range_iter(&self,start:usize, end:usize) -> MapGraphRangeIter<'_> {
-    //     MapGraphRangeIter{
-    //         neighbors: &self.neighbors,
-    //         cur: start,
-    //         end: end,
-    //     }
-    // }
-}
-
-impl GraphOps for MapGraph {
-    fn add_local_neighbors(
+    /// Copy the neighbor list of a local node into a region of this pe, returning it so it can
+    /// be announced to the other pes with `add_remote_neighbors`.
+    pub fn add_local_neighbors(
         &mut self,
         node: u32,
         neighbors: OneSidedMemoryRegion<u32>,
@@ -63,9 +59,20 @@ impl GraphOps for MapGraph {
         self.neighbors.insert(node, lmr_neighbors.clone());
         lmr_neighbors
     }
-    fn add_remote_neighbors(&mut self, node: u32, neighbors: OneSidedMemoryRegion<u32>) {
+    /// Register the neighbor list of a node owned by another pe.
+    pub fn add_remote_neighbors(&mut self, node: u32, neighbors: OneSidedMemoryRegion<u32>) {
         self.neighbors.insert(node, neighbors);
     }
+    // pub fn range_iter(&self,start:usize, end:usize) -> MapGraphRangeIter<'_> {
+    //     MapGraphRangeIter{
+    //         neighbors: &self.neighbors,
+    //         cur: start,
+    //         end: end,
+    //     }
+    // }
+}
+
+impl GraphOps for MapGraph {
     fn neighbors(&self, node: &u32) -> std::slice::Iter<'_, u32> {
         if let Some(n) = self.neighbors.get(node) {
             match unsafe { n.as_slice() } {
@@ -93,8 +100,16 @@ impl GraphOps for MapGraph {
     }
 
     fn node_is_local(&self, node: &u32) -> bool {
-        //probably should abstract this out to the graphops trait
-        *node as usize % self.team.num_pes() == self.team.team_pe_id().unwrap()
+        self.partitioner
+            .owns(self.team.team_pe_id().unwrap(), *node)
+    }
+
+    fn set_partitioner(&mut self, partitioner: Arc<Partitioner>) {
+        self.partitioner = partitioner;
+    }
+
+    fn partitioner(&self) -> &Partitioner {
+        &self.partitioner
     }
 }
 
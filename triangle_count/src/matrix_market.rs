@@ -0,0 +1,262 @@
+// MatrixMarket coordinate files (https://math.nist.gov/MatrixMarket/formats.html):
+//
+//   %%MatrixMarket matrix coordinate <field> <symmetry>
+//   % comments
+//   <rows> <cols> <entries>
+//   <row> <col> [<value>]
+//   ...
+//
+// Every matrix is read as an undirected graph: an entry (i, j) is the edge {i - 1, j - 1}. For
+// `general` matrices both (i, j) and (j, i) may be present, for the other symmetries only one
+// triangle is stored and the mirrored entry is implied. Self loops (i == j) are dropped.
+// Files without a banner are read as `pattern general`, and extra columns are ignored.
+
+use crate::GraphLoadError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Pattern,
+    Integer,
+    Real,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+    Hermitian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Banner {
+    pub field: Field,
+    pub symmetry: Symmetry,
+}
+
+impl Default for Banner {
+    fn default() -> Self {
+        Banner {
+            field: Field::Pattern,
+            symmetry: Symmetry::General,
+        }
+    }
+}
+
+impl Banner {
+    /// Parse a `%%MatrixMarket` banner line, `Ok(None)` if the line is not a banner.
+    pub fn parse(line: &str) -> Result<Option<Banner>, String> {
+        let words = line
+            .split_whitespace()
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<_>>();
+        if words.first().map(String::as_str) != Some("%%matrixmarket") {
+            return Ok(None);
+        }
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        let (object, format, field, symmetry) = match words[1..] {
+            [object, format, field, symmetry] => (object, format, field, symmetry),
+            _ => return Err(format!("malformed banner {:?}", line.trim_end())),
+        };
+        if object != "matrix" {
+            return Err(format!(
+                "unsupported object {object}, only matrix is supported"
+            ));
+        }
+        if format != "coordinate" {
+            return Err(format!(
+                "unsupported format {format}, only coordinate is supported"
+            ));
+        }
+        let field = match field {
+            "pattern" => Field::Pattern,
+            "integer" => Field::Integer,
+            "real" | "double" => Field::Real,
+            _ => return Err(format!("unsupported field {field}")),
+        };
+        let symmetry = match symmetry {
+            "general" => Symmetry::General,
+            "symmetric" => Symmetry::Symmetric,
+            "skew-symmetric" => Symmetry::SkewSymmetric,
+            "hermitian" => Symmetry::Hermitian,
+            _ => return Err(format!("unsupported symmetry {symmetry}")),
+        };
+        Ok(Some(Banner { field, symmetry }))
+    }
+
+    pub fn has_values(&self) -> bool {
+        self.field != Field::Pattern
+    }
+
+    /// The 0-based endpoints and value (1.0 for pattern matrices) of an entry line,
+    /// `None` if the line is malformed.
+    pub fn parse_entry(&self, line: &str) -> Option<(u32, u32, f32)> {
+        let mut vals = line.split_whitespace();
+        let mut id = || {
+            vals.next()
+                .and_then(|v| v.parse::<u32>().ok())
+                .and_then(|id| id.checked_sub(1))
+        };
+        let (row, col) = (id()?, id()?);
+        let value = match self.field {
+            Field::Pattern => 1.0,
+            Field::Integer | Field::Real => vals.next()?.parse::<f32>().ok()?,
+        };
+        Some((row, col, value))
+    }
+
+    /// The value of the mirrored entry (j, i) implied by the entry (i, j) of a matrix with this
+    /// symmetry.
+    pub fn mirrored_value(&self, value: f32) -> f32 {
+        match self.symmetry {
+            Symmetry::SkewSymmetric => -value,
+            _ => value, // general matrices are symmetrized, hermitian values are real here
+        }
+    }
+
+    /// The two directed edges `(src, dst, value)` of the entry (row, col), `None` for a self
+    /// loop, which is dropped.
+    pub fn directed_edges(&self, row: u32, col: u32, value: f32) -> Option<[(u32, u32, f32); 2]> {
+        if row == col {
+            return None;
+        }
+        Some([(row, col, value), (col, row, self.mirrored_value(value))])
+    }
+}
+
+/// The size line: `(rows, cols, entries)`.
+pub fn parse_size(line: &str) -> Option<(usize, usize, usize)> {
+    let vals = line
+        .split_whitespace()
+        .map(|val| val.parse::<usize>().ok())
+        .collect::<Vec<_>>();
+    match vals[..] {
+        [Some(rows), Some(cols), Some(entries)] => Some((rows, cols, entries)),
+        _ => None,
+    }
+}
+
+/// Check the size line of a graph: the matrix must be square.
+pub fn check_size(rows: usize, cols: usize) -> Result<(), GraphLoadError> {
+    if rows != cols {
+        return Err(GraphLoadError::NonSquareHeader { rows, cols });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn banner(line: &str) -> Banner {
+        Banner::parse(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn banners_set_the_field_and_symmetry() {
+        let cases = [
+            ("pattern general", Field::Pattern, Symmetry::General),
+            ("integer symmetric", Field::Integer, Symmetry::Symmetric),
+            ("real skew-symmetric", Field::Real, Symmetry::SkewSymmetric),
+            ("double hermitian", Field::Real, Symmetry::Hermitian),
+        ];
+        for (rest, field, symmetry) in cases {
+            let line = format!("%%MatrixMarket matrix coordinate {rest}\n");
+            assert_eq!(banner(&line), Banner { field, symmetry });
+        }
+        // the banner is case insensitive
+        assert_eq!(
+            banner("%%matrixmarket MATRIX Coordinate Pattern Symmetric"),
+            Banner {
+                field: Field::Pattern,
+                symmetry: Symmetry::Symmetric
+            }
+        );
+        assert_eq!(Banner::parse("% a comment").unwrap(), None);
+        assert_eq!(Banner::parse("3 3 2").unwrap(), None);
+        assert_eq!(
+            Banner::default(),
+            banner("%%MatrixMarket matrix coordinate pattern general")
+        );
+    }
+
+    #[test]
+    fn unsupported_banners_are_rejected() {
+        for line in [
+            "%%MatrixMarket matrix coordinate real",
+            "%%MatrixMarket matrix coordinate real general extra",
+            "%%MatrixMarket vector coordinate real general",
+            "%%MatrixMarket matrix array real general",
+            "%%MatrixMarket matrix coordinate complex general",
+            "%%MatrixMarket matrix coordinate real lower",
+        ] {
+            assert!(Banner::parse(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn entries_follow_the_field() {
+        let pattern = Banner::default();
+        assert!(!pattern.has_values());
+        assert_eq!(pattern.parse_entry("3 1"), Some((2, 0, 1.0)));
+        // extra columns are ignored, a pattern entry has no value
+        assert_eq!(pattern.parse_entry("3 1 7.5 x"), Some((2, 0, 1.0)));
+        let integer = banner("%%MatrixMarket matrix coordinate integer general");
+        assert!(integer.has_values());
+        assert_eq!(integer.parse_entry("1 2 -4"), Some((0, 1, -4.0)));
+        let real = banner("%%MatrixMarket matrix coordinate real symmetric");
+        assert_eq!(real.parse_entry("4 2 2.5e-1"), Some((3, 1, 0.25)));
+        // missing or invalid values and ids, and 0 ids (the entries are 1-based)
+        assert_eq!(real.parse_entry("4 2"), None);
+        assert_eq!(real.parse_entry("4 2 x"), None);
+        assert_eq!(pattern.parse_entry("4"), None);
+        assert_eq!(pattern.parse_entry("a 2"), None);
+        assert_eq!(pattern.parse_entry("0 2"), None);
+    }
+
+    #[test]
+    fn mirrored_entries_follow_the_symmetry() {
+        let general = Banner::default();
+        let symmetric = banner("%%MatrixMarket matrix coordinate real symmetric");
+        let skew = banner("%%MatrixMarket matrix coordinate real skew-symmetric");
+        let hermitian = banner("%%MatrixMarket matrix coordinate real hermitian");
+        assert_eq!(general.mirrored_value(2.0), 2.0);
+        assert_eq!(symmetric.mirrored_value(2.0), 2.0);
+        assert_eq!(skew.mirrored_value(2.0), -2.0);
+        assert_eq!(hermitian.mirrored_value(2.0), 2.0);
+        assert_eq!(
+            skew.directed_edges(3, 1, 1.5),
+            Some([(3, 1, 1.5), (1, 3, -1.5)])
+        );
+        assert_eq!(
+            general.directed_edges(0, 2, 1.0),
+            Some([(0, 2, 1.0), (2, 0, 1.0)])
+        );
+    }
+
+    #[test]
+    fn self_loops_are_dropped() {
+        for banner in [
+            Banner::default(),
+            banner("%%MatrixMarket matrix coordinate real skew-symmetric"),
+        ] {
+            let (row, col, value) = banner.parse_entry("5 5 1").unwrap();
+            assert_eq!(banner.directed_edges(row, col, value), None);
+        }
+    }
+
+    #[test]
+    fn size_lines() {
+        assert_eq!(parse_size("4 4 10"), Some((4, 4, 10)));
+        assert_eq!(parse_size("  4\t4 10 \n"), Some((4, 4, 10)));
+        assert_eq!(parse_size("4 4"), None);
+        assert_eq!(parse_size("4 4 10 1"), None);
+        assert_eq!(parse_size("4 4 -1"), None);
+        assert_eq!(parse_size(""), None);
+        assert!(check_size(4, 4).is_ok());
+        assert!(matches!(
+            check_size(3, 4),
+            Err(GraphLoadError::NonSquareHeader { rows: 3, cols: 4 })
+        ));
+    }
+}
@@ -0,0 +1,381 @@
+use lamellar::active_messaging::prelude::*;
+use lamellar::array::prelude::*;
+use lamellar::darc::prelude::*;
+use lamellar::memregion::prelude::*;
+use lamellar_graph::cli::GraphArgs;
+use lamellar_graph::{Graph, GraphData};
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    // every node sends its contribution to the owners of its neighbors with active messages
+    Push,
+    // every node reads the contributions of its neighbors from a distributed array, one sided
+    Pull,
+}
+
+impl Variant {
+    fn name(&self) -> &'static str {
+        match self {
+            Variant::Push => "push",
+            Variant::Pull => "pull",
+        }
+    }
+}
+
+impl std::str::FromStr for Variant {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "push" => Ok(Variant::Push),
+            "pull" => Ok(Variant::Pull),
+            _ => Err(format!("unknown variant {s} (expected push or pull)")),
+        }
+    }
+}
+
+#[lamellar::AmData]
+struct ContributionsAm {
+    graph: Darc<GraphData>,
+    sums: Darc<Mutex<Vec<f64>>>,
+    contributions: Vec<(u32, f64)>, // (node, contribution of one of its neighbors)
+}
+
+#[lamellar::am]
+impl LamellarAM for ContributionsAm {
+    async fn exec() {
+        let partitioner = self.graph.partitioner();
+        let mut sums = self.sums.lock().unwrap();
+        for (node, contribution) in &self.contributions {
+            sums[partitioner.local_index(*node)] += contribution;
+        }
+    }
+}
+
+#[lamellar::AmData]
+struct SumAm {
+    totals: Darc<Mutex<[f64; 2]>>,
+    slot: usize,
+    value: f64,
+}
+
+#[lamellar::am]
+impl LamellarAM for SumAm {
+    async fn exec() {
+        self.totals.lock().unwrap()[self.slot] += self.value;
+    }
+}
+
+// sums over all pes into an accumulator allocated once. Consecutive sums use alternate slots: the
+// next contribution to a slot is sent after the barrier of the following sum, which no pe passes
+// before it has read and reset its copy of the slot.
+struct GlobalSum {
+    totals: Darc<Mutex<[f64; 2]>>,
+    calls: usize,
+}
+
+impl GlobalSum {
+    // collective
+    fn new(world: &LamellarWorld) -> GlobalSum {
+        GlobalSum {
+            totals: Darc::new(world, Mutex::new([0.0; 2])).block().unwrap(),
+            calls: 0,
+        }
+    }
+
+    // collective: the sum of `local` over all pes, returned on every pe
+    fn sum(&mut self, world: &LamellarWorld, local: f64) -> f64 {
+        let slot = self.calls % 2;
+        self.calls += 1;
+        world.block_on(world.exec_am_all(SumAm {
+            totals: self.totals.clone(),
+            slot: slot,
+            value: local,
+        }));
+        world.barrier();
+        std::mem::take(&mut self.totals.lock().unwrap()[slot])
+    }
+}
+
+// collective: add the contribution of every local node to `sums` on the owners of its neighbors
+fn push(
+    world: &LamellarWorld,
+    graph: &Graph,
+    nodes: &[u32],
+    contributions: &[f64],
+    sums: &Darc<Mutex<Vec<f64>>>,
+    buf_size: usize,
+) {
+    let data = graph.data();
+    let partitioner = graph.partitioner();
+    let mut buffers = vec![vec![]; graph.num_pes()];
+    let send = |pe: usize, contributions: Vec<(u32, f64)>| {
+        let _ = world
+            .exec_am_pe(
+                pe,
+                ContributionsAm {
+                    graph: data.clone(),
+                    sums: sums.clone(),
+                    contributions: contributions,
+                },
+            )
+            .spawn();
+    };
+    for (node, contribution) in nodes.iter().zip(contributions) {
+        for neighbor in data.real_neighbors(node) {
+            let pe = partitioner.owner(*neighbor);
+            buffers[pe].push((*neighbor, *contribution));
+            if buffers[pe].len() >= buf_size {
+                send(pe, std::mem::take(&mut buffers[pe]));
+            }
+        }
+    }
+    for (pe, contributions) in buffers.into_iter().enumerate() {
+        if !contributions.is_empty() {
+            send(pe, contributions);
+        }
+    }
+    world.wait_all();
+    world.barrier();
+}
+
+// the contributions read by the pull variant: the contribution of every node is in the block of
+// its owner in `shared`, at `Partitioner::aligned_index`, and the contributions of the neighbors of
+// the local nodes are copied into `buffer` with one get per pe, of the range of its block holding
+// them
+struct PullPlan {
+    shared: UnsafeArray<f64>,
+    // (index in `shared`, offset in `buffer`, length) of every get
+    gets: Vec<(usize, usize, usize)>,
+    buffer: OneSidedMemoryRegion<f64>,
+    // position in `buffer` of every neighbor of the local nodes, in the order of the nodes
+    positions: Vec<usize>,
+}
+
+impl PullPlan {
+    // collective
+    fn new(world: &LamellarWorld, graph: &Graph, nodes: &[u32]) -> PullPlan {
+        let data = graph.data();
+        let partitioner = graph.partitioner();
+        let block_len = partitioner.max_local_nodes().max(1);
+        let shared = UnsafeArray::<f64>::new(
+            world.team(),
+            graph.num_pes() * block_len,
+            Distribution::Block,
+        )
+        .block();
+        let neighbors = nodes
+            .iter()
+            .flat_map(|n| data.real_neighbors(n).iter().copied())
+            .collect::<Vec<_>>();
+        // the range of local indices of the neighbors owned by every pe
+        let mut ranges = vec![(usize::MAX, 0); graph.num_pes()];
+        for neighbor in &neighbors {
+            let range = &mut ranges[partitioner.owner(*neighbor)];
+            let index = partitioner.local_index(*neighbor);
+            *range = (range.0.min(index), range.1.max(index + 1));
+        }
+        let mut gets = vec![];
+        // offset in `buffer` of the range of every pe
+        let mut offsets = vec![0; graph.num_pes()];
+        let mut len = 0;
+        for (pe, (start, end)) in ranges.iter().enumerate() {
+            if start < end {
+                gets.push((pe * block_len + start, len, end - start));
+                offsets[pe] = len;
+                len += end - start;
+            }
+        }
+        let positions = neighbors
+            .iter()
+            .map(|n| {
+                let pe = partitioner.owner(*n);
+                offsets[pe] + partitioner.local_index(*n) - ranges[pe].0
+            })
+            .collect();
+        PullPlan {
+            shared: shared,
+            gets: gets,
+            buffer: world.alloc_one_sided_mem_region::<f64>(len.max(1)),
+            positions: positions,
+        }
+    }
+}
+
+// collective: publish the contribution of every local node in its block of `plan.shared`, then
+// read the contributions of the neighbors of every local node with one sided gets and sum them
+// into `sums`
+fn pull(
+    world: &LamellarWorld,
+    contributions: &[f64],
+    plan: &PullPlan,
+    degrees: &[usize],
+    sums: &mut [f64],
+) {
+    let local = unsafe { plan.shared.local_as_mut_slice() };
+    local[..contributions.len()].copy_from_slice(contributions);
+    world.barrier(); // every contribution is published
+    let gets = plan
+        .gets
+        .iter()
+        .map(|(index, offset, len)| {
+            let buffer = plan.buffer.sub_region(*offset..*offset + *len);
+            unsafe { plan.shared.get(*index, buffer) }.spawn()
+        })
+        .collect::<Vec<_>>();
+    world.block_on(async move {
+        for get in gets {
+            get.await;
+        }
+    });
+    world.barrier(); // everyone is done reading before the next iteration overwrites
+    let buffer = unsafe { plan.buffer.as_slice().unwrap() };
+    let mut positions = plan.positions.iter();
+    for (sum, degree) in sums.iter_mut().zip(degrees) {
+        *sum = positions.by_ref().take(*degree).map(|p| buffer[*p]).sum();
+    }
+}
+
+const USAGE: &str = "\
+usage: pagerank <graph> [--graph map|csr] [--partition <strategy>] [--variant push|pull]
+                [--damping <d>] [--tolerance <t>] [--max-iterations <n>] [--buf-size <n>]";
+
+fn main() {
+    let mut args = GraphArgs::new(USAGE);
+    let mut variant = Variant::Push;
+    let mut damping = 0.85;
+    let mut tolerance = 1e-6;
+    let mut max_iterations = 100;
+    let mut buf_size = 10000;
+    while let Some(option) = args.next_option() {
+        match option.as_str() {
+            "--variant" => variant = args.value("--variant"),
+            "--damping" => damping = args.value("--damping"),
+            "--tolerance" => tolerance = args.value("--tolerance"),
+            "--max-iterations" => max_iterations = args.value("--max-iterations"),
+            "--buf-size" => buf_size = args.value("--buf-size"),
+            _ => args.usage_error(&format!("unexpected argument {option}")),
+        }
+    }
+
+    let world = lamellar::LamellarWorldBuilder::new().build();
+    let my_pe = world.my_pe();
+    let graph = args.load(&world);
+    let num_nodes = graph.num_nodes();
+
+    let mut bench = args.benchmark(&graph);
+    bench.with_output("variant", variant.name());
+    bench.with_output("damping", damping);
+    bench.with_output("tolerance", tolerance);
+    bench.with_output("max_iterations", max_iterations);
+    if variant == Variant::Push {
+        bench.with_output("buf_size", buf_size);
+    }
+
+    let data = graph.data();
+    let nodes = graph.partitioner().local_nodes(my_pe);
+    let degrees = nodes
+        .iter()
+        .map(|n| data.real_neighbors(n).len())
+        .collect::<Vec<_>>();
+    let sums = Darc::new(&world, Mutex::new(vec![0.0; nodes.len()]))
+        .block()
+        .unwrap();
+    let plan = match variant {
+        Variant::Push => None,
+        Variant::Pull => Some(PullPlan::new(&world, &graph, &nodes)),
+    };
+
+    let mut global_sum = GlobalSum::new(&world);
+    let mut ranks = vec![1.0 / num_nodes.max(1) as f64; nodes.len()];
+    let mut iteration_times = vec![];
+    let mut residuals = vec![];
+    let mut converged = false;
+    world.barrier();
+    let timer = Instant::now();
+    while iteration_times.len() < max_iterations {
+        let iteration_timer = Instant::now();
+        // nodes without neighbors spread their rank over every node
+        let contributions = ranks
+            .iter()
+            .zip(degrees.iter())
+            .map(|(rank, degree)| {
+                if *degree == 0 {
+                    0.0
+                } else {
+                    rank / *degree as f64
+                }
+            })
+            .collect::<Vec<_>>();
+        let local_dangling: f64 = ranks
+            .iter()
+            .zip(degrees.iter())
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(rank, _)| rank)
+            .sum();
+        let dangling = global_sum.sum(&world, local_dangling);
+
+        match &plan {
+            None => {
+                sums.lock().unwrap().fill(0.0);
+                world.barrier(); // no contribution arrives before the sums are reset
+                push(&world, &graph, &nodes, &contributions, &sums, buf_size);
+            }
+            Some(plan) => {
+                let mut sums = sums.lock().unwrap();
+                pull(&world, &contributions, plan, &degrees, &mut sums);
+            }
+        }
+
+        let base = (1.0 - damping + damping * dangling) / num_nodes.max(1) as f64;
+        let sums = sums.lock().unwrap();
+        let mut local_residual = 0.0;
+        for (rank, sum) in ranks.iter_mut().zip(sums.iter()) {
+            let new_rank = base + damping * sum;
+            local_residual += (new_rank - *rank).abs();
+            *rank = new_rank;
+        }
+        drop(sums);
+        let residual = global_sum.sum(&world, local_residual);
+        let iteration_secs = iteration_timer.elapsed().as_secs_f64();
+        if my_pe == 0 {
+            println!(
+                "iteration {}: L1 residual {residual:.3e}, {iteration_secs:.6} s",
+                iteration_times.len()
+            );
+        }
+        iteration_times.push(iteration_secs);
+        residuals.push(residual);
+        if residual < tolerance {
+            converged = true;
+            break;
+        }
+    }
+    let total_secs = timer.elapsed().as_secs_f64();
+
+    // the ranks sum to 1 up to rounding, a cheap sanity check of the distributed updates
+    let rank_sum = global_sum.sum(&world, ranks.iter().sum());
+    if my_pe == 0 {
+        println!(
+            "{} after {} iterations, rank sum {rank_sum:.6}, total time: {total_secs:.6}",
+            if converged {
+                "converged"
+            } else {
+                "not converged"
+            },
+            iteration_times.len()
+        );
+    }
+    bench.with_output("iterations", iteration_times.len());
+    bench.with_output("converged", converged);
+    bench.with_output("iteration_time_secs", iteration_times);
+    bench.with_output("residuals", residuals);
+    bench.with_output("rank_sum", rank_sum);
+    bench.with_output("total_time_secs", total_secs);
+    bench.with_output("MB_sent", world.MB_sent());
+
+    if let Some(bench) = bench.aggregate(&world) {
+        bench.write(&benchmark_record::default_output_path("benchmarking"));
+    }
+}
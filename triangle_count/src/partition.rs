@@ -0,0 +1,370 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// How the nodes of a graph are assigned to the PEs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartitionStrategy {
+    /// Node `n` is owned by PE `n % num_pes`.
+    #[default]
+    Cyclic,
+    /// Contiguous ranges with the same number of nodes.
+    Block,
+    /// Nodes are assigned in decreasing degree order to the PE with the fewest edges so far.
+    DegreeBalanced,
+    /// Contiguous ranges with about the same number of edges (1D edge partitioning).
+    EdgeBalanced,
+}
+
+impl PartitionStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PartitionStrategy::Cyclic => "cyclic",
+            PartitionStrategy::Block => "block",
+            PartitionStrategy::DegreeBalanced => "degree",
+            PartitionStrategy::EdgeBalanced => "edge",
+        }
+    }
+}
+
+impl std::str::FromStr for PartitionStrategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cyclic" => Ok(PartitionStrategy::Cyclic),
+            "block" => Ok(PartitionStrategy::Block),
+            "degree" | "degree-balanced" => Ok(PartitionStrategy::DegreeBalanced),
+            "edge" | "edge-balanced" => Ok(PartitionStrategy::EdgeBalanced),
+            _ => Err(format!(
+                "unknown partitioning {s} (expected cyclic, block, degree or edge)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Layout {
+    Cyclic,
+    // pe `p` owns the nodes `starts[p]..starts[p + 1]`
+    Ranges(Vec<usize>),
+    // owner and local index of every node
+    Table { owner: Vec<u32>, index: Vec<u32> },
+}
+
+/// The owner (and index among the owner's nodes) of every node. Every PE builds the same
+/// partitioner from the global degree sequence, so ownership is known without communication.
+#[derive(Debug, Clone)]
+pub struct Partitioner {
+    strategy: PartitionStrategy,
+    num_pes: usize,
+    num_nodes: usize,
+    layout: Layout,
+    // neighbor entries (sum of the degrees) owned by each pe
+    pe_entries: Vec<usize>,
+    // nodes owned by the pe owning the most nodes
+    max_local_nodes: usize,
+}
+
+impl Partitioner {
+    /// The cyclic partitioner, before the nodes are known.
+    pub fn cyclic(num_pes: usize) -> Partitioner {
+        Partitioner {
+            strategy: PartitionStrategy::Cyclic,
+            num_pes: num_pes,
+            num_nodes: 0,
+            layout: Layout::Cyclic,
+            pe_entries: vec![0; num_pes],
+            max_local_nodes: 0,
+        }
+    }
+
+    /// Partition the nodes `0..degrees.len()` with the given strategy.
+    pub fn new(strategy: PartitionStrategy, num_pes: usize, degrees: &[u32]) -> Partitioner {
+        let num_nodes = degrees.len();
+        let total: usize = degrees.iter().map(|d| *d as usize).sum();
+        let layout_strategy = match strategy {
+            // without edges, balancing edges is balancing nodes
+            PartitionStrategy::EdgeBalanced if total == 0 => PartitionStrategy::Block,
+            strategy => strategy,
+        };
+        let layout = match layout_strategy {
+            PartitionStrategy::Cyclic => Layout::Cyclic,
+            PartitionStrategy::Block => Layout::Ranges(
+                (0..=num_pes)
+                    .map(|pe| (num_nodes * pe).div_ceil(num_pes))
+                    .collect(),
+            ),
+            PartitionStrategy::EdgeBalanced => {
+                let mut starts = vec![0; num_pes + 1];
+                let mut pe = 1;
+                let mut prefix = 0;
+                for (node, degree) in degrees.iter().enumerate() {
+                    // pe `p` starts at the first node whose prefix reaches p / num_pes of the edges
+                    while pe < num_pes && prefix * num_pes >= total * pe {
+                        starts[pe] = node;
+                        pe += 1;
+                    }
+                    prefix += *degree as usize;
+                }
+                for start in &mut starts[pe..] {
+                    *start = num_nodes;
+                }
+                Layout::Ranges(starts)
+            }
+            PartitionStrategy::DegreeBalanced => {
+                let mut order = (0..num_nodes as u32).collect::<Vec<_>>();
+                order.sort_by_key(|node| (Reverse(degrees[*node as usize]), *node));
+                // (entries, nodes, pe) of the least loaded pe first
+                let mut loads = (0..num_pes)
+                    .map(|pe| Reverse((0usize, 0usize, pe)))
+                    .collect::<BinaryHeap<_>>();
+                let mut owner = vec![0; num_nodes];
+                for node in order {
+                    let Reverse((entries, nodes, pe)) = loads.pop().unwrap();
+                    owner[node as usize] = pe as u32;
+                    let entries = entries + degrees[node as usize] as usize;
+                    loads.push(Reverse((entries, nodes + 1, pe)));
+                }
+                let mut counts = vec![0; num_pes];
+                let index = owner
+                    .iter()
+                    .map(|pe| {
+                        counts[*pe as usize] += 1;
+                        counts[*pe as usize] - 1
+                    })
+                    .collect();
+                Layout::Table {
+                    owner: owner,
+                    index: index,
+                }
+            }
+        };
+        let mut partitioner = Partitioner {
+            strategy: strategy,
+            num_pes: num_pes,
+            num_nodes: num_nodes,
+            layout: layout,
+            pe_entries: vec![0; num_pes],
+            max_local_nodes: 0,
+        };
+        let mut pe_nodes = vec![0; num_pes];
+        for (node, degree) in degrees.iter().enumerate() {
+            let pe = partitioner.owner(node as u32);
+            partitioner.pe_entries[pe] += *degree as usize;
+            pe_nodes[pe] += 1;
+        }
+        partitioner.max_local_nodes = pe_nodes.into_iter().max().unwrap_or(0);
+        partitioner
+    }
+
+    pub fn strategy(&self) -> PartitionStrategy {
+        self.strategy
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    /// Whether `node` is a node of the graph owned by `pe`.
+    pub fn owns(&self, pe: usize, node: u32) -> bool {
+        (node as usize) < self.num_nodes && self.owner(node) == pe
+    }
+
+    /// The pe owning `node`, which must be smaller than `num_nodes`.
+    pub fn owner(&self, node: u32) -> usize {
+        match &self.layout {
+            Layout::Cyclic => node as usize % self.num_pes,
+            Layout::Ranges(starts) => starts.partition_point(|start| *start <= node as usize) - 1,
+            Layout::Table { owner, .. } => owner[node as usize] as usize,
+        }
+    }
+
+    /// Position of the node among the nodes of its owner, in increasing order.
+    pub fn local_index(&self, node: u32) -> usize {
+        match &self.layout {
+            Layout::Cyclic => node as usize / self.num_pes,
+            Layout::Ranges(starts) => node as usize - starts[self.owner(node)],
+            Layout::Table { index, .. } => index[node as usize] as usize,
+        }
+    }
+
+    /// The nodes owned by `pe`, in increasing order.
+    pub fn local_nodes(&self, pe: usize) -> Vec<u32> {
+        match &self.layout {
+            Layout::Cyclic => (pe..self.num_nodes)
+                .step_by(self.num_pes)
+                .map(|n| n as u32)
+                .collect(),
+            Layout::Ranges(starts) => (starts[pe] as u32..starts[pe + 1] as u32).collect(),
+            Layout::Table { owner, .. } => (0..self.num_nodes as u32)
+                .filter(|n| owner[*n as usize] as usize == pe)
+                .collect(),
+        }
+    }
+
+    /// Number of nodes owned by the pe owning the most nodes.
+    pub fn max_local_nodes(&self) -> usize {
+        self.max_local_nodes
+    }
+
+    /// Index of `node` in an array of `num_pes * max_local_nodes` elements split in equal blocks,
+    /// whose block on every pe holds the nodes of that pe in the order of `local_nodes`.
+    pub fn aligned_index(&self, node: u32) -> usize {
+        self.owner(node) * self.max_local_nodes + self.local_index(node)
+    }
+
+    /// Neighbor entries (sum of the degrees of the owned nodes) of every pe.
+    pub fn pe_entries(&self) -> &[usize] {
+        &self.pe_entries
+    }
+
+    /// Max over mean of the neighbor entries per pe, 1.0 is perfectly balanced.
+    pub fn edge_imbalance(&self) -> f64 {
+        let total: usize = self.pe_entries.iter().sum();
+        let max = self.pe_entries.iter().max().copied().unwrap_or(0);
+        if total == 0 {
+            1.0
+        } else {
+            max as f64 * self.num_pes as f64 / total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRATEGIES: [PartitionStrategy; 4] = [
+        PartitionStrategy::Cyclic,
+        PartitionStrategy::Block,
+        PartitionStrategy::DegreeBalanced,
+        PartitionStrategy::EdgeBalanced,
+    ];
+
+    // every node has exactly one owner, and `owner`, `local_index` and `aligned_index` agree with
+    // the position of the node in `local_nodes` of its owner
+    fn assert_consistent(partitioner: &Partitioner, degrees: &[u32]) {
+        let num_pes = partitioner.pe_entries().len();
+        let mut all = vec![];
+        let mut pe_entries = vec![];
+        for pe in 0..num_pes {
+            let nodes = partitioner.local_nodes(pe);
+            assert!(nodes.windows(2).all(|w| w[0] < w[1]));
+            assert!(nodes.len() <= partitioner.max_local_nodes());
+            for (i, node) in nodes.iter().enumerate() {
+                assert_eq!(partitioner.owner(*node), pe);
+                assert!(partitioner.owns(pe, *node));
+                assert_eq!(partitioner.local_index(*node), i);
+                assert_eq!(
+                    partitioner.aligned_index(*node),
+                    pe * partitioner.max_local_nodes() + i
+                );
+            }
+            assert!(!partitioner.owns(pe, degrees.len() as u32));
+            pe_entries.push(nodes.iter().map(|n| degrees[*n as usize] as usize).sum());
+            all.extend(nodes);
+        }
+        all.sort_unstable();
+        assert_eq!(all, (0..degrees.len() as u32).collect::<Vec<_>>());
+        assert_eq!(partitioner.pe_entries(), pe_entries);
+        assert_eq!(partitioner.num_nodes(), degrees.len());
+    }
+
+    #[test]
+    fn strategies_agree_with_their_local_nodes() {
+        let degrees = [9, 5, 4, 3, 1, 1, 1, 0, 0, 0, 7, 2];
+        for strategy in STRATEGIES {
+            assert_eq!(strategy.name().parse(), Ok(strategy));
+            for num_pes in [1, 2, 3, 5] {
+                let partitioner = Partitioner::new(strategy, num_pes, &degrees);
+                assert_eq!(partitioner.strategy(), strategy);
+                assert_consistent(&partitioner, &degrees);
+            }
+        }
+        assert!("metis".parse::<PartitionStrategy>().is_err());
+    }
+
+    #[test]
+    fn cyclic_and_block_layouts() {
+        let degrees = [1; 10];
+        let cyclic = Partitioner::new(PartitionStrategy::Cyclic, 3, &degrees);
+        assert_eq!(cyclic.local_nodes(1), vec![1, 4, 7]);
+        assert_eq!(cyclic.max_local_nodes(), 4);
+        let block = Partitioner::new(PartitionStrategy::Block, 3, &degrees);
+        assert_eq!(block.local_nodes(0), vec![0, 1, 2, 3]);
+        assert_eq!(block.local_nodes(2), vec![7, 8, 9]);
+        assert_eq!(block.pe_entries(), [4, 3, 3]);
+    }
+
+    #[test]
+    fn more_pes_than_nodes() {
+        let degrees = [2, 1, 1];
+        for strategy in STRATEGIES {
+            let partitioner = Partitioner::new(strategy, 8, &degrees);
+            assert_consistent(&partitioner, &degrees);
+            assert_eq!(partitioner.max_local_nodes(), 1, "{}", strategy.name());
+            let empty = (0..8)
+                .filter(|pe| partitioner.local_nodes(*pe).is_empty())
+                .count();
+            assert_eq!(empty, 5, "{}", strategy.name());
+        }
+        // the cyclic partitioner of a graph without nodes
+        let partitioner = Partitioner::cyclic(4);
+        assert_eq!(partitioner.max_local_nodes(), 0);
+        assert!(partitioner.local_nodes(3).is_empty());
+        assert!(!partitioner.owns(0, 0));
+    }
+
+    #[test]
+    fn edge_balanced_splits_the_entries() {
+        let degrees = [3, 1, 1, 1, 1, 1, 0, 0];
+        let partitioner = Partitioner::new(PartitionStrategy::EdgeBalanced, 2, &degrees);
+        assert_eq!(partitioner.local_nodes(0), vec![0, 1]);
+        assert_eq!(partitioner.pe_entries(), [4, 4]);
+        assert_eq!(partitioner.edge_imbalance(), 1.0);
+        // a single heavy node cannot be split, the pes after it get the rest
+        let degrees = [10, 0, 1, 1];
+        let partitioner = Partitioner::new(PartitionStrategy::EdgeBalanced, 3, &degrees);
+        assert_consistent(&partitioner, &degrees);
+        assert_eq!(partitioner.local_nodes(0), vec![0]);
+    }
+
+    #[test]
+    fn edge_balanced_without_edges_is_block() {
+        let degrees = [0; 7];
+        let edge = Partitioner::new(PartitionStrategy::EdgeBalanced, 3, &degrees);
+        let block = Partitioner::new(PartitionStrategy::Block, 3, &degrees);
+        assert_eq!(edge.strategy(), PartitionStrategy::EdgeBalanced);
+        for pe in 0..3 {
+            assert_eq!(edge.local_nodes(pe), block.local_nodes(pe));
+        }
+        assert_eq!(edge.edge_imbalance(), 1.0);
+    }
+
+    #[test]
+    fn degree_balanced_breaks_ties_by_nodes_then_pe() {
+        // equal degrees are assigned in node order, equal loads to the lowest pe
+        let degrees = [1; 6];
+        let partitioner = Partitioner::new(PartitionStrategy::DegreeBalanced, 3, &degrees);
+        for pe in 0..3 {
+            assert_eq!(partitioner.local_nodes(pe), vec![pe as u32, pe as u32 + 3]);
+        }
+        // node 3 goes to pe 0, which has as many entries as pe 1 but fewer nodes
+        let degrees = [2, 1, 1, 0, 0];
+        let partitioner = Partitioner::new(PartitionStrategy::DegreeBalanced, 2, &degrees);
+        assert_eq!(partitioner.local_nodes(0), vec![0, 3, 4]);
+        assert_eq!(partitioner.local_nodes(1), vec![1, 2]);
+        assert_eq!(partitioner.local_index(4), 2);
+        assert_eq!(partitioner.pe_entries(), [2, 2]);
+    }
+
+    #[test]
+    fn edge_imbalance_is_max_over_mean() {
+        let degrees = [6, 0, 0, 0];
+        let block = Partitioner::new(PartitionStrategy::Block, 2, &degrees);
+        assert_eq!(block.edge_imbalance(), 2.0);
+        let degree = Partitioner::new(PartitionStrategy::DegreeBalanced, 2, &[3, 3, 2, 1, 1, 1]);
+        assert_eq!(degree.pe_entries(), [6, 5]);
+        assert_eq!(degree.edge_imbalance(), 12.0 / 11.0);
+        assert_eq!(Partitioner::cyclic(4).edge_imbalance(), 1.0);
+    }
+}
@@ -0,0 +1,148 @@
+// Single process triangle counting and connected components, the references the distributed
+// results are checked against. Everything here runs on one pe, so it is only meant for graphs
+// that fit in its memory.
+
+use crate::generate::Generator;
+use crate::intersect::IntersectKernel;
+
+/// The lower neighbor lists (sorted neighbors smaller than the node) of the undirected graph with
+/// the given edges, without self loops and duplicate edges.
+pub fn lower_neighbor_lists(
+    num_nodes: usize,
+    edges: impl IntoIterator<Item = (u32, u32)>,
+) -> Vec<Vec<u32>> {
+    let mut lists = vec![vec![]; num_nodes];
+    for (src, dst) in edges {
+        if src != dst {
+            let (lower, upper) = (std::cmp::min(src, dst), std::cmp::max(src, dst));
+            lists[upper as usize].push(lower);
+        }
+    }
+    for list in &mut lists {
+        list.sort_unstable();
+        list.dedup();
+    }
+    lists
+}
+
+/// The lower neighbor lists of a generated graph, with its input labels.
+pub fn generated_lower_neighbor_lists(generator: &Generator) -> Vec<Vec<u32>> {
+    let edges = (0..generator.num_chunks()).flat_map(|chunk| generator.edges(chunk));
+    lower_neighbor_lists(generator.num_nodes(), edges)
+}
+
+/// Number of triangles of the graph with the given lower neighbor lists. Every triangle
+/// `n2 < n1 < n0` is counted once, as the common lower neighbor `n2` of the edge `(n0, n1)`.
+pub fn count_triangles(lower_neighbors: &[Vec<u32>]) -> u64 {
+    lower_neighbors
+        .iter()
+        .map(|neighbors_0| {
+            neighbors_0
+                .iter()
+                .map(|n1| {
+                    IntersectKernel::Merge.count(neighbors_0, &lower_neighbors[*n1 as usize]) as u64
+                })
+                .sum::<u64>()
+        })
+        .sum()
+}
+
+/// The component of every node of the graph with the given lower neighbor lists, labeled by its
+/// smallest node (union-find with path halving).
+pub fn component_labels(lower_neighbors: &[Vec<u32>]) -> Vec<u32> {
+    let mut parents = (0..lower_neighbors.len() as u32).collect::<Vec<_>>();
+    fn find(parents: &mut [u32], mut node: u32) -> u32 {
+        while parents[node as usize] != node {
+            let grandparent = parents[parents[node as usize] as usize];
+            parents[node as usize] = grandparent;
+            node = grandparent;
+        }
+        node
+    }
+    for (node, neighbors) in lower_neighbors.iter().enumerate() {
+        for neighbor in neighbors {
+            let (root_0, root_1) = (
+                find(&mut parents, node as u32),
+                find(&mut parents, *neighbor),
+            );
+            // the smaller root stays a root, so every root is the smallest node of its component
+            let (lower, upper) = (std::cmp::min(root_0, root_1), std::cmp::max(root_0, root_1));
+            parents[upper as usize] = lower;
+        }
+    }
+    (0..lower_neighbors.len() as u32)
+        .map(|node| find(&mut parents, node))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(spec: &str) -> Vec<Vec<u32>> {
+        generated_lower_neighbor_lists(&spec.parse().unwrap())
+    }
+
+    #[test]
+    fn clique_has_n_choose_3_triangles() {
+        for n in [0u64, 1, 2, 3, 4, 10, 50] {
+            let expected = n * n.saturating_sub(1) * n.saturating_sub(2) / 6;
+            assert_eq!(
+                count_triangles(&generated(&format!("clique:n={n}"))),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn lattice_triangles() {
+        // a square grid has no triangles, splitting every cell by a diagonal makes two
+        assert_eq!(count_triangles(&generated("lattice:rows=7,cols=5")), 0);
+        assert_eq!(
+            count_triangles(&generated("lattice:rows=7,cols=5,diagonals=true")),
+            2 * 6 * 4
+        );
+        assert_eq!(
+            count_triangles(&generated("lattice:rows=1,cols=9,diagonals=true")),
+            0
+        );
+    }
+
+    #[test]
+    fn self_loops_and_duplicates_are_dropped() {
+        let edges = [(0, 1), (1, 0), (1, 2), (2, 0), (0, 2), (2, 2), (2, 3)];
+        let lists = lower_neighbor_lists(4, edges);
+        assert_eq!(lists, vec![vec![], vec![0], vec![0, 1], vec![2]]);
+        assert_eq!(count_triangles(&lists), 1);
+    }
+
+    #[test]
+    fn components_are_labeled_by_their_smallest_node() {
+        let edges = [(4, 1), (1, 6), (3, 5), (6, 4), (2, 2)];
+        let labels = component_labels(&lower_neighbor_lists(8, edges));
+        assert_eq!(labels, vec![0, 1, 2, 3, 1, 3, 1, 7]);
+        let labels = component_labels(&generated("lattice:rows=6,cols=4"));
+        assert!(labels.iter().all(|label| *label == 0));
+    }
+
+    #[test]
+    fn kernels_agree_with_the_reference() {
+        let lists = generated("rmat:scale=9,ef=8,seed=3");
+        let expected = count_triangles(&lists);
+        assert!(expected > 0);
+        for kernel in ["merge", "galloping", "hash", "bitmap", "adaptive"] {
+            let kernel: IntersectKernel = kernel.parse().unwrap();
+            let count: usize = lists
+                .iter()
+                .map(|neighbors_0| {
+                    let prepared = kernel.prepare(neighbors_0);
+                    neighbors_0
+                        .iter()
+                        .map(|n1| prepared.count(&lists[*n1 as usize]))
+                        .sum::<usize>()
+                })
+                .sum();
+            assert_eq!(count as u64, expected, "{}", kernel.name());
+        }
+    }
+}
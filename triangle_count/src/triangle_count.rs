@@ -1,20 +1,22 @@
 use lamellar::active_messaging::prelude::*;
+use lamellar::array::prelude::*;
 use lamellar::darc::prelude::*;
-use lamellar_graph::{Graph, GraphData, GraphType};
+use lamellar_graph::clustering;
+use lamellar_graph::{Graph, GraphData, GraphType, IntersectKernel, LoadOptions};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use benchmark_record;
 
 #[lamellar::AmData]
 struct CntAm {
-    final_cnt: Darc<AtomicUsize>,
+    global_cnt: Darc<AtomicUsize>,
     cnt: usize,
 }
 
 #[lamellar::am]
 impl LamellarAM for CntAm {
     async fn exec() {
-        println!("here 1");
-        self.final_cnt.fetch_add(self.cnt, Ordering::Relaxed);
+        self.global_cnt.fetch_add(self.cnt, Ordering::Relaxed);
     }
 }
 
@@ -24,6 +26,8 @@ struct LaunchAm {
     start: u32,
     end: u32,
     final_cnt: Darc<AtomicUsize>,
+    vertex_cnt: Option<AtomicArray<usize>>, // per vertex counts, if requested
+    kernel: IntersectKernel,
 }
 
 #[lamellar::local_am]
@@ -32,18 +36,36 @@ impl LamellarAM for LaunchAm {
         let task_group = LamellarTaskGroup::new(lamellar::world.clone());
         let graph_data = self.graph.data();
         for node_0 in (self.start..self.end).filter(|n| self.graph.node_is_local(n)) {
-            let _ = task_group
-                .exec_am_all(TcAm {
-                    graph: graph_data.clone(),
-                    node: node_0,
-                    neighbors: graph_data
-                        .neighbors_iter(&node_0)
-                        .take_while(|n| n < &&node_0)
-                        .map(|n| *n)
-                        .collect::<Vec<u32>>(), //only send neighbors that are less than node_0 as an optimization
-                    final_cnt: self.final_cnt.clone(),
-                })
-                .spawn();
+            let neighbors = graph_data
+                .neighbors_iter(&node_0)
+                .take_while(|n| n < &&node_0)
+                .map(|n| *n)
+                .collect::<Vec<u32>>(); //only send neighbors that are less than node_0 as an optimization
+            match &self.vertex_cnt {
+                None => {
+                    let _ = task_group
+                        .exec_am_all(TcAm {
+                            graph: graph_data.clone(),
+                            node: node_0,
+                            neighbors: neighbors,
+                            final_cnt: self.final_cnt.clone(),
+                            kernel: self.kernel,
+                        })
+                        .spawn();
+                }
+                Some(vertex_cnt) => {
+                    let _ = task_group
+                        .exec_am_all(VertexTcAm {
+                            graph: graph_data.clone(),
+                            node: node_0,
+                            neighbors: neighbors,
+                            final_cnt: self.final_cnt.clone(),
+                            vertex_cnt: vertex_cnt.clone(),
+                            kernel: self.kernel,
+                        })
+                        .spawn();
+                }
+            }
         }
         task_group.await_all().await;
     }
@@ -55,29 +77,7 @@ struct TcAm {
     node: u32,
     neighbors: Vec<u32>,
     final_cnt: Darc<AtomicUsize>,
-}
-
-impl TcAm {
-    fn sorted_intersection_count<'a>(
-        set0: impl Iterator<Item = &'a u32> + Clone,
-        mut set1: impl Iterator<Item = &'a u32> + Clone,
-    ) -> usize {
-        let mut count = 0;
-        if let Some(mut node_1) = set1.next() {
-            for node_0 in set0 {
-                while node_1 < node_0 {
-                    node_1 = match set1.next() {
-                        Some(node_1) => node_1,
-                        None => return count,
-                    };
-                }
-                if node_0 == node_1 {
-                    count += 1;
-                }
-            }
-        }
-        count
-    }
+    kernel: IntersectKernel,
 }
 
 #[lamellar::am]
@@ -85,24 +85,86 @@ impl LamellarAM for TcAm {
     async fn exec() {
         // println!("here");
         let mut cnt = 0;
+        let neighbors_0 = self.kernel.prepare(&self.neighbors);
         for node_1 in self
             .neighbors
             .iter()
             .filter(|n| self.graph.node_is_local(n))
         {
             //check to make sure node_1 is local to this pe
-            let neighs_1 = self
-                .graph
-                .neighbors_iter(node_1)
-                .take_while(|n| n < &node_1);
-            cnt += TcAm::sorted_intersection_count(self.neighbors.iter(), neighs_1);
+            cnt += neighbors_0.count(self.graph.lower_neighbors(node_1));
         }
         self.final_cnt.fetch_add(cnt, Ordering::SeqCst);
     }
 }
 
+// TcAm that also attributes every triangle to its three vertices
+#[lamellar::AmData]
+struct VertexTcAm {
+    graph: Darc<GraphData>,
+    node: u32,
+    neighbors: Vec<u32>,
+    final_cnt: Darc<AtomicUsize>,
+    vertex_cnt: AtomicArray<usize>,
+    kernel: IntersectKernel,
+}
+
+#[lamellar::am]
+impl LamellarAM for VertexTcAm {
+    async fn exec() {
+        let mut counts = HashMap::new();
+        let cnt = clustering::attribute_triangles(
+            &self.graph,
+            self.node,
+            &self.neighbors,
+            &self.kernel.prepare(&self.neighbors),
+            &mut counts,
+        );
+        self.final_cnt.fetch_add(cnt, Ordering::SeqCst);
+        clustering::add_counts(&self.vertex_cnt, self.graph.partitioner(), counts).await;
+    }
+}
+
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    // args: <graph file> [launch_threads] [--graph map|csr] [--partition <strategy>] [--kernel <kernel>]
+    //       [--save-bin <path>] [--verify] [--per-vertex <path>]
+    let mut graph_type = GraphType::MapGraph;
+    let mut options = LoadOptions::default();
+    let mut kernel = IntersectKernel::default();
+    let mut save_bin: Option<String> = None;
+    let mut verify = false;
+    let mut per_vertex: Option<String> = None;
+    let mut args: Vec<String> = vec![];
+    let mut arg_iter = std::env::args();
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--graph" => {
+                graph_type = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("{e}"))
+            }
+            "--partition" => {
+                options.partitioning = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("{e}"))
+            }
+            "--kernel" => {
+                kernel = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("{e}"))
+            }
+            "--save-bin" => save_bin = arg_iter.next(),
+            "--verify" => verify = true,
+            "--per-vertex" => per_vertex = arg_iter.next(),
+            _ => args.push(arg),
+        }
+    }
     let file = &args[1];
     let launch_threads = args
         .get(2)
@@ -117,17 +179,40 @@ fn main() {
     let num_pes = world.num_pes();
 
     let mut bench = benchmark_record::BenchmarkInformation::new();
-    bench.with_output("my_pe", my_pe.to_string());
-    bench.with_output("num_pes", num_pes.to_string());
+    bench.with_output("my_pe", my_pe);
+    bench.with_output("num_pes", num_pes);
+    bench.with_output("graph_type", graph_type.name());
+    bench.with_output("kernel", kernel.name());
 
     //this loads, reorders, and distributes the graph to all PEs
-    let graph: Graph = Graph::new(file, GraphType::MapGraph, world.clone());
-    graph.dump_to_bin(&format!("{file}.bin"));
+    let graph: Graph = match Graph::with_options(file, graph_type, options, world.clone()) {
+        Ok(graph) => graph,
+        Err(e) => {
+            // every pe gets an error, so they all exit here instead of waiting at a barrier
+            eprintln!("PE {my_pe}: unable to load graph {file}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = &save_bin {
+        // explicit conversion, load it back with `<path>` as the graph argument
+        if let Err(e) = graph.dump_to_bin(path) {
+            eprintln!("PE {my_pe}: unable to save graph to {path}: {e}");
+            std::process::exit(1);
+        }
+    }
     let final_cnt = Darc::new(&world, AtomicUsize::new(0)).block().unwrap(); // initialize our local counter (which is accessible to all PEs)
+    let global_cnt = Darc::new(&world, AtomicUsize::new(0)).block().unwrap(); // the sum of the local counters, on PE 0
+    let vertex_cnt = per_vertex
+        .as_ref()
+        .map(|_| clustering::vertex_counters(&graph)); // the triangles of every vertex, on the pe owning it
 
     if my_pe == 0 {
         println!("num nodes {:?}", graph.num_nodes())
     };
+    bench.with_output("num_nodes", graph.num_nodes());
+    bench.with_output("num_edges", graph.num_edges());
+    bench.with_output("partitioner", graph.partitioner().strategy().name());
+    bench.with_output("edge_imbalance", graph.partitioner().edge_imbalance());
 
     world.barrier();
     let timer = std::time::Instant::now();
@@ -144,6 +229,8 @@ fn main() {
             start: start,
             end: end,
             final_cnt: final_cnt.clone(),
+            vertex_cnt: vertex_cnt.clone(),
+            kernel: kernel,
         }));
     }
 
@@ -157,7 +244,7 @@ fn main() {
     if my_pe == 0 {
         let issue_secs = timer.elapsed().as_secs_f64();
         println!("issue time: {:?}", issue_secs);
-        bench.with_output("issue_time (secs)", issue_secs.to_string());
+        bench.with_output("issue_time (secs)", issue_secs);
     };
     // at this point all the triangle counting active messages have been initiated.
 
@@ -165,7 +252,7 @@ fn main() {
     if my_pe == 0 {
         let local_secs = timer.elapsed().as_secs_f64();
         println!("local time: {:?}", local_secs);
-        bench.with_output("local_time (secs)", local_secs.to_string());
+        bench.with_output("local_time (secs)", local_secs);
     };
 
     world.barrier(); //wait for all the triangle counting active messages to finish on all PEs
@@ -173,29 +260,63 @@ fn main() {
         println!("local cnt {:?}", final_cnt.load(Ordering::SeqCst))
     };
 
-    if my_pe != 0 {
-        world.block_on(world.exec_am_pe(
-            //send the local triangle counting result to the PE 0
-            0,
-            CntAm {
-                final_cnt: final_cnt.clone(),
-                cnt: final_cnt.load(Ordering::SeqCst),
-            },
-        ));
-    }
+    world.block_on(world.exec_am_pe(
+        //send the local triangle counting result to the PE 0
+        0,
+        CntAm {
+            global_cnt: global_cnt.clone(),
+            cnt: final_cnt.load(Ordering::SeqCst),
+        },
+    ));
     world.barrier(); //at this point the final triangle counting result is available on PE 0
 
     let global_secs = timer.elapsed().as_secs_f64();
-    bench.with_output("triangles_counted", (final_cnt.load(Ordering::SeqCst) as u64).to_string());
-    bench.with_output("global_time_secs", global_secs.to_string());
-
+    let local_triangles = final_cnt.load(Ordering::SeqCst) as u64;
+    let triangles = global_cnt.load(Ordering::SeqCst) as u64;
+    bench.with_output("local_triangles_counted", local_triangles);
     if my_pe == 0 {
+        bench.with_output("triangles_counted", triangles);
+    }
+    bench.with_output("global_time_secs", global_secs);
+
+    if verify {
+        // outside of the timed region, the reference count gathers the whole graph on PE 0
+        if let Some(expected) = graph.reference_triangle_count() {
+            if triangles == expected {
+                println!("verification passed: {expected} triangles");
+            } else {
+                eprintln!("verification FAILED: counted {triangles}, reference {expected}");
+            }
+            bench.with_output("reference_triangles", expected);
+            bench.with_output("verified", triangles == expected);
+        }
+    }
+
+    if let (Some(path), Some(vertex_cnt)) = (&per_vertex, &vertex_cnt) {
+        // outside of the timed region as well, every pe appends the counts of its nodes to the file
+        match clustering::vertex_statistics(&graph, vertex_cnt, Some(path)) {
+            Ok(summary) => {
+                if my_pe == 0 {
+                    println!(
+                        "per vertex counts written to {path}, global clustering coefficient {:.6}",
+                        summary.global_coefficient()
+                    );
+                }
+                bench.with_output("wedges", summary.wedges);
+                bench.with_output("global_clustering", summary.global_coefficient());
+            }
+            Err(e) => {
+                eprintln!("PE {my_pe}: unable to write per vertex counts to {path}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(bench) = bench.aggregate(&world) {
         println!(
             "triangles counted: {:?} global time: {:?}",
-            final_cnt.load(Ordering::SeqCst),
-            global_secs
+            triangles, global_secs
         );
         bench.write(&benchmark_record::default_output_path("benchmarking"));
     };
-
 }
@@ -1,7 +1,9 @@
 use lamellar::active_messaging::prelude::*;
 use lamellar::array::prelude::*;
 use lamellar::darc::prelude::*;
-use lamellar_graph::{Graph, GraphData, GraphType};
+use lamellar_graph::clustering;
+use lamellar_graph::{Graph, GraphData, GraphType, IntersectKernel, LoadOptions};
+use std::collections::HashMap;
 use std::time::Instant;
 use benchmark_record::BenchmarkInformation;
 
@@ -11,7 +13,42 @@ struct LaunchAm {
     start: u32,
     end: u32,
     final_cnt: AtomicArray<usize>, // count per-PE (one slot per PE)
+    vertex_cnt: Option<AtomicArray<usize>>, // per vertex counts, if requested
     buf_size: usize,
+    kernel: IntersectKernel,
+}
+
+impl LaunchAm {
+    fn send(
+        &self,
+        task_group: &LamellarTaskGroup,
+        graph: &Darc<GraphData>,
+        data: Vec<(u32, Vec<u32>)>,
+    ) {
+        match &self.vertex_cnt {
+            None => {
+                let _ = task_group
+                    .exec_am_all(BufferedTcAm {
+                        graph: graph.clone(),
+                        data: data,
+                        final_cnt: self.final_cnt.clone(),
+                        kernel: self.kernel,
+                    })
+                    .spawn();
+            }
+            Some(vertex_cnt) => {
+                let _ = task_group
+                    .exec_am_all(BufferedVertexTcAm {
+                        graph: graph.clone(),
+                        data: data,
+                        final_cnt: self.final_cnt.clone(),
+                        vertex_cnt: vertex_cnt.clone(),
+                        kernel: self.kernel,
+                    })
+                    .spawn();
+            }
+        }
+    }
 }
 
 #[lamellar::local_am]
@@ -31,13 +68,7 @@ impl LamellarAM for LaunchAm {
             cur_len += neighs.len();
             buffer.push((node_0, neighs)); // pack node + neighbors
             if cur_len > self.buf_size {
-                let _ = task_group
-                    .exec_am_all(BufferedTcAm {
-                        graph: graph_data.clone(),
-                        data: buffer,
-                        final_cnt: self.final_cnt.clone(),
-                    })
-                    .spawn();
+                self.send(&task_group, &graph_data, buffer);
                 buffer = vec![];
                 cur_len = 0;
             }
@@ -45,13 +76,7 @@ impl LamellarAM for LaunchAm {
 
         if cur_len > 0 {
             // send remaining
-            let _ = task_group
-                .exec_am_all(BufferedTcAm {
-                    graph: graph_data.clone(),
-                    data: buffer,
-                    final_cnt: self.final_cnt.clone(),
-                })
-                .spawn();
+            self.send(&task_group, &graph_data, buffer);
         }
 
         task_group.await_all().await;
@@ -63,29 +88,7 @@ struct BufferedTcAm {
     graph: Darc<GraphData>,
     data: Vec<(u32, Vec<u32>)>,
     final_cnt: AtomicArray<usize>,
-}
-
-impl BufferedTcAm {
-    fn sorted_intersection_count<'a>(
-        set0: impl Iterator<Item = &'a u32> + Clone,
-        mut set1: impl Iterator<Item = &'a u32> + Clone,
-    ) -> usize {
-        let mut count = 0;
-        if let Some(mut node_1) = set1.next() {
-            for node_0 in set0 {
-                while node_1 < node_0 {
-                    node_1 = match set1.next() {
-                        Some(node_1) => node_1,
-                        None => return count,
-                    };
-                }
-                if node_0 == node_1 {
-                    count += 1;
-                }
-            }
-        }
-        count
-    }
+    kernel: IntersectKernel,
 }
 
 #[lamellar::am]
@@ -93,13 +96,10 @@ impl LamellarAM for BufferedTcAm {
     async fn exec() {
         let mut cnt = 0;
         for (_node_0, neighbors) in &self.data {
+            let neighbors_0 = self.kernel.prepare(neighbors);
             // loop over neighbors that are local to this PE
             for node_1 in neighbors.iter().filter(|n| self.graph.node_is_local(n)) {
-                let neighs_1 = self
-                    .graph
-                    .neighbors_iter(node_1)
-                    .take_while(|n| n < &node_1);
-                cnt += BufferedTcAm::sorted_intersection_count(neighbors.iter(), neighs_1);
+                cnt += neighbors_0.count(self.graph.lower_neighbors(node_1));
             }
         }
         // one element per PE: update local slot
@@ -107,9 +107,76 @@ impl LamellarAM for BufferedTcAm {
     }
 }
 
+// BufferedTcAm that also attributes every triangle to its three vertices
+#[lamellar::AmData]
+struct BufferedVertexTcAm {
+    graph: Darc<GraphData>,
+    data: Vec<(u32, Vec<u32>)>,
+    final_cnt: AtomicArray<usize>,
+    vertex_cnt: AtomicArray<usize>,
+    kernel: IntersectKernel,
+}
+
+#[lamellar::am]
+impl LamellarAM for BufferedVertexTcAm {
+    async fn exec() {
+        let mut cnt = 0;
+        let mut counts = HashMap::new();
+        for (node_0, neighbors) in &self.data {
+            cnt += clustering::attribute_triangles(
+                &self.graph,
+                *node_0,
+                neighbors,
+                &self.kernel.prepare(neighbors),
+                &mut counts,
+            );
+        }
+        self.final_cnt.local_data().at(0).fetch_add(cnt);
+        clustering::add_counts(&self.vertex_cnt, self.graph.partitioner(), counts).await;
+    }
+}
+
 fn main() {
     // --- args / world -------------------------------------------------------
-    let args: Vec<String> = std::env::args().collect();
+    // args: <graph file> [launch_threads] [--graph map|csr] [--partition <strategy>] [--kernel <kernel>]
+    //       [--save-bin <path>] [--verify] [--per-vertex <path>]
+    let mut graph_type = GraphType::MapGraph;
+    let mut options = LoadOptions::default();
+    let mut kernel = IntersectKernel::default();
+    let mut save_bin: Option<String> = None;
+    let mut verify = false;
+    let mut per_vertex: Option<String> = None;
+    let mut args: Vec<String> = vec![];
+    let mut arg_iter = std::env::args();
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--graph" => {
+                graph_type = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("{e}"))
+            }
+            "--partition" => {
+                options.partitioning = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("{e}"))
+            }
+            "--kernel" => {
+                kernel = arg_iter
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("{e}"))
+            }
+            "--save-bin" => save_bin = arg_iter.next(),
+            "--verify" => verify = true,
+            "--per-vertex" => per_vertex = arg_iter.next(),
+            _ => args.push(arg),
+        }
+    }
     let file = &args[1];
     let launch_threads = args
         .get(2)
@@ -124,16 +191,41 @@ fn main() {
     let num_pes = world.num_pes();
 
     // --- graph & counters ---------------------------------------------------
-    let graph: Graph = Graph::new(file, GraphType::MapGraph, world.clone());
+    let graph: Graph = match Graph::with_options(file, graph_type, options, world.clone()) {
+        Ok(graph) => graph,
+        Err(e) => {
+            // every pe gets an error, so they all exit here instead of waiting at a barrier
+            eprintln!("PE {my_pe}: unable to load graph {file}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = &save_bin {
+        // explicit conversion, load it back with `<path>` as the graph argument
+        if let Err(e) = graph.dump_to_bin(path) {
+            eprintln!("PE {my_pe}: unable to save graph to {path}: {e}");
+            std::process::exit(1);
+        }
+    }
     let final_cnt = AtomicArray::new(world.team(), world.num_pes(), Distribution::Block).block();
+    let vertex_cnt = per_vertex
+        .as_ref()
+        .map(|_| clustering::vertex_counters(&graph));
 
     if my_pe == 0 {
         println!("num nodes {:?}", graph.num_nodes());
     }
 
+    // --- reference count (PE 0 only), computed once for all buffer sizes -----
+    let reference = if verify {
+        graph.reference_triangle_count()
+    } else {
+        None
+    };
+
     // --- per-thread batch size (same as original) ---------------------------
     let batch_size = (graph.num_nodes() as f32) / (launch_threads as f32);
     let benchmark_result_file = benchmark_record::default_output_path("benchmarking");
+    let mut per_vertex_written = false;
 
     // --- main loop over buffer sizes (preserved) ----------------------------
     for buf_size in [10usize, 100, 1000, 10000, 100000, 1000000].iter().copied() {
@@ -143,10 +235,15 @@ fn main() {
 
         // fresh record per buf_size (one JSON line per iteration)
         let mut bench = BenchmarkInformation::new();
-        bench.with_output("num_pes", num_pes.to_string());
-        bench.with_output("launch_threads", launch_threads.to_string());
-        bench.with_output("buf_size", buf_size.to_string());
-        bench.with_output("num_nodes", graph.num_nodes().to_string());
+        bench.with_output("num_pes", num_pes);
+        bench.with_output("launch_threads", launch_threads);
+        bench.with_output("buf_size", buf_size);
+        bench.with_output("num_nodes", graph.num_nodes());
+        bench.with_output("num_edges", graph.num_edges());
+        bench.with_output("graph_type", graph_type.name());
+        bench.with_output("kernel", kernel.name());
+        bench.with_output("partitioner", graph.partitioner().strategy().name());
+        bench.with_output("edge_imbalance", graph.partitioner().edge_imbalance());
 
         world.barrier();
         let timer = Instant::now();
@@ -163,7 +260,9 @@ fn main() {
                         start,
                         end,
                         final_cnt: final_cnt.clone(),
+                        vertex_cnt: vertex_cnt.clone(),
                         buf_size,
+                        kernel,
                     })
                     .spawn(),
             );
@@ -180,7 +279,7 @@ fn main() {
         if my_pe == 0 {
             println!("issue time: {:.6}", issue_secs);
         }
-        bench.with_output("issue_time_secs", format!("{:.6}", issue_secs));
+        bench.with_output("issue_time_secs", issue_secs);
 
         // wait for local completion
         world.wait_all();
@@ -188,7 +287,7 @@ fn main() {
         if my_pe == 0 {
             println!("local time: {:.6}", local_secs);
         }
-        bench.with_output("local_time_secs", format!("{:.6}", local_secs));
+        bench.with_output("local_time_secs", local_secs);
 
         // global completion
         world.barrier();
@@ -201,21 +300,48 @@ fn main() {
                 final_cnt_sum, global_secs
             );
         }
-        bench.with_output("global_time_secs", format!("{:.6}", global_secs));
+        bench.with_output("global_time_secs", global_secs);
 
         // record triangle count (or null)
-        if let Some(sum) = final_cnt_sum {
-            bench.with_output("triangles_counted", (sum as u64).to_string());
-        } else {
-            bench.with_output("triangles_counted", "<None>".into());
+        bench.with_output("triangles_counted", final_cnt_sum.map(|sum| sum as u64));
+        if let Some(expected) = reference {
+            let verified = final_cnt_sum.map(|sum| sum as u64) == Some(expected);
+            if !verified {
+                eprintln!("verification FAILED: counted {final_cnt_sum:?}, reference {expected}");
+            }
+            bench.with_output("reference_triangles", expected);
+            bench.with_output("verified", verified);
+        }
+
+        if let Some(vertex_cnt) = &vertex_cnt {
+            // every buffer size finds the same counts, only the first one writes them to the file
+            let path = per_vertex.as_deref().filter(|_| !per_vertex_written);
+            match clustering::vertex_statistics(&graph, vertex_cnt, path) {
+                Ok(summary) => {
+                    per_vertex_written = true;
+                    if let (Some(path), 0) = (path, my_pe) {
+                        println!(
+                            "per vertex counts written to {path}, global clustering coefficient {:.6}",
+                            summary.global_coefficient()
+                        );
+                    }
+                    bench.with_output("wedges", summary.wedges);
+                    bench.with_output("global_clustering", summary.global_coefficient());
+                }
+                Err(e) => {
+                    eprintln!("PE {my_pe}: unable to write per vertex counts: {e}");
+                    std::process::exit(1);
+                }
+            }
+            world.block_on(vertex_cnt.dist_iter().for_each(|x| x.store(0)));
         }
 
         // network stats
         let mb_sent = world.MB_sent();
-        bench.with_output("MB_sent",  mb_sent.to_string());
-        bench.with_output("MB_per_sec", (mb_sent / global_secs.max(1e-12)).to_string());
+        bench.with_output("MB_sent", mb_sent);
+        bench.with_output("MB_per_sec", mb_sent / global_secs.max(1e-12));
 
-        if my_pe == 0 {
+        if let Some(bench) = bench.aggregate(&world) {
             bench.write(&benchmark_result_file);
             println!("Benchmark Results for buf_size: {buf_size}");
             bench.display(Some(3));